@@ -0,0 +1,53 @@
+//! Compare the cost of spawning a restricted command using [`Rules`] and [`PreparedRules`]
+use std::{process::Command, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use leucite::{CommandExt, Rules};
+use tempdir::TempDir;
+
+/// Rules with `n` paths, like a base policy of system paths and a toolchain
+fn rules(dir: &TempDir, n: usize) -> Rules {
+    (0..n).fold(Rules::new(), |rules, i| {
+        let path = dir.path().join(format!("toolchain/{i}/lib"));
+        std::fs::create_dir_all(&path).unwrap();
+        rules.add_read_only(path)
+    })
+}
+
+fn spawn(c: &mut Criterion) {
+    let dir = TempDir::new("leucite").unwrap();
+    let work = dir.path().join("work");
+    std::fs::create_dir(&work).unwrap();
+
+    let mut group = c.benchmark_group("spawn");
+    for n in [8, 64, 512] {
+        let base = rules(&dir, n)
+            .add_read_only("/usr")
+            .add_read_only("/lib")
+            .add_read_only("/lib64");
+
+        group.bench_with_input(BenchmarkId::new("rules", n), &base, |b, base| {
+            b.iter(|| {
+                let rules = base.clone().add_read_write(&work);
+                Command::new("/usr/bin/true")
+                    .restrict(Arc::new(rules))
+                    .status()
+                    .unwrap()
+            })
+        });
+
+        let prepared = base.prepare().unwrap();
+        group.bench_with_input(BenchmarkId::new("prepared", n), &prepared, |b, prepared| {
+            b.iter(|| {
+                Command::new("/usr/bin/true")
+                    .restrict_prepared(prepared.with_working_dir(&work))
+                    .status()
+                    .unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, spawn);
+criterion_main!(benches);
@@ -0,0 +1,332 @@
+//! Capture the accesses which landlock denied to a command, using the audit subsystem
+//!
+//! Landlock logs each denied access as an audit record when the rules were applied with
+//! [`Rules::log_denials`](crate::Rules::log_denials), which requires landlock ABI 7 (Linux 6.15)
+//! and audit to be enabled (see `auditctl(8)`).  An [`AuditListener`] receives these records over
+//! netlink, which requires `CAP_AUDIT_READ`, and correlates them with a command using the landlock
+//! domain that the command created when it was restricted.
+//!
+//! ```no_run
+//! # use std::{process::Command, sync::Arc};
+//! # use leucite::{audit::AuditListener, CommandExt, Rules};
+//! let mut listener = AuditListener::new()?;
+//!
+//! let mut child = Command::new("cat")
+//!     .arg("/etc/passwd")
+//!     .restrict(Arc::new(Rules::new().add_read_only("/usr").log_denials()))
+//!     .spawn()?;
+//! child.wait()?;
+//!
+//! for denied in listener.denials_of(child.id())? {
+//!     println!("{denied}");
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::{
+    ffi::OsString,
+    fmt, io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStringExt,
+    },
+    path::PathBuf,
+};
+
+use crate::prlimit::read_errno;
+
+const NETLINK_AUDIT: libc::c_int = 9;
+/// The multicast group which receives a copy of each audit record
+const AUDIT_NLGRP_READLOG: u32 = 1;
+
+const AUDIT_GET: u16 = 1000;
+const AUDIT_LANDLOCK_ACCESS: u16 = 1423;
+const AUDIT_LANDLOCK_DOMAIN: u16 = 1424;
+
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+
+/// An access which was denied to a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeniedAccess {
+    /// The path to which access was denied, if the access was to a path
+    pub path: Option<PathBuf>,
+    /// The rights which were requested but not granted, as they are named by the kernel (e.g.,
+    /// `fs.write_file` or `net.connect_tcp`)
+    pub requested_rights: Vec<String>,
+}
+
+impl fmt::Display for DeniedAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.requested_rights.join(","))?;
+        if let Some(path) = &self.path {
+            write!(f, " on {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// A record of a landlock event, received from the audit subsystem
+#[derive(Debug)]
+enum Record {
+    /// A landlock domain was created by the process `pid`
+    Domain { domain: String, pid: u32 },
+    /// Access was denied in a domain
+    Access {
+        domain: String,
+        denied: DeniedAccess,
+    },
+}
+
+/// Receives the audit records of denied accesses, see the [module documentation](self)
+#[derive(Debug)]
+pub struct AuditListener {
+    fd: OwnedFd,
+    records: Vec<Record>,
+}
+
+impl AuditListener {
+    /// Start listening for audit records
+    ///
+    /// The listener must be created before the commands that it will report on are spawned.  Fails
+    /// with [`io::ErrorKind::Unsupported`] if audit is disabled, and with
+    /// [`io::ErrorKind::PermissionDenied`] without `CAP_AUDIT_READ`.
+    pub fn new() -> io::Result<Self> {
+        // SAFETY: `socket` has no memory safety requirements and any error is reported through
+        // `errno`
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                NETLINK_AUDIT,
+            )
+        };
+        if fd < 0 {
+            return Err(read_errno());
+        }
+        // SAFETY: `fd` was just opened, and is owned by nothing else
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        // SAFETY: `sockaddr_nl` is valid when zeroed
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as _;
+        addr.nl_groups = 1 << (AUDIT_NLGRP_READLOG - 1);
+        // SAFETY: `addr` is a valid `sockaddr_nl` of the given size
+        let ret = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                (&addr as *const libc::sockaddr_nl).cast(),
+                mem::size_of::<libc::sockaddr_nl>() as _,
+            )
+        };
+        if ret != 0 {
+            return Err(read_errno());
+        }
+
+        let listener = Self {
+            fd,
+            records: Vec::new(),
+        };
+        if listener.audit_enabled()? == Some(false) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "audit is not enabled",
+            ));
+        }
+        Ok(listener)
+    }
+
+    /// Query whether audit is enabled, or `None` if that is not allowed
+    fn audit_enabled(&self) -> io::Result<Option<bool>> {
+        let mut request = [0u8; NLMSG_HDRLEN];
+        request[0..4].copy_from_slice(&(NLMSG_HDRLEN as u32).to_ne_bytes());
+        request[4..6].copy_from_slice(&AUDIT_GET.to_ne_bytes());
+        request[6..8].copy_from_slice(&(libc::NLM_F_REQUEST as u16).to_ne_bytes());
+        // SAFETY: `request` is valid for its length
+        let ret = unsafe {
+            libc::send(
+                self.fd.as_raw_fd(),
+                request.as_ptr().cast(),
+                request.len(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(read_errno());
+        }
+
+        // The reply may be preceded by records which were already received
+        let mut buf = vec![0u8; 8192];
+        loop {
+            // SAFETY: `buf` is valid for its length
+            let len =
+                unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if len < 0 {
+                let err = read_errno();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    // SAFETY: `poll` is given a single valid `pollfd`
+                    let mut pfd = libc::pollfd {
+                        fd: self.fd.as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    };
+                    if unsafe { libc::poll(&mut pfd, 1, 1000) } <= 0 {
+                        return Ok(None);
+                    }
+                    continue;
+                }
+                return Err(err);
+            }
+
+            for (ty, payload) in messages(&buf[..len as usize]) {
+                match ty {
+                    // `audit_status.enabled` is the second field
+                    AUDIT_GET => {
+                        let enabled = payload.get(4..8).ok_or_else(|| {
+                            io::Error::new(io::ErrorKind::InvalidData, "truncated audit status")
+                        })?;
+                        return Ok(Some(u32::from_ne_bytes(enabled.try_into().unwrap()) != 0));
+                    }
+                    // Querying the status requires `CAP_AUDIT_CONTROL`
+                    NLMSG_ERROR if payload.get(..4).is_some_and(|e| e != [0; 4]) => {
+                        return Ok(None)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Receive each of the records which have been sent since the last call
+    fn receive(&mut self) -> io::Result<()> {
+        let mut buf = vec![0u8; 8192];
+        loop {
+            // SAFETY: `buf` is valid for its length
+            let len =
+                unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            if len < 0 {
+                let err = read_errno();
+                if err.kind() == io::ErrorKind::WouldBlock {
+                    return Ok(());
+                }
+                return Err(err);
+            }
+
+            for (ty, payload) in messages(&buf[..len as usize]) {
+                let text = String::from_utf8_lossy(payload);
+                if let Some(record) = parse_record(ty, &text) {
+                    self.records.push(record);
+                }
+            }
+        }
+    }
+
+    /// Get the accesses which were denied to the command with the process id `pid`, and any of
+    /// its threads or children which did not restrict themselves further
+    ///
+    /// This should be called after the command has exited.  Records which were received for
+    /// other commands are kept, so that they can be retrieved later.  Records may be dropped by
+    /// the kernel if they are produced faster than they are received, so the list may not be
+    /// complete.
+    pub fn denials_of(&mut self, pid: u32) -> io::Result<Vec<DeniedAccess>> {
+        self.receive()?;
+
+        let domains: Vec<String> = (self.records.iter())
+            .filter_map(|r| match r {
+                Record::Domain { domain, pid: p } if *p == pid => Some(domain.clone()),
+                _ => None,
+            })
+            .collect();
+
+        Ok((self.records.iter())
+            .filter_map(|r| match r {
+                Record::Access { domain, denied } if domains.contains(domain) => {
+                    Some(denied.clone())
+                }
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// Split a buffer that was received from netlink into the type and payload of each message
+fn messages(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let len = u32::from_ne_bytes(buf.get(0..4)?.try_into().ok()?) as usize;
+        let ty = u16::from_ne_bytes(buf.get(4..6)?.try_into().ok()?);
+        let payload = buf.get(NLMSG_HDRLEN..len)?;
+        // Messages are aligned to 4 bytes
+        buf = buf.get((len + 3) & !3..).unwrap_or_default();
+        Some((ty, payload))
+    })
+}
+
+/// Parse the text of an audit record, such as
+/// `audit(1.2:3): domain=1a2b blockers=fs.write_file path="/tmp/x" dev="vda" ino=1`
+fn parse_record(ty: u16, text: &str) -> Option<Record> {
+    let (_, fields) = text.split_once("): ")?;
+    let fields = fields_of(fields);
+    let field = |key: &str| fields.iter().find(|(k, _)| *k == key).map(|(_, v)| v);
+
+    match ty {
+        AUDIT_LANDLOCK_DOMAIN => Some(Record::Domain {
+            domain: String::from_utf8_lossy(field("domain")?).into_owned(),
+            pid: String::from_utf8_lossy(field("pid")?).parse().ok()?,
+        }),
+        AUDIT_LANDLOCK_ACCESS => Some(Record::Access {
+            domain: String::from_utf8_lossy(field("domain")?).into_owned(),
+            denied: DeniedAccess {
+                path: field("path").map(|p| OsString::from_vec(p.clone()).into()),
+                requested_rights: String::from_utf8_lossy(field("blockers")?)
+                    .split(',')
+                    .map(String::from)
+                    .collect(),
+            },
+        }),
+        _ => None,
+    }
+}
+
+/// Split the `key=value` fields of an audit record
+///
+/// Values which are quoted are taken verbatim, while the kernel encodes values which may contain
+/// special characters (like paths) as unquoted hex.
+fn fields_of(mut text: &str) -> Vec<(&str, Vec<u8>)> {
+    let mut fields = Vec::new();
+    while let Some((key, rest)) = text.trim_start().split_once('=') {
+        let (value, rest) = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            (
+                quoted.as_bytes()[..end].to_vec(),
+                quoted.get(end + 1..).unwrap_or(""),
+            )
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let value = &rest[..end];
+            let value = match key {
+                "path" | "exe" | "comm" => decode_hex(value),
+                _ => None,
+            };
+            (
+                value.unwrap_or_else(|| rest.as_bytes()[..end].to_vec()),
+                &rest[end..],
+            )
+        };
+        fields.push((key, value));
+        text = rest;
+    }
+    fields
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    (s.as_bytes().chunks(2))
+        .map(|pair| match pair {
+            [hi, lo] => Some((hex_digit(*hi)? << 4) | hex_digit(*lo)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|d| d as u8)
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+use landlock::{AccessFs, BitFlags, ABI};
+
+use crate::{Error, FsType, PermissivePolicy, Rules};
+
+/// A description of [`Rules`] which is only validated once it is built, see [`Rules::builder`]
+///
+/// The methods to describe the rules are the same as those of [`Rules`], which never fail and
+/// defer any errors until the rules are applied.  [`RulesBuilder::build`] instead resolves and
+/// checks the whole policy up front, so that a misconfigured policy fails fast, e.g., when a
+/// service starts rather than when it spawns its first command.
+///
+/// ```no_run
+/// # use leucite::Rules;
+/// let rules = Rules::builder()
+///     .add_read_only("/usr")
+///     .add_read_write("/tmp/work")
+///     .add_connect_port(443)
+///     .build()?;
+/// # Ok::<_, leucite::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RulesBuilder(Rules);
+
+/// Forward each method to the method of [`Rules`] with the same name
+macro_rules! forward {
+    ($(fn $name: ident($($arg: ident: $ty: ty),*);)+) => {
+        $(
+            #[doc = concat!("See [`Rules::", stringify!($name), "`]")]
+            pub fn $name(self, $($arg: $ty),*) -> Self {
+                Self(self.0.$name($($arg),*))
+            }
+        )+
+    };
+}
+
+impl Rules {
+    /// Create a [`RulesBuilder`], which validates the rules when they are built
+    pub fn builder() -> RulesBuilder {
+        RulesBuilder::default()
+    }
+}
+
+impl From<Rules> for RulesBuilder {
+    fn from(rules: Rules) -> Self {
+        Self(rules)
+    }
+}
+
+impl RulesBuilder {
+    forward! {
+        fn add_read_only(p: impl Into<PathBuf>);
+        fn add_read_execute(p: impl Into<PathBuf>);
+        fn add_read_write(p: impl Into<PathBuf>);
+        fn add_write_only(p: impl Into<PathBuf>);
+        fn add_path_access(p: impl Into<PathBuf>, access: impl Into<BitFlags<AccessFs>>);
+        fn add_read_only_file(p: impl Into<PathBuf>);
+        fn add_read_write_file(p: impl Into<PathBuf>);
+        fn add_refer(p: impl Into<PathBuf>);
+        fn with_dynamic_linker();
+        fn add_connect_port(p: u16);
+        fn add_connect_port_any_host(p: u16);
+        fn add_bind_port(p: u16);
+        fn add_connect_port_range(range: RangeInclusive<u16>);
+        fn add_bind_port_range(range: RangeInclusive<u16>);
+        fn remove_read_only(p: impl AsRef<Path>);
+        fn remove_read_execute(p: impl AsRef<Path>);
+        fn remove_read_write(p: impl AsRef<Path>);
+        fn remove_write_only(p: impl AsRef<Path>);
+        fn remove_path_access(p: impl AsRef<Path>);
+        fn remove_connect_port(p: u16);
+        fn remove_bind_port(p: u16);
+        fn client_only();
+        fn server_only(bind_ports: impl IntoIterator<Item = u16>);
+        fn allow_all_network();
+        fn deny_all_network();
+        fn allow_all_bind();
+        fn allow_all_connect();
+        fn merge(other: Rules);
+        fn merge_beneath(other: Rules);
+        fn intersect(other: Rules);
+        fn with_abi(abi: ABI);
+        fn read_access_bits(access: impl Into<BitFlags<AccessFs>>);
+        fn write_access_bits(access: impl Into<BitFlags<AccessFs>>);
+        fn allow_tmp_execution(allow: bool);
+        fn audit_only();
+        fn log_denials();
+        fn scope_abstract_unix_sockets();
+        fn scope_signals();
+        fn on_fully_permissive(policy: PermissivePolicy);
+        fn deny_filesystem_types(types: &[FsType]);
+    }
+
+    /// See [`Rules::add_read_only_with_writable`]
+    pub fn add_read_only_with_writable(
+        self,
+        root: impl Into<PathBuf>,
+        writable_subpaths: &[PathBuf],
+    ) -> Result<Self, Error> {
+        Ok(Self(
+            self.0
+                .add_read_only_with_writable(root, writable_subpaths)?,
+        ))
+    }
+
+    /// Validate the described rules, and build them
+    ///
+    /// Each path is canonicalized (see [`Rules::canonicalize`]), so that the rules are expressed
+    /// in the resolved form which landlock enforces, and then:
+    ///
+    /// - fails with [`Error::Canonicalize`] if a path does not exist or can not be resolved
+    /// - fails with [`Error::DeniedFilesystem`] if a path resides on a denied filesystem, see
+    ///   [`Rules::deny_filesystem_types`]
+    /// - fails with [`Error::FullyPermissive`] if the rules grant full access and
+    ///   [`PermissivePolicy::Error`] is set, see [`Rules::on_fully_permissive`]
+    ///
+    /// This reads the filesystem, so the result only reflects the filesystem at the time it was
+    /// built.
+    pub fn build(self) -> Result<Rules, Error> {
+        let mut rules = self.0;
+        for path in rules.paths_mut() {
+            *path = fs::canonicalize(&*path).map_err(|source| Error::Canonicalize {
+                path: path.clone(),
+                source,
+            })?;
+        }
+        rules.check_filesystem_types()?;
+        if rules.permissive_policy == PermissivePolicy::Error && rules.is_fully_permissive() {
+            return Err(Error::FullyPermissive);
+        }
+        Ok(rules)
+    }
+}
@@ -0,0 +1,89 @@
+//! Discovery of the cgroup v2 hierarchy, which is done in the parent so that the child only needs
+//! to read and write files in fixed-size buffers.
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io,
+    os::{fd::OwnedFd, unix::ffi::OsStrExt},
+    time::Duration,
+};
+
+/// Everything which the child needs to set `cpu.max` on its own cgroup
+///
+/// The directories are opened in the parent, so that they stay reachable from the child after it
+/// changed its root directory.
+#[derive(Debug)]
+pub(crate) struct CpuMax {
+    /// The `/proc` directory, used to find the cgroup of the child
+    pub(crate) proc: OwnedFd,
+    /// The mount point of the cgroup v2 hierarchy
+    pub(crate) mount: OwnedFd,
+    /// The cgroup of the parent, relative to `mount`
+    pub(crate) parent: Vec<u8>,
+    /// The value to write to `cpu.max`
+    pub(crate) value: Vec<u8>,
+}
+
+impl CpuMax {
+    /// Look up the cgroup v2 hierarchy of the current process and format the `cpu.max` value for
+    /// `quota` microseconds of CPU time per `period`
+    pub(crate) fn new(quota: Duration, period: Duration) -> io::Result<Self> {
+        Ok(Self {
+            proc: File::open("/proc")?.into(),
+            mount: File::open(OsStr::from_bytes(&cgroup2_mount()?))?.into(),
+            parent: own_cgroup(&fs::read("/proc/self/cgroup")?)
+                .ok_or(io::ErrorKind::Unsupported)?
+                .to_vec(),
+            value: format!("{} {}\n", quota.as_micros(), period.as_micros()).into_bytes(),
+        })
+    }
+}
+
+/// Find the mount point of the cgroup v2 hierarchy from `/proc/self/mountinfo`
+fn cgroup2_mount() -> io::Result<Vec<u8>> {
+    let mountinfo = fs::read("/proc/self/mountinfo")?;
+    mountinfo
+        .split(|&b| b == b'\n')
+        .find_map(|line| {
+            // The optional fields are terminated by a single `-`, followed by the filesystem type
+            let mut fields = line.split(|&b| b == b' ');
+            let mount = fields.nth(4)?;
+            let fs_type = fields.skip_while(|&f| f != b"-").nth(1)?;
+            (fs_type == b"cgroup2").then(|| unescape(mount))
+        })
+        .ok_or_else(|| io::ErrorKind::Unsupported.into())
+}
+
+/// Undo the octal escaping of whitespace and backslashes in `/proc/self/mountinfo`
+fn unescape(field: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        let octal = field
+            .get(i + 1..i + 4)
+            .filter(|o| o.iter().all(|d| (b'0'..=b'7').contains(d)));
+        match (field[i], octal) {
+            (b'\\', Some(o)) => {
+                out.push(
+                    o.iter()
+                        .fold(0u8, |n, d| n.wrapping_mul(8).wrapping_add(d - b'0')),
+                );
+                i += 4;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Get the path of the cgroup v2 hierarchy from the contents of `/proc/<pid>/cgroup`
+///
+/// This does not allocate, so that it can be used from the child.
+pub(crate) fn own_cgroup(contents: &[u8]) -> Option<&[u8]> {
+    contents
+        .split(|&b| b == b'\n')
+        .find_map(|line| line.strip_prefix(b"0::"))
+}
@@ -0,0 +1,307 @@
+//! Reporting why a command could not be restricted back to the parent
+//!
+//! A `pre_exec` hook can only fail with an [`io::Error`], of which only the OS error code is passed
+//! to the parent, so the [`Error`] is lost.  Instead, the child writes a fixed-size record of the
+//! error to a pipe before failing, which refers to the path of the error by its position in the
+//! rules, so that nothing needs to be allocated to report it.
+use std::{
+    error::Error as _,
+    fmt, io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    path::{Path, PathBuf},
+};
+
+use landlock::AccessNet;
+
+use crate::{prlimit::read_errno, Error, FsType, Rules};
+
+/// The kind of an [`Error`], without any of its data, see [`Error::kind`]
+///
+/// More kinds may be added along with new variants of [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RestrictErrorKind {
+    /// See [`Error::AccessFs`]
+    AccessFs,
+    /// See [`Error::AcessNet`]
+    AccessNet,
+    /// See [`Error::CreateRuleset`]
+    CreateRuleset,
+    /// See [`Error::SetBindPorts`]
+    SetBindPorts,
+    /// See [`Error::SetConnectPorts`]
+    SetConnectPorts,
+    /// See [`Error::Scope`]
+    Scope,
+    /// See [`Error::RestrictThread`]
+    RestrictThread,
+    /// See [`Error::LandlockNotSupported`]
+    LandlockNotSupported,
+    /// See [`Error::InvalidPath`]
+    InvalidPath,
+    /// See [`Error::DeniedFilesystem`]
+    DeniedFilesystem,
+    /// See [`Error::RestrictLogging`]
+    RestrictLogging,
+    /// See [`Error::FullyPermissive`]
+    FullyPermissive,
+    /// See [`Error::AddPathRule`]
+    AddPathRule,
+    /// See [`Error::AddPortRule`]
+    AddPortRule,
+    /// See [`Error::RecursiveDirectory`]
+    RecursiveDirectory,
+    /// See [`Error::Canonicalize`]
+    Canonicalize,
+    /// See [`Error::NotBeneathRoot`]
+    NotBeneathRoot,
+}
+
+impl RestrictErrorKind {
+    const ALL: [Self; 17] = [
+        Self::AccessFs,
+        Self::AccessNet,
+        Self::CreateRuleset,
+        Self::SetBindPorts,
+        Self::SetConnectPorts,
+        Self::Scope,
+        Self::RestrictThread,
+        Self::LandlockNotSupported,
+        Self::InvalidPath,
+        Self::DeniedFilesystem,
+        Self::RestrictLogging,
+        Self::FullyPermissive,
+        Self::AddPathRule,
+        Self::AddPortRule,
+        Self::RecursiveDirectory,
+        Self::Canonicalize,
+        Self::NotBeneathRoot,
+    ];
+}
+
+impl fmt::Display for RestrictErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::AccessFs => "setting filesystem access",
+            Self::AccessNet => "setting network access",
+            Self::CreateRuleset => "creating ruleset",
+            Self::SetBindPorts => "setting bind ports",
+            Self::SetConnectPorts => "setting connect ports",
+            Self::Scope => "scoping ruleset",
+            Self::RestrictThread => "restricting current thread",
+            Self::LandlockNotSupported => "installed kernel does not support landlock",
+            Self::InvalidPath => "opening path",
+            Self::DeniedFilesystem => "path resides on a denied filesystem",
+            Self::RestrictLogging => "restricting current thread with logging",
+            Self::FullyPermissive => "rules grant full access, so restricting would have no effect",
+            Self::AddPathRule => "granting access to path",
+            Self::AddPortRule => "allowing access to port",
+            Self::RecursiveDirectory => {
+                "path is a directory, which can only be granted recursively"
+            }
+            Self::Canonicalize => "canonicalizing path",
+            Self::NotBeneathRoot => "path is not beneath the root",
+        })
+    }
+}
+
+/// Why a command could not be restricted, as reported by the child, see
+/// [`CommandExt::spawn_restricted`](crate::CommandExt::spawn_restricted)
+///
+/// This carries the same information as the [`Error`] which occurred in the child, except that
+/// the landlock error which caused it is reduced to its OS error.
+#[derive(Debug)]
+pub struct ChildError {
+    kind: RestrictErrorKind,
+    path: Option<PathBuf>,
+    port: Option<(u16, AccessNet)>,
+    fs_type: Option<FsType>,
+    source: Option<io::Error>,
+}
+
+impl ChildError {
+    /// Get the [`ChildError`] which is wrapped by `error`, if there is one
+    pub fn of(error: &io::Error) -> Option<&Self> {
+        error.get_ref()?.downcast_ref()
+    }
+
+    /// The kind of the error which occurred in the child
+    pub fn kind(&self) -> RestrictErrorKind {
+        self.kind
+    }
+
+    /// The path which caused the error, e.g., for [`RestrictErrorKind::DeniedFilesystem`]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The port and the access to it which caused the error, for [`RestrictErrorKind::AddPortRule`]
+    pub fn port(&self) -> Option<(u16, AccessNet)> {
+        self.port
+    }
+
+    /// The type of the filesystem which is denied, for [`RestrictErrorKind::DeniedFilesystem`]
+    pub fn fs_type(&self) -> Option<FsType> {
+        self.fs_type
+    }
+}
+
+impl fmt::Display for ChildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(path) = &self.path {
+            write!(f, " {}", path.display())?;
+        }
+        if let Some((port, access)) = self.port {
+            write!(f, " {port} ({access:?})")?;
+        }
+        if let Some(fs_type) = self.fs_type {
+            write!(f, " ({:#x})", fs_type.magic())?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ": {source}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ChildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| e as _)
+    }
+}
+
+/// The record of an [`Error`] which is written by the child:
+///
+/// | bytes    | field                                                  |
+/// |----------|--------------------------------------------------------|
+/// | `0`      | the [`RestrictErrorKind`]                              |
+/// | `1`      | the access to the port: `0` for none, `1` for bind, `2` for connect |
+/// | `2..4`   | the port                                               |
+/// | `4..8`   | the OS error, or `0`                                   |
+/// | `8..12`  | the position of the path in [`paths`], or `u32::MAX`   |
+/// | `12..16` | the magic number of the filesystem type, or `0`        |
+type Record = [u8; 16];
+
+/// A pipe through which the child reports why it could not be restricted
+///
+/// Both ends are kept open until the command is dropped, so that a command which is spawned more
+/// than once never writes to a closed pipe.  Both ends are closed on `exec`.
+#[derive(Debug)]
+pub(crate) struct ErrorPipe {
+    read: OwnedFd,
+    write: OwnedFd,
+}
+
+impl ErrorPipe {
+    pub(crate) fn new() -> io::Result<Self> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` is valid for two file descriptors and any error is reported through `errno`
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } != 0 {
+            return Err(read_errno());
+        }
+        // SAFETY: Both file descriptors were just opened and are not owned by anything else
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        Ok(Self { read, write })
+    }
+
+    /// Report `error` from the child, which occurred when restricting it using `rules`
+    ///
+    /// This does not allocate.  Failing to report is ignored, since the command fails either way.
+    pub(crate) fn report(&self, rules: &Rules, error: &Error) {
+        let record = encode(rules, error);
+        // SAFETY: `record` is valid for its length, and `write` is open
+        unsafe { libc::write(self.write.as_raw_fd(), record.as_ptr().cast(), record.len()) };
+    }
+
+    /// Take the error which was reported by the child, which was restricted using `rules`
+    pub(crate) fn take(&self, rules: &Rules) -> Option<ChildError> {
+        let mut record: Record = [0; 16];
+        // SAFETY: `record` is valid for its length, and `read` is open
+        let ret = unsafe {
+            libc::read(
+                self.read.as_raw_fd(),
+                record.as_mut_ptr().cast(),
+                record.len(),
+            )
+        };
+        (ret == record.len() as isize)
+            .then(|| decode(rules, record))
+            .flatten()
+    }
+}
+
+/// Every path of `rules`, in the order that [`Rules::check_filesystem_types`] checks them
+fn paths(rules: &Rules) -> impl Iterator<Item = &Path> {
+    rules
+        .read_only
+        .iter()
+        .chain(&rules.read_execute)
+        .chain(&rules.read_write)
+        .chain(&rules.write_only)
+        .chain(rules.path_access.iter().map(|(path, _)| path))
+        .map(PathBuf::as_path)
+}
+
+fn encode(rules: &Rules, error: &Error) -> Record {
+    let mut errno = 0;
+    let mut source = error.source();
+    while let Some(e) = source {
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            errno = e.raw_os_error().unwrap_or(0);
+            break;
+        }
+        source = e.source();
+    }
+
+    let (path, fs_type, port) = match error {
+        Error::InvalidPath { path, .. }
+        | Error::AddPathRule { path, .. }
+        | Error::RecursiveDirectory { path }
+        | Error::Canonicalize { path, .. } => (Some(path), 0, None),
+        Error::DeniedFilesystem { path, fs_type } => (Some(path), fs_type.magic(), None),
+        Error::AddPortRule { port, access, .. } => (None, 0, Some((*port, *access))),
+        _ => (None, 0, None),
+    };
+    let index = path
+        .and_then(|path| paths(rules).position(|p| p == path))
+        .and_then(|i| u32::try_from(i).ok())
+        .unwrap_or(u32::MAX);
+    let (port, access) = match port {
+        Some((port, AccessNet::BindTcp)) => (port, 1),
+        Some((port, _)) => (port, 2),
+        None => (0, 0),
+    };
+
+    let mut record: Record = [0; 16];
+    record[0] = error.kind() as u8;
+    record[1] = access;
+    record[2..4].copy_from_slice(&port.to_ne_bytes());
+    record[4..8].copy_from_slice(&errno.to_ne_bytes());
+    record[8..12].copy_from_slice(&index.to_ne_bytes());
+    record[12..16].copy_from_slice(&fs_type.to_ne_bytes());
+    record
+}
+
+fn decode(rules: &Rules, record: Record) -> Option<ChildError> {
+    let field = |i: usize| <[u8; 4]>::try_from(&record[i..i + 4]).unwrap_or_default();
+    let port = u16::from_ne_bytes([record[2], record[3]]);
+    let errno = i32::from_ne_bytes(field(4));
+    let index = u32::from_ne_bytes(field(8));
+    let fs_type = u32::from_ne_bytes(field(12));
+
+    Some(ChildError {
+        kind: *RestrictErrorKind::ALL.get(usize::from(record[0]))?,
+        path: usize::try_from(index)
+            .ok()
+            .and_then(|i| paths(rules).nth(i))
+            .map(Path::to_path_buf),
+        port: match record[1] {
+            1 => Some((port, AccessNet::BindTcp)),
+            2 => Some((port, AccessNet::ConnectTcp)),
+            _ => None,
+        },
+        fs_type: (fs_type != 0).then_some(FsType::from_magic(fs_type)),
+        source: (errno != 0).then(|| io::Error::from_raw_os_error(errno)),
+    })
+}
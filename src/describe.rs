@@ -0,0 +1,270 @@
+use std::{fmt, path::PathBuf};
+
+use landlock::{Access, AccessFs, AccessNet, BitFlags, ABI};
+
+use crate::{json::ACCESS_NAMES, kernel_abi_version, Ports, Rules};
+
+/// A structured summary of what a set of [`Rules`] would enforce on the running kernel, see
+/// [`Rules::describe`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct RulesDescription {
+    /// Whether the rules would be enforced, i.e., they are not [audit only](Rules::audit_only)
+    pub enforced: bool,
+    /// Paths added by [`Rules::add_read_only`], which are granted [`RulesDescription::read_access`]
+    pub read_only: Vec<PathBuf>,
+    /// Paths added by [`Rules::add_read_execute`]
+    pub read_execute: Vec<PathBuf>,
+    /// Paths added by [`Rules::add_read_write`], which are granted both
+    /// [`RulesDescription::read_access`] and [`RulesDescription::write_access`]
+    pub read_write: Vec<PathBuf>,
+    /// Paths added by [`Rules::add_write_only`], which are granted
+    /// [`RulesDescription::write_access`]
+    pub write_only: Vec<PathBuf>,
+    /// Paths added by [`Rules::add_path_access`], with the rights that are granted on each
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serde_support::path_access::serialize")
+    )]
+    pub path_access: Vec<(PathBuf, BitFlags<AccessFs>)>,
+    /// The rights which are granted for "read" access, see [`Rules::read_access`]
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serde_support::serialize_access")
+    )]
+    pub read_access: BitFlags<AccessFs>,
+    /// The rights which are granted for "write" access, see [`Rules::write_access`].  This does
+    /// not include `Execute` unless [`Rules::allows_tmp_execution`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serde_support::serialize_access")
+    )]
+    pub write_access: BitFlags<AccessFs>,
+    /// TCP ports to which the command may bind, or `None` if binding is not restricted
+    pub bind_ports: Option<Vec<u16>>,
+    /// TCP ports to which the command may connect, or `None` if connecting is not restricted
+    pub connect_ports: Option<Vec<u16>>,
+    /// Whether connecting to abstract UNIX sockets outside of the sandbox is denied, see
+    /// [`Rules::scope_abstract_unix_sockets`]
+    pub scope_abstract_unix_sockets: bool,
+    /// Whether sending signals to processes outside of the sandbox is denied, see
+    /// [`Rules::scope_signals`]
+    pub scope_signals: bool,
+    /// The landlock ABI which is requested, see [`Rules::with_abi`]
+    pub requested_abi: i32,
+    /// The landlock ABI which would be enforced on the running kernel, see
+    /// [`Rules::effective_abi`]
+    pub effective_abi: i32,
+    /// The highest landlock ABI which is supported by the running kernel, or `None` if it does
+    /// not support landlock.  This may be higher than the ABIs which are known to [`ABI`].
+    pub kernel_abi: Option<i32>,
+    /// Filesystem rights which are handled by the requested ABI, but which the running kernel
+    /// does not support, and so are always allowed
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serde_support::serialize_access")
+    )]
+    pub unsupported_access: BitFlags<AccessFs>,
+    /// Whether the running kernel supports restricting TCP ports (ABI 4 or later)
+    pub network_supported: bool,
+}
+
+impl Rules {
+    /// Describe what these rules would enforce on the running kernel, without restricting
+    /// anything
+    ///
+    /// This is intended for a `--dry-run` flag, to print the effective policy before calling the
+    /// irreversible [`Rules::restrict_self`].  The only syscall which is made is the query for the
+    /// ABI supported by the kernel.  Unlike [`Rules::exposure_report`], which combines the paths by
+    /// what they expose, this keeps the paths grouped by how they were added.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").add_connect_port(443);
+    /// let description = rules.describe();
+    ///
+    /// assert_eq!(description.read_only, vec![std::path::PathBuf::from("/usr")]);
+    /// assert_eq!(description.connect_ports, Some(vec![443]));
+    /// println!("{description}");
+    /// ```
+    pub fn describe(&self) -> RulesDescription {
+        let version = kernel_abi_version();
+        let effective = self.effective_abi();
+
+        RulesDescription {
+            enforced: !self.audit_only,
+            read_only: self.read_only.clone(),
+            read_execute: self.read_execute.clone(),
+            read_write: self.read_write.clone(),
+            write_only: self.write_only.clone(),
+            path_access: self
+                .handled_path_access()
+                .map(|(path, access)| (path.to_path_buf(), access))
+                .collect(),
+            read_access: self.read_access(),
+            write_access: self.writable_access(self.write_access()),
+            bind_ports: self.ports(AccessNet::BindTcp).allowed(),
+            connect_ports: self.ports(AccessNet::ConnectTcp).allowed(),
+            scope_abstract_unix_sockets: self.scope_abstract_unix_sockets,
+            scope_signals: self.scope_signals,
+            requested_abi: self.abi() as i32,
+            effective_abi: effective as i32,
+            kernel_abi: (version > 0).then_some(version),
+            unsupported_access: AccessFs::from_all(self.abi()) & !AccessFs::from_all(effective),
+            network_supported: effective as i32 >= ABI::V4 as i32,
+        }
+    }
+}
+
+/// A single line which lists each path with how it was added, and the allowed ports
+///
+/// This is intended for logging the policy which is applied, e.g.
+/// `ro:/usr ro:/etc rw:/tmp/foo connect:[80,443] bind:[*]`.  Paths are prefixed by `ro`
+/// ([`Rules::add_read_only`]), `rx` ([`Rules::add_read_execute`]), `rw` ([`Rules::add_read_write`]),
+/// or `wo` ([`Rules::add_write_only`]), and paths added by [`Rules::add_path_access`] by the names
+/// of their rights, e.g. `read_file,read_dir:/srv`.  Ports are `[*]` if any port is allowed.
+/// Rules which are [audit only](Rules::audit_only) end with `audit_only`.  Use
+/// [`Rules::describe`] for everything that the rules would enforce on the running kernel.
+///
+/// ```
+/// # use leucite::Rules;
+/// let rules = Rules::new()
+///     .add_read_only("/usr")
+///     .add_read_write("/tmp/foo")
+///     .add_connect_port(80)
+///     .add_connect_port(443)
+///     .allow_all_bind();
+///
+/// assert_eq!(rules.to_string(), "ro:/usr rw:/tmp/foo connect:[80,443] bind:[*]");
+/// ```
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (mode, paths) in [
+            ("ro", &self.read_only),
+            ("rx", &self.read_execute),
+            ("rw", &self.read_write),
+            ("wo", &self.write_only),
+        ] {
+            for path in paths {
+                write!(f, "{mode}:{} ", path.display())?;
+            }
+        }
+        for (path, access) in &self.path_access {
+            let names = ACCESS_NAMES.iter().filter(|(a, _)| access.contains(*a));
+            for (i, (_, name)) in names.enumerate() {
+                let sep = if i == 0 { "" } else { "," };
+                write!(f, "{sep}{name}")?;
+            }
+            write!(f, ":{} ", path.display())?;
+        }
+
+        write!(f, "connect:")?;
+        write_ports(f, &self.connect_ports)?;
+        write!(f, " bind:")?;
+        write_ports(f, &self.bind_ports)?;
+        if self.audit_only {
+            write!(f, " audit_only")?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the ports which are allowed by `ports`, e.g., `[80,443]`, or `[*]` for any port
+fn write_ports(f: &mut fmt::Formatter<'_>, ports: &Ports) -> fmt::Result {
+    let Some(ports) = ports.listed() else {
+        return write!(f, "[*]");
+    };
+    write!(f, "[")?;
+    for (i, port) in ports.iter().enumerate() {
+        let sep = if i == 0 { "" } else { "," };
+        write!(f, "{sep}{port}")?;
+    }
+    write!(f, "]")
+}
+
+/// Write the names of the rights in `access`, e.g., `read_file, read_dir`
+pub(crate) fn write_access(f: &mut fmt::Formatter<'_>, access: BitFlags<AccessFs>) -> fmt::Result {
+    let names = ACCESS_NAMES.iter().filter(|(a, _)| access.contains(*a));
+    for (i, (_, name)) in names.enumerate() {
+        let sep = if i == 0 { "" } else { ", " };
+        write!(f, "{sep}{name}")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for RulesDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "enforced: {}", self.enforced)?;
+        write!(
+            f,
+            "abi: requested {}, effective {}, kernel ",
+            self.requested_abi, self.effective_abi
+        )?;
+        match self.kernel_abi {
+            Some(abi) => writeln!(f, "{abi}")?,
+            None => writeln!(f, "unsupported")?,
+        }
+        if !self.unsupported_access.is_empty() {
+            write!(f, "unsupported rights: ")?;
+            write_access(f, self.unsupported_access)?;
+            writeln!(f)?;
+        }
+
+        write!(f, "read access: ")?;
+        write_access(f, self.read_access)?;
+        write!(f, "\nwrite access: ")?;
+        write_access(f, self.write_access)?;
+        writeln!(f)?;
+
+        for (name, paths) in [
+            ("read only", &self.read_only),
+            ("read execute", &self.read_execute),
+            ("read write", &self.read_write),
+            ("write only", &self.write_only),
+        ] {
+            if !paths.is_empty() {
+                writeln!(f, "{name}:")?;
+                for path in paths {
+                    writeln!(f, "  {}", path.display())?;
+                }
+            }
+        }
+        if !self.path_access.is_empty() {
+            writeln!(f, "custom access:")?;
+            for (path, access) in &self.path_access {
+                write!(f, "  {}: ", path.display())?;
+                write_access(f, *access)?;
+                writeln!(f)?;
+            }
+        }
+
+        for (name, ports) in [
+            ("bind ports", &self.bind_ports),
+            ("connect ports", &self.connect_ports),
+        ] {
+            write!(f, "{name}: ")?;
+            match ports {
+                None => writeln!(f, "any")?,
+                Some(ports) if ports.is_empty() => writeln!(f, "none")?,
+                Some(ports) => {
+                    let ports = ports.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                    writeln!(f, "{}", ports.join(", "))?
+                }
+            }
+        }
+        if !self.network_supported && (self.bind_ports.is_some() || self.connect_ports.is_some()) {
+            writeln!(f, "  (ports are not restricted by the running kernel)")?;
+        }
+        for (name, scoped) in [
+            ("abstract unix sockets", self.scope_abstract_unix_sockets),
+            ("signals", self.scope_signals),
+        ] {
+            if scoped {
+                writeln!(f, "scoped: {name}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
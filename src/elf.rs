@@ -0,0 +1,341 @@
+//! A minimal ELF parser which finds the shared libraries that an executable depends on, in the
+//! same way as the dynamic linker (see `ld.so(8)`)
+use std::{
+    collections::VecDeque,
+    fs, io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const PT_LOAD: u32 = 1;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+
+/// Directories which are searched after those in `/etc/ld.so.conf`
+const DEFAULT_DIRS: [&str; 4] = ["/lib64", "/usr/lib64", "/lib", "/usr/lib"];
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Add two offsets which were read from the file, which may overflow in a malformed file
+fn add(a: u64, b: u64) -> io::Result<u64> {
+    a.checked_add(b)
+        .ok_or_else(|| invalid("offset out of range"))
+}
+
+/// The dynamic linking information of an ELF file
+#[derive(Debug, Default)]
+struct Dynamic {
+    /// The class (32 or 64 bit) and machine of the file, which libraries must match
+    kind: (u8, u16),
+    interpreter: Option<PathBuf>,
+    needed: Vec<PathBuf>,
+    rpath: Vec<PathBuf>,
+    runpath: Vec<PathBuf>,
+}
+
+/// Reads integers from an ELF file with the endianness and class of the file
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    is_64: bool,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: u64) -> io::Result<[u8; N]> {
+        let offset = usize::try_from(offset).map_err(|_| invalid("offset out of range"))?;
+        self.data
+            .get(offset..)
+            .and_then(|d| d.get(..N))
+            .and_then(|d| d.try_into().ok())
+            .ok_or_else(|| invalid("unexpected end of file"))
+    }
+
+    fn u16(&self, offset: u64) -> io::Result<u16> {
+        let b = self.bytes(offset)?;
+        Ok(if self.little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32(&self, offset: u64) -> io::Result<u32> {
+        let b = self.bytes(offset)?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    fn u64(&self, offset: u64) -> io::Result<u64> {
+        let b = self.bytes(offset)?;
+        Ok(if self.little_endian {
+            u64::from_le_bytes(b)
+        } else {
+            u64::from_be_bytes(b)
+        })
+    }
+
+    /// Read a word, which is 4 bytes in 32-bit files and 8 bytes in 64-bit files
+    fn word(&self, offset: u64) -> io::Result<u64> {
+        if self.is_64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    /// Read a nul-terminated string
+    fn str(&self, offset: u64) -> io::Result<&[u8]> {
+        let offset = usize::try_from(offset).map_err(|_| invalid("offset out of range"))?;
+        let data = self
+            .data
+            .get(offset..)
+            .ok_or_else(|| invalid("string out of range"))?;
+        let len = data
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+        Ok(&data[..len])
+    }
+}
+
+fn parse(data: &[u8]) -> io::Result<Dynamic> {
+    if data.get(..4) != Some(b"\x7fELF") {
+        return Err(invalid("not an ELF file"));
+    }
+    let class = *data.get(4).ok_or_else(|| invalid("truncated ELF header"))?;
+    let r = Reader {
+        data,
+        is_64: class == 2,
+        little_endian: data.get(5) == Some(&1),
+    };
+
+    let machine = r.u16(18)?;
+    // The offsets of the fields in the file header and program headers depend on the class
+    let (phoff, phentsize, phnum) = if r.is_64 {
+        (r.u64(32)?, r.u16(54)?, r.u16(56)?)
+    } else {
+        (u64::from(r.u32(28)?), r.u16(42)?, r.u16(44)?)
+    };
+
+    let mut dynamic = None;
+    let mut loads = Vec::new();
+    let mut out = Dynamic {
+        kind: (class, machine),
+        ..Default::default()
+    };
+    for i in 0..u64::from(phnum) {
+        // `i * phentsize` can not overflow, since both are at most `u16::MAX`
+        let ph = add(phoff, i * u64::from(phentsize))?;
+        // (p_offset, p_vaddr, p_filesz)
+        let segment = if r.is_64 {
+            (
+                r.u64(add(ph, 8)?)?,
+                r.u64(add(ph, 16)?)?,
+                r.u64(add(ph, 32)?)?,
+            )
+        } else {
+            (
+                u64::from(r.u32(add(ph, 4)?)?),
+                u64::from(r.u32(add(ph, 8)?)?),
+                u64::from(r.u32(add(ph, 16)?)?),
+            )
+        };
+        match r.u32(ph)? {
+            PT_DYNAMIC => dynamic = Some(segment),
+            PT_LOAD => loads.push(segment),
+            PT_INTERP => {
+                let interp = r.str(segment.0)?;
+                out.interpreter = Some(PathBuf::from(std::ffi::OsStr::from_bytes(interp)));
+            }
+            _ => {}
+        }
+    }
+
+    // A static executable has no dynamic segment
+    let Some((offset, _, size)) = dynamic else {
+        return Ok(out);
+    };
+
+    let entry_size = if r.is_64 { 16 } else { 8 };
+    let mut entries = Vec::new();
+    let mut strtab = None;
+    for entry in (offset..add(offset, size)?).step_by(entry_size) {
+        let tag = r.word(entry)?;
+        let value = r.word(add(entry, entry_size as u64 / 2)?)?;
+        match tag {
+            DT_NULL => break,
+            DT_STRTAB => strtab = Some(value),
+            DT_NEEDED | DT_RPATH | DT_RUNPATH => entries.push((tag, value)),
+            _ => {}
+        }
+    }
+
+    if entries.is_empty() {
+        return Ok(out);
+    }
+
+    // The string table is referred to by its virtual address, so find it in the file
+    let strtab = strtab.ok_or_else(|| invalid("missing string table"))?;
+    let strtab = loads
+        .iter()
+        .find_map(|(offset, vaddr, size)| {
+            let delta = strtab.checked_sub(*vaddr).filter(|delta| delta < size)?;
+            Some(add(*offset, delta))
+        })
+        .ok_or_else(|| invalid("string table is not loaded"))??;
+
+    for (tag, value) in entries {
+        let s = std::ffi::OsStr::from_bytes(r.str(add(strtab, value)?)?);
+        match tag {
+            DT_NEEDED => out.needed.push(s.into()),
+            DT_RPATH => out.rpath.extend(std::env::split_paths(s)),
+            _ => out.runpath.extend(std::env::split_paths(s)),
+        }
+    }
+    Ok(out)
+}
+
+/// Get the directories which are configured in `/etc/ld.so.conf` and the files that it includes
+fn configured_dirs(conf: &Path, dirs: &mut Vec<PathBuf>) {
+    let Ok(contents) = fs::read_to_string(conf) else {
+        return;
+    };
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(pattern) = line.strip_prefix("include") {
+            for conf in glob(Path::new(pattern.trim())) {
+                configured_dirs(&conf, dirs);
+            }
+        } else if !line.is_empty() {
+            dirs.push(line.into());
+        }
+    }
+}
+
+/// Expand a pattern with a `*` in its file name, which is all that `ld.so.conf` uses in practice
+fn glob(pattern: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (pattern.parent(), pattern.file_name()) else {
+        return Vec::new();
+    };
+    let name = name.to_string_lossy();
+    let Some((prefix, suffix)) = name.split_once('*') else {
+        return vec![pattern.to_path_buf()];
+    };
+
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy())
+                .is_some_and(|n| n.starts_with(prefix) && n.ends_with(suffix))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Replace `$ORIGIN` in an `RPATH` or `RUNPATH` entry with the directory of the object
+fn expand_origin(dir: &Path, origin: &Path) -> PathBuf {
+    let dir = dir.to_string_lossy();
+    let origin = origin.to_string_lossy();
+    dir.replace("${ORIGIN}", &origin)
+        .replace("$ORIGIN", &origin)
+        .into()
+}
+
+/// Get the files and directories which the dynamic linker reads to load shared libraries from
+/// the system, which exist on this host
+pub(crate) fn linker_paths() -> Vec<PathBuf> {
+    let mut paths = vec![
+        PathBuf::from("/etc/ld.so.cache"),
+        PathBuf::from("/etc/ld.so.preload"),
+    ];
+    configured_dirs(Path::new("/etc/ld.so.conf"), &mut paths);
+    paths.extend(DEFAULT_DIRS.iter().map(PathBuf::from));
+
+    let mut existing: Vec<PathBuf> = Vec::new();
+    for path in paths {
+        if path.exists() && !existing.contains(&path) {
+            existing.push(path);
+        }
+    }
+    existing
+}
+
+/// Get the interpreter (the dynamic linker) of `binary`, if it has one
+pub(crate) fn interpreter_of(binary: &Path) -> io::Result<Option<PathBuf>> {
+    Ok(parse(&fs::read(binary)?)?.interpreter)
+}
+
+/// Find the file of each shared library which `binary` depends on, recursively, along with its
+/// interpreter (the dynamic linker)
+///
+/// `LD_LIBRARY_PATH` is not used, since the environment of the command is not known.
+pub(crate) fn shared_libs_of(binary: &Path) -> io::Result<Vec<PathBuf>> {
+    let root = parse(&fs::read(binary)?)?;
+
+    let mut system_dirs = Vec::new();
+    configured_dirs(Path::new("/etc/ld.so.conf"), &mut system_dirs);
+    system_dirs.extend(DEFAULT_DIRS.iter().map(PathBuf::from));
+
+    let mut libs: Vec<PathBuf> = root.interpreter.iter().cloned().collect();
+    let mut queue = VecDeque::from([(binary.to_path_buf(), Vec::new(), root)]);
+    while let Some((object, loader_rpath, dynamic)) = queue.pop_front() {
+        let origin = object.parent().unwrap_or(Path::new("/"));
+        // The `RPATH` of an object is searched, followed by the `RPATH` of each object which
+        // loaded it, unless the object has a `RUNPATH`
+        let rpath: Vec<_> = (dynamic.rpath.iter())
+            .map(|dir| expand_origin(dir, origin))
+            .chain(loader_rpath)
+            .collect();
+        let search = if dynamic.runpath.is_empty() {
+            &rpath[..]
+        } else {
+            &[]
+        };
+        let runpath = dynamic.runpath.iter().map(|dir| expand_origin(dir, origin));
+
+        for needed in &dynamic.needed {
+            let found = if needed.as_os_str().as_bytes().contains(&b'/') {
+                // A name with a slash is used as a path, relative to the working directory
+                Some((needed.clone(), parse(&fs::read(needed)?)?))
+            } else {
+                (search.iter().cloned())
+                    .chain(runpath.clone())
+                    .chain(system_dirs.iter().cloned())
+                    .map(|dir| dir.join(needed))
+                    .find_map(|path| {
+                        // Libraries for a different class or machine are skipped, like the linker
+                        let lib = parse(&fs::read(&path).ok()?).ok()?;
+                        (lib.kind == dynamic.kind).then_some((path, lib))
+                    })
+            };
+
+            let Some((path, lib)) = found else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("shared library {} not found", needed.display()),
+                ));
+            };
+            if !libs.contains(&path) {
+                libs.push(path.clone());
+                queue.push_back((path, rpath.clone(), lib));
+            }
+        }
+    }
+    Ok(libs)
+}
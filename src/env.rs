@@ -0,0 +1,101 @@
+//! Loading [`Rules`] from environment variables, see [`Rules::from_env`]
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+};
+
+use crate::{Ports, Rules};
+
+/// An error from loading [`Rules`] from environment variables, see [`Rules::from_env`]
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum RulesFromEnvError {
+    #[error("${var} is not valid unicode")]
+    NotUnicode { var: &'static str },
+    #[error(
+        "invalid port {value:?} in ${var}, expected a number from 0 to 65535, `any`, or `none`"
+    )]
+    InvalidPort { var: &'static str, value: String },
+    #[error("${var} is empty, use `none` to deny all ports")]
+    NoPorts { var: &'static str },
+}
+
+impl Rules {
+    /// Load rules from the environment variables of the current process
+    ///
+    /// This allows operators to adjust the policy of a deployment (e.g., a container) without
+    /// changing any code.  The following variables are read, each of which is optional:
+    ///
+    /// | variable                | format                                                  |
+    /// |-------------------------|---------------------------------------------------------|
+    /// | `LEUCITE_READ_ONLY`     | paths separated by `:`, see [`Rules::add_read_only`]    |
+    /// | `LEUCITE_READ_EXECUTE`  | paths separated by `:`, see [`Rules::add_read_execute`] |
+    /// | `LEUCITE_READ_WRITE`    | paths separated by `:`, see [`Rules::add_read_write`]   |
+    /// | `LEUCITE_WRITE_ONLY`    | paths separated by `:`, see [`Rules::add_write_only`]   |
+    /// | `LEUCITE_BIND_PORTS`    | ports separated by `,`, `any`, or `none`                |
+    /// | `LEUCITE_CONNECT_PORTS` | ports separated by `,`, `any`, or `none`                |
+    ///
+    /// Paths are split like `$PATH` (see [`env::split_paths`]), and empty entries are ignored.
+    /// Ports may be surrounded by whitespace, but an empty port variable is rejected rather than
+    /// silently denying all ports.  Like [`Rules::new`], anything which is not granted is denied,
+    /// so with none of the variables set, the rules deny all access.
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use leucite::{CommandExt, Rules};
+    /// // LEUCITE_READ_ONLY=/usr:/etc LEUCITE_CONNECT_PORTS=80,443
+    /// let rules = Rules::from_env()?;
+    /// # Ok::<_, leucite::RulesFromEnvError>(())
+    /// ```
+    pub fn from_env() -> Result<Self, RulesFromEnvError> {
+        Self::from_vars(|var| env::var_os(var))
+    }
+
+    /// Load rules from the variables which are looked up using `var`, see [`Rules::from_env`]
+    fn from_vars(var: impl Fn(&str) -> Option<OsString>) -> Result<Self, RulesFromEnvError> {
+        let paths = |name| {
+            let value = var(name).unwrap_or_default();
+            env::split_paths(&value)
+                .filter(|path| !path.as_os_str().is_empty())
+                .collect()
+        };
+        let mut rules = Self {
+            read_only: paths("LEUCITE_READ_ONLY"),
+            read_execute: paths("LEUCITE_READ_EXECUTE"),
+            read_write: paths("LEUCITE_READ_WRITE"),
+            write_only: paths("LEUCITE_WRITE_ONLY"),
+            ..Self::new()
+        };
+        if let Some(value) = var("LEUCITE_BIND_PORTS") {
+            rules.bind_ports = parse_ports("LEUCITE_BIND_PORTS", &value)?;
+        }
+        if let Some(value) = var("LEUCITE_CONNECT_PORTS") {
+            rules.connect_ports = parse_ports("LEUCITE_CONNECT_PORTS", &value)?;
+        }
+        Ok(rules)
+    }
+}
+
+/// Parse the ports of the variable `var`
+fn parse_ports(var: &'static str, value: &OsStr) -> Result<Ports, RulesFromEnvError> {
+    let value = value
+        .to_str()
+        .ok_or(RulesFromEnvError::NotUnicode { var })?
+        .trim();
+    match value {
+        "" => Err(RulesFromEnvError::NoPorts { var }),
+        "any" => Ok(Ports::Any),
+        "none" => Ok(Ports::None),
+        _ => value
+            .split(',')
+            .map(|port| {
+                port.trim()
+                    .parse()
+                    .map_err(|_| RulesFromEnvError::InvalidPort {
+                        var,
+                        value: port.to_string(),
+                    })
+            })
+            .collect::<Result<_, _>>()
+            .map(Ports::Only),
+    }
+}
@@ -0,0 +1,73 @@
+use std::{ffi::CString, io, mem::MaybeUninit, os::unix::ffi::OsStrExt, path::Path};
+
+use crate::prlimit::read_errno;
+
+/// A type of filesystem, as reported by [`statfs(2)`](https://www.man7.org/linux/man-pages/man2/statfs.2.html)
+///
+/// Landlock can not filter access based on the type of filesystem, so this is checked against
+/// each granted path before the rules are applied.  See [`Rules::deny_filesystem_types`](crate::Rules::deny_filesystem_types).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FsType(u32);
+
+impl FsType {
+    /// Network File System
+    pub const NFS: Self = Self(0x6969);
+    /// SMB, version 1
+    pub const SMB: Self = Self(0x517b);
+    /// SMB, version 2 and up
+    pub const SMB2: Self = Self(0xfe53_4d42);
+    /// Common Internet File System
+    pub const CIFS: Self = Self(0xff53_4d42);
+    /// Plan 9 filesystem protocol
+    pub const V9FS: Self = Self(0x0102_1997);
+    /// Ceph
+    pub const CEPH: Self = Self(0x00c3_6400);
+    /// Andrew File System
+    pub const AFS: Self = Self(0x5346_414f);
+    /// Any filesystem in userspace (e.g., `sshfs`)
+    pub const FUSE: Self = Self(0x6573_5546);
+
+    /// Commonly used network filesystems
+    pub const NETWORK: &'static [Self] = &[
+        Self::NFS,
+        Self::SMB,
+        Self::SMB2,
+        Self::CIFS,
+        Self::V9FS,
+        Self::CEPH,
+        Self::AFS,
+        Self::FUSE,
+    ];
+
+    /// Create a filesystem type from its magic number (see `linux/magic.h`)
+    #[inline]
+    pub const fn from_magic(magic: u32) -> Self {
+        Self(magic)
+    }
+
+    /// Get the magic number for this filesystem type
+    #[inline]
+    pub const fn magic(self) -> u32 {
+        self.0
+    }
+
+    /// Get the type of the filesystem on which `path` resides
+    pub fn of(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
+        let mut buf = MaybeUninit::<libc::statfs>::uninit();
+        // SAFETY: `path` is a valid, nul-terminated string and `buf` is large enough to hold the
+        // result.  Any error is reported through `errno`.
+        let ret = unsafe { libc::statfs(path.as_ptr(), buf.as_mut_ptr()) };
+        if ret != 0 {
+            return Err(read_errno());
+        }
+
+        // SAFETY: `statfs` succeeded, so `buf` has been initialised
+        let buf = unsafe { buf.assume_init() };
+        // Magic numbers are 32 bits, but `f_type` may be a signed and/or larger integer depending
+        // on the platform
+        Ok(Self(buf.f_type as u32))
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt::Write as _;
+
+use landlock::{AccessFs, AccessNet, BitFlags};
+
+use crate::{LimitValue, Ports, Rules, SandboxProfile};
+
+/// The name of each filesystem access right in the JSON schema, which matches the name of the
+/// `LANDLOCK_ACCESS_FS_*` constant of the kernel
+pub(crate) const ACCESS_NAMES: [(AccessFs, &str); 16] = [
+    (AccessFs::Execute, "execute"),
+    (AccessFs::WriteFile, "write_file"),
+    (AccessFs::ReadFile, "read_file"),
+    (AccessFs::ReadDir, "read_dir"),
+    (AccessFs::RemoveDir, "remove_dir"),
+    (AccessFs::RemoveFile, "remove_file"),
+    (AccessFs::MakeChar, "make_char"),
+    (AccessFs::MakeDir, "make_dir"),
+    (AccessFs::MakeReg, "make_reg"),
+    (AccessFs::MakeSock, "make_sock"),
+    (AccessFs::MakeFifo, "make_fifo"),
+    (AccessFs::MakeBlock, "make_block"),
+    (AccessFs::MakeSym, "make_sym"),
+    (AccessFs::Refer, "refer"),
+    (AccessFs::Truncate, "truncate"),
+    (AccessFs::IoctlDev, "ioctl_dev"),
+];
+
+impl SandboxProfile {
+    /// The version of the schema which is produced by [`SandboxProfile::to_json`]
+    ///
+    /// This is incremented whenever a field is removed or its meaning changes.  Fields may be
+    /// added without changing the version, so consumers should ignore fields that they do not
+    /// know.
+    pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+    /// Describe this profile as JSON, for consumption by external tools or for attestation
+    ///
+    /// Unlike the `serde` implementations of this crate, which follow the layout of the types, this
+    /// is a stable and documented schema which covers the whole profile.  The output is a single
+    /// object (shown here with comments):
+    ///
+    /// ```text
+    /// {
+    ///   "schema": "leucite-sandbox-profile",
+    ///   "version": 1,
+    ///   // `null` if filesystem and network access are not restricted
+    ///   "rules": {
+    ///     // `false` for rules which are audit only
+    ///     "enforced": true,
+    ///     "filesystem": {
+    ///       // paths in the order that they were added, with the names of the
+    ///       // `LANDLOCK_ACCESS_FS_*` rights which are granted beneath them
+    ///       "grants": [{ "path": "/usr", "access": ["execute", "read_file", "read_dir"] }],
+    ///       // magic numbers of the denied filesystems, see `statfs(2)`
+    ///       "denied_filesystems": [26985]
+    ///     },
+    ///     // the sorted TCP ports which are allowed, or `null` for any port
+    ///     "network": { "bind": [], "connect": [80, 443] },
+    ///     // the IPC which is scoped to the sandbox
+    ///     "scopes": { "abstract_unix_sockets": false, "signals": true },
+    ///     // the landlock ABI which is requested, see `Rules::with_abi`
+    ///     "abi": 6
+    ///   },
+    ///   // each limit is `null` if it is not set, or `"unlimited"`
+    ///   "limits": {
+    ///     "max_memory_bytes": 100000000,
+    ///     // the hard limit, if it differs from the soft limit above
+    ///     "max_memory_hard_bytes": null,
+    ///     "max_address_space_bytes": null,
+    ///     "max_file_size_bytes": "unlimited",
+    ///     "max_file_size_hard_bytes": null,
+    ///     "max_threads": null,
+    ///     "max_cpu_time_s": null,
+    ///     "max_core_size_bytes": null,
+    ///     "max_stack_size_bytes": null,
+    ///     "max_open_files": null,
+    ///     "max_processes": null,
+    ///     "max_pending_signals": null,
+    ///     "max_message_queue_bytes": null,
+    ///     "max_locked_memory_bytes": null,
+    ///     "min_nice": null,
+    ///     "hard_deadline_us": null,
+    ///     "max_cpu_bandwidth": { "quota_us": 50000, "period_us": 100000 }
+    ///   },
+    ///   "process": {
+    ///     "oom_score_adj": null,
+    ///     "nice": null,
+    ///     "idle_priority": false,
+    ///     "detach_controlling_tty": false,
+    ///     "private_ipc": false,
+    ///     // `null` if the root directory is not changed
+    ///     "chroot": { "root": "/srv/jail", "cwd": "/" },
+    ///     "tmpfs": [{ "path": "/tmp", "size_bytes": 10000000 }],
+    ///     // each is `null` if it is not changed
+    ///     "uid": 65534,
+    ///     "gid": 65534,
+    ///     "supplementary_groups": []
+    ///   },
+    ///   // only the names of the variables are included, as their values may be secret
+    ///   "environment": { "clear": true, "set": ["PATH"] }
+    /// }
+    /// ```
+    ///
+    /// Paths and environment variables which are not valid UTF-8 are converted lossily.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        write!(
+            out,
+            r#"{{"schema":"leucite-sandbox-profile","version":{},"rules":"#,
+            Self::JSON_SCHEMA_VERSION
+        )
+        .unwrap();
+        match &self.rules {
+            Some(rules) => write_rules(&mut out, rules),
+            None => out.push_str("null"),
+        }
+
+        out.push_str(r#","limits":{"#);
+        for (name, unit, limit) in self.limits.entries() {
+            write!(out, r#""{name}{unit}":"#).unwrap();
+            match limit {
+                Some(LimitValue::Unlimited) => out.push_str(r#""unlimited""#),
+                Some(LimitValue::Bounded(value)) => write!(out, "{value}").unwrap(),
+                None => out.push_str("null"),
+            }
+            out.push(',');
+        }
+        out.push_str(r#""min_nice":"#);
+        write_option(&mut out, self.limits.min_nice);
+        out.push_str(r#","hard_deadline_us":"#);
+        write_option(&mut out, self.hard_deadline.map(|d| d.as_micros()));
+        out.push_str(r#","max_cpu_bandwidth":"#);
+        match self.max_cpu_bandwidth {
+            Some((quota, period)) => write!(
+                out,
+                r#"{{"quota_us":{},"period_us":{}}}"#,
+                quota.as_micros(),
+                period.as_micros()
+            )
+            .unwrap(),
+            None => out.push_str("null"),
+        }
+
+        out.push_str(r#"},"process":{"oom_score_adj":"#);
+        write_option(&mut out, self.oom_score_adj);
+        out.push_str(r#","nice":"#);
+        write_option(&mut out, self.nice);
+        write!(
+            out,
+            r#","idle_priority":{},"detach_controlling_tty":{},"private_ipc":{},"chroot":"#,
+            self.idle_priority, self.detach_controlling_tty, self.private_ipc
+        )
+        .unwrap();
+        match &self.chroot {
+            Some((root, cwd)) => {
+                out.push_str(r#"{"root":"#);
+                write_str(&mut out, &root.to_string_lossy());
+                out.push_str(r#","cwd":"#);
+                write_str(&mut out, &cwd.to_string_lossy());
+                out.push('}');
+            }
+            None => out.push_str("null"),
+        }
+        out.push_str(r#","tmpfs":["#);
+        for (i, (path, size)) in self.tmpfs.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(r#"{"path":"#);
+            write_str(&mut out, &path.to_string_lossy());
+            write!(out, r#","size_bytes":{}}}"#, size.bytes()).unwrap();
+        }
+        out.push_str(r#"],"uid":"#);
+        write_option(&mut out, self.uid);
+        out.push_str(r#","gid":"#);
+        write_option(&mut out, self.gid);
+        out.push_str(r#","supplementary_groups":"#);
+        match &self.supplementary_groups {
+            Some(groups) => write_list(&mut out, groups),
+            None => out.push_str("null"),
+        }
+        out.push('}');
+
+        write!(
+            out,
+            r#","environment":{{"clear":{},"set":["#,
+            self.env_clear
+        )
+        .unwrap();
+        for (i, (key, _)) in self.envs.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_str(&mut out, &key.to_string_lossy());
+        }
+        out.push_str("]}}");
+        out
+    }
+}
+
+fn write_rules(out: &mut String, rules: &Rules) {
+    write!(
+        out,
+        r#"{{"enforced":{},"filesystem":{{"grants":["#,
+        !rules.audit_only
+    )
+    .unwrap();
+    for (i, (path, access)) in rules.path_grants().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(r#"{"path":"#);
+        write_str(out, &path.to_string_lossy());
+        out.push_str(r#","access":"#);
+        write_access(out, access);
+        out.push('}');
+    }
+
+    out.push_str(r#"],"denied_filesystems":"#);
+    write_list(out, rules.denied_fs_types.iter().map(|t| t.magic()));
+    out.push_str(r#"},"network":{"bind":"#);
+    write_ports(out, rules.ports(AccessNet::BindTcp));
+    out.push_str(r#","connect":"#);
+    write_ports(out, rules.ports(AccessNet::ConnectTcp));
+    write!(
+        out,
+        r#"}},"scopes":{{"abstract_unix_sockets":{},"signals":{}}},"abi":{}}}"#,
+        rules.scope_abstract_unix_sockets,
+        rules.scope_signals,
+        rules.abi() as i32
+    )
+    .unwrap();
+}
+
+fn write_access(out: &mut String, access: BitFlags<AccessFs>) {
+    out.push('[');
+    let names = ACCESS_NAMES.iter().filter(|(a, _)| access.contains(*a));
+    for (i, (_, name)) in names.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_str(out, name);
+    }
+    out.push(']');
+}
+
+fn write_ports(out: &mut String, ports: &Ports) {
+    match ports.allowed() {
+        Some(ports) => write_list(out, ports),
+        None => out.push_str("null"),
+    }
+}
+
+fn write_list<T: std::fmt::Display>(out: &mut String, items: impl IntoIterator<Item = T>) {
+    out.push('[');
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write!(out, "{item}").unwrap();
+    }
+    out.push(']');
+}
+
+fn write_option<T: std::fmt::Display>(out: &mut String, value: Option<T>) {
+    match value {
+        Some(value) => write!(out, "{value}").unwrap(),
+        None => out.push_str("null"),
+    }
+}
+
+/// Write `s` as a JSON string, escaping it as necessary
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
@@ -25,17 +25,104 @@
 //!     .wait()?;
 //! # std::io::Result::Ok(())
 //! ```
+#[cfg(feature = "tokio")]
+use ::tokio::process::Command as TokioCommand;
+use cgroup::CpuMax;
 use landlock::{
-    path_beneath_rules, Access, AccessFs, AccessNet, NetPort, Ruleset, RulesetAttr,
-    RulesetCreatedAttr, RulesetStatus, ABI,
+    path_beneath_rules, Access, AccessFs, AccessNet, BitFlags, NetPort, PathFd, Ruleset,
+    RulesetAttr, RulesetCreated, RulesetCreatedAttr, RulesetStatus, Scope, ABI,
 };
 use prlimit::Limit;
-use std::{io, os::unix::process::CommandExt as _, path::PathBuf, process::Command, sync::Arc};
-#[cfg(feature = "tokio")]
-use tokio::process::Command as TokioCommand;
+use std::{
+    ffi::{CString, OsStr},
+    fmt, fs, io,
+    ops::RangeInclusive,
+    os::{
+        fd::{AsRawFd, OwnedFd},
+        unix::{
+            ffi::{OsStrExt, OsStringExt},
+            process::CommandExt as _,
+        },
+    },
+    path::{Component, Path, PathBuf},
+    process::Command,
+    sync::Arc,
+    time::Duration,
+};
 
 mod prlimit;
-pub use prlimit::MemorySize;
+pub use prlimit::{LimitValue, MemorySize, MemorySizeDelta, ParseMemorySizeError, Resource};
+
+mod process;
+
+mod cgroup;
+
+mod fstype;
+pub use fstype::FsType;
+
+mod scratch;
+pub use scratch::ScratchDir;
+
+mod profile;
+pub use profile::{Phase, SandboxProfile};
+
+mod limits;
+pub use limits::ResourceLimits;
+
+mod sandbox;
+pub use sandbox::Sandbox;
+
+mod report;
+pub use report::ExposureReport;
+
+mod describe;
+pub use describe::RulesDescription;
+
+mod json;
+
+#[cfg(feature = "serde")]
+mod serde_support;
+#[cfg(feature = "serde")]
+pub use serde_support::human_memory_size;
+
+mod elf;
+
+mod validate;
+pub use validate::MissingAccess;
+
+mod prepared;
+pub use prepared::PreparedRules;
+
+mod builder;
+pub use builder::RulesBuilder;
+
+mod env;
+pub use env::RulesFromEnvError;
+
+mod child_error;
+use child_error::ErrorPipe;
+pub use child_error::{ChildError, RestrictErrorKind};
+
+mod record;
+pub use record::{Enforcement, PendingAudit, SandboxAudit};
+
+mod selfcheck;
+pub use selfcheck::{self_check, CheckOutcome, SelfCheckReport};
+
+mod wait;
+pub use wait::{resident_memory, wait_with_rss_limit, WaitOutcome};
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "audit")]
+pub mod audit;
+
+#[cfg(feature = "tracing")]
+mod trace;
+
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 
 mod private {
     pub trait Sealed {}
@@ -56,10 +143,205 @@ pub enum Error {
     SetBindPorts(#[source] landlock::RulesetError),
     #[error("setting connect ports: {0}")]
     SetConnectPorts(#[source] landlock::RulesetError),
+    #[error("scoping ruleset: {0}")]
+    Scope(#[source] landlock::RulesetError),
     #[error("restricting current thread: {0}")]
     RestrictThread(#[source] landlock::RulesetError),
     #[error("installed kernel does not support landlock")]
     LandlockNotSupported,
+    #[error("opening {}: {source}", path.display())]
+    InvalidPath {
+        path: PathBuf,
+        #[source]
+        source: landlock::PathFdError,
+    },
+    #[error("{} resides on a denied filesystem ({:#x})", path.display(), fs_type.magic())]
+    DeniedFilesystem { path: PathBuf, fs_type: FsType },
+    #[error("restricting current thread with logging: {0}")]
+    RestrictLogging(#[source] io::Error),
+    #[error("rules grant full access, so restricting would have no effect")]
+    FullyPermissive,
+    #[error("granting access to {}: {source}", path.display())]
+    AddPathRule {
+        path: PathBuf,
+        #[source]
+        source: landlock::RulesetError,
+    },
+    #[error("{} is a directory, which landlock can only grant access to recursively", path.display())]
+    RecursiveDirectory { path: PathBuf },
+    #[error("canonicalizing {}: {source}", path.display())]
+    Canonicalize {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("allowing {access:?} on port {port}: {source}")]
+    AddPortRule {
+        port: u16,
+        access: AccessNet,
+        #[source]
+        source: landlock::RulesetError,
+    },
+    #[error("{} is not a relative path beneath {}", path.display(), root.display())]
+    NotBeneathRoot { root: PathBuf, path: PathBuf },
+}
+
+impl Error {
+    /// Get the kind of this error, without any of its data
+    pub fn kind(&self) -> RestrictErrorKind {
+        match self {
+            Self::AccessFs(_) => RestrictErrorKind::AccessFs,
+            Self::AcessNet(_) => RestrictErrorKind::AccessNet,
+            Self::CreateRuleset(_) => RestrictErrorKind::CreateRuleset,
+            Self::SetBindPorts(_) => RestrictErrorKind::SetBindPorts,
+            Self::SetConnectPorts(_) => RestrictErrorKind::SetConnectPorts,
+            Self::Scope(_) => RestrictErrorKind::Scope,
+            Self::RestrictThread(_) => RestrictErrorKind::RestrictThread,
+            Self::LandlockNotSupported => RestrictErrorKind::LandlockNotSupported,
+            Self::InvalidPath { .. } => RestrictErrorKind::InvalidPath,
+            Self::DeniedFilesystem { .. } => RestrictErrorKind::DeniedFilesystem,
+            Self::RestrictLogging(_) => RestrictErrorKind::RestrictLogging,
+            Self::FullyPermissive => RestrictErrorKind::FullyPermissive,
+            Self::AddPathRule { .. } => RestrictErrorKind::AddPathRule,
+            Self::AddPortRule { .. } => RestrictErrorKind::AddPortRule,
+            Self::RecursiveDirectory { .. } => RestrictErrorKind::RecursiveDirectory,
+            Self::Canonicalize { .. } => RestrictErrorKind::Canonicalize,
+            Self::NotBeneathRoot { .. } => RestrictErrorKind::NotBeneathRoot,
+        }
+    }
+}
+
+/// The landlock ABI which is requested when restricting by default, see [`Rules::with_abi`]
+const REQUESTED_ABI: ABI = ABI::V4;
+
+/// Flag for `landlock_create_ruleset` to query the highest ABI supported by the kernel
+const LANDLOCK_CREATE_RULESET_VERSION: libc::c_uint = 1 << 0;
+
+/// Flag for `landlock_restrict_self` to log denials after the next `exec`, since ABI 7
+const LANDLOCK_RESTRICT_SELF_LOG_NEW_EXEC_ON: libc::c_uint = 1 << 1;
+
+/// Query the highest landlock ABI which is supported by the running kernel
+fn kernel_abi() -> ABI {
+    ABI::from(kernel_abi_version())
+}
+
+/// Query the highest landlock ABI which is supported by the running kernel, or `None` if it does
+/// not support landlock (or landlock is disabled)
+///
+/// This only queries the kernel, so nothing is enforced on the calling thread.  It is intended to
+/// decide at startup whether to enable sandboxing and which features to rely on, rather than
+/// finding out when restricting fails with [`Error::LandlockNotSupported`].  A kernel which
+/// supports an ABI which is newer than the ABIs known to [`ABI`] is reported as the newest known
+/// ABI.
+///
+/// ```
+/// # use leucite::landlock_abi;
+/// # use landlock::ABI;
+/// match landlock_abi() {
+///     Some(abi) if abi as i32 >= ABI::V4 as i32 => println!("TCP ports can be restricted"),
+///     Some(_) => println!("only the filesystem can be restricted"),
+///     None => println!("landlock is not supported"),
+/// }
+/// ```
+pub fn landlock_abi() -> Option<ABI> {
+    match kernel_abi() {
+        ABI::Unsupported => None,
+        abi => Some(abi),
+    }
+}
+
+/// Query the version of the highest landlock ABI which is supported by the running kernel, which
+/// may be newer than the ABIs that are known to [`ABI`]
+fn kernel_abi_version() -> i32 {
+    // SAFETY: Passing a null attribute pointer with a size of 0 is the documented way to query the
+    // ABI version.  No ruleset is created, and any error is reported through the return value.
+    let version = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    version as i32
+}
+
+/// Restrict the current thread using `ruleset`, logging any denials after the next `exec`
+///
+/// The `landlock` crate doesn't support the flags of `landlock_restrict_self`, so this makes the
+/// same calls as [`RulesetCreated::restrict_self`] directly.
+fn restrict_self_logged(ruleset: RulesetCreated, requested: ABI) -> Result<RulesetStatus, Error> {
+    let Some(fd) = Option::<OwnedFd>::from(ruleset) else {
+        return Ok(RulesetStatus::NotEnforced);
+    };
+
+    // SAFETY: `prctl` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(Error::RestrictLogging(io::Error::last_os_error()));
+    }
+    // SAFETY: `fd` is a valid ruleset, and any error is reported through `errno`
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_restrict_self,
+            fd.as_raw_fd(),
+            LANDLOCK_RESTRICT_SELF_LOG_NEW_EXEC_ON,
+        )
+    };
+    if ret != 0 {
+        return Err(Error::RestrictLogging(io::Error::last_os_error()));
+    }
+
+    Ok(if (kernel_abi() as i32) < (requested as i32) {
+        RulesetStatus::PartiallyEnforced
+    } else {
+        RulesetStatus::FullyEnforced
+    })
+}
+
+/// The status of a process after it has been restricted using [`Rules`]
+#[non_exhaustive]
+pub struct RestrictStatus {
+    /// How much of the requested ruleset is enforced
+    pub ruleset: RulesetStatus,
+    /// The landlock ABI which was enforced.  This is the highest ABI supported by the kernel, up to
+    /// the ABI that was requested.
+    pub effective_abi: ABI,
+}
+
+impl fmt::Debug for RestrictStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestrictStatus")
+            .field("ruleset", &self.ruleset)
+            .field("effective_abi", &(self.effective_abi as i32))
+            .finish()
+    }
+}
+
+/// A token which proves that the current thread has been restricted, see
+/// [`Rules::restrict_self_guarded`]
+///
+/// Functions which must only run once the sandbox is in place can take a `Restricted` (or a
+/// reference to one) as an argument, so that calling them before restricting is a compile-time
+/// error:
+///
+/// ```no_run
+/// # use leucite::{Restricted, Rules};
+/// fn handle_untrusted_input(_proof: &Restricted) {
+///     // ...
+/// }
+///
+/// let rules = Rules::new().add_read_only("/usr");
+/// // SAFETY: Nothing else is running yet
+/// let proof = unsafe { rules.restrict_self_guarded() }.unwrap();
+/// handle_untrusted_input(&proof);
+/// ```
+///
+/// Landlock restrictions can never be undone, so dropping this does nothing: the thread stays
+/// restricted either way.  Since landlock only restricts the thread which applied the rules (and
+/// the threads and processes it creates afterwards), the token can not be sent to other threads.
+#[derive(Debug)]
+pub struct Restricted {
+    _not_send: std::marker::PhantomData<*const ()>,
 }
 
 /// Struct which holds the rules for restrictions.  For more information, see [`Ruleset`].
@@ -77,13 +359,246 @@ pub enum Error {
 ///     .add_connect_port(80)
 ///     .add_connect_port(443);
 /// ```
+///
+/// # Default deny
+///
+/// Landlock denies everything which is not explicitly granted: once restricted, a command can
+/// only access the paths beneath those that have been added, with the rights that they were added
+/// with, and can only use the ports which have been added.  This is the opposite of tools like
+/// AppArmor in complain mode, so a command that works outside of the sandbox will often fail
+/// inside of it until each of the paths that it uses (e.g., its libraries in `/lib`, or `/dev/null`)
+/// has been granted.
+///
+/// The filesystem and the network behave the same way when nothing has been added: [`Rules::new`]
+/// on its own denies all access to the filesystem, and all TCP binds and connections (with ABI 4
+/// or later, see [`Rules::with_abi`]).  The same holds for rules whose paths or ports have all
+/// been removed again.  Use [`Rules::allow_all_network`] to leave the network unrestricted.
+///
+/// Landlock can not log accesses without denying them, so to develop a policy iteratively, use
+/// [`Rules::audit_only`] to run the command without enforcing the rules, and
+/// [`Rules::missing_access`] to check the paths that the command uses (e.g., collected using
+/// `strace -f -e trace=file`, or [`Rules::from_open_files_of`]) against the rules.
+///
+/// # Recursion
+///
+/// Access which is granted to a directory always applies to everything beneath it, at any depth,
+/// since landlock only has rules for a path and the hierarchy beneath it.  There is no way to
+/// grant access to the entries of a directory without also granting it to its subdirectories,
+/// and no way to exclude a subdirectory from a grant on its parent (landlock is additive, see
+/// [`Rules::add_read_only_with_writable`]).  Access which is granted to a file only applies to
+/// that file.  To make sure that a path is not accidentally granted recursively, use
+/// [`Rules::try_add_read_only_shallow`], which fails for directories.
+///
+/// # Network
+///
+/// Landlock restricts the network by TCP port only, never by address.  A port which is added
+/// using [`Rules::add_connect_port`] may be used to connect to _any_ host, over IPv4 or IPv6, so
+/// `add_connect_port(443)` allows HTTPS to the whole internet, not only to the service that the
+/// command is meant to reach.  Likewise, [`Rules::add_bind_port`] allows binding to the port on any
+/// local address, including `0.0.0.0` and `::`.  No landlock ABI so far can scope network access
+/// by address, and UDP and other protocols are not restricted at all.
+///
+/// [`Rules::add_connect_port_any_host`] is the same as [`Rules::add_connect_port`], with a name
+/// which makes this explicit at the call site.  To restrict which hosts a command may reach, run it
+/// in its own network namespace (e.g., using `unshare(1)` or a container runtime), where the only
+/// reachable endpoint is a proxy which filters by host, and allow only the port of that proxy.
+///
+/// # Equality
+///
+/// Rules implement `Eq` and `Hash`, so they can be used as keys of a `HashMap` (e.g., to cache
+/// anything which is derived from a policy).  Equality is structural, so the order in which paths
+/// and ports were added matters, as do duplicates.  Use [`Rules::normalize`] first so that rules
+/// which only differ in those ways compare equal.
+///
+/// # Configuration
+///
+/// With the `serde` feature, rules can be loaded from a configuration file.  Each field is
+/// optional and defaults to the same value as [`Rules::new`], and unknown fields are rejected so
+/// that a typo can't silently weaken a policy:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// # use leucite::Rules;
+/// let rules: Rules = serde_json::from_str(r#"{
+///     "read_only": ["/usr", "/etc"],
+///     "read_write": ["/tmp/work"],
+///     "write_only": [],
+///     "bind_ports": "none",
+///     "connect_ports": [443],
+///     "read_access": ["read_file", "read_dir"]
+/// }"#).unwrap();
+/// # }
+/// ```
+///
+/// The other fields are `read_execute`, `path_access` (a list of objects with a `path` and its
+/// `access`), `denied_fs_types` (magic numbers, see [`FsType`]), `write_access`, `audit_only`,
+/// `log_denials`, `permissive_policy`, `writable_noexec`, `scope_abstract_unix_sockets`,
+/// `scope_signals`, and `abi`.
+/// Ports are either `"any"`, `"none"`, or a list of ports, and access rights are the names of the
+/// `LANDLOCK_ACCESS_FS_*` rights.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct Rules {
     read_only: Vec<PathBuf>,
+    read_execute: Vec<PathBuf>,
     read_write: Vec<PathBuf>,
     write_only: Vec<PathBuf>,
-    bind_ports: Vec<u16>,
-    connect_ports: Vec<u16>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::path_access"))]
+    path_access: Vec<(PathBuf, BitFlags<AccessFs>)>,
+    bind_ports: Ports,
+    connect_ports: Ports,
+    denied_fs_types: Vec<FsType>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::access"))]
+    read_access: Option<BitFlags<AccessFs>>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_support::access"))]
+    write_access: Option<BitFlags<AccessFs>>,
+    audit_only: bool,
+    log_denials: bool,
+    permissive_policy: PermissivePolicy,
+    writable_noexec: bool,
+    scope_abstract_unix_sockets: bool,
+    scope_signals: bool,
+    abi: Option<i32>,
+}
+
+/// The ports which may be used for a kind of network access
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Ports {
+    /// Any port may be used, i.e., the access is not restricted
+    Any,
+    /// No port may be used
+    None,
+    /// Only the listed ports may be used.  If no ports are listed, no port may be used.
+    Only(Vec<u16>),
+}
+
+impl Default for Ports {
+    fn default() -> Self {
+        Self::Only(Vec::new())
+    }
+}
+
+impl Ports {
+    fn add(&mut self, port: u16) {
+        match self {
+            Self::Any => {}
+            Self::None => *self = Self::Only(vec![port]),
+            Self::Only(ports) => ports.push(port),
+        }
+    }
+
+    /// Add each port in `range` which is not already allowed
+    fn add_range(&mut self, range: RangeInclusive<u16>) {
+        match self {
+            Self::Any => {}
+            Self::None => *self = Self::Only(range.collect()),
+            Self::Only(ports) => {
+                let mut existing = ports.clone();
+                existing.sort_unstable();
+                ports.extend(range.filter(|p| existing.binary_search(p).is_err()));
+            }
+        }
+    }
+
+    fn remove(&mut self, port: u16) {
+        if let Self::Only(ports) = self {
+            ports.retain(|&p| p != port);
+        }
+    }
+
+    /// Merge `other` into these ports, allowing any port which is allowed by either
+    fn merge(&mut self, other: Ports) {
+        match (&mut *self, other) {
+            (Self::Any, _) | (_, Self::None) => {}
+            (_, Self::Any) => *self = Self::Any,
+            (Self::None, other) => *self = other,
+            (Self::Only(ports), Self::Only(other)) => merge_vec(ports, other),
+        }
+    }
+
+    /// Intersect these ports with `other`, allowing only the ports which are allowed by both
+    fn intersect(&mut self, other: Ports) {
+        match (&mut *self, other) {
+            (_, Self::Any) | (Self::None, _) => {}
+            (Self::Any, other) => *self = other,
+            (_, Self::None) => *self = Self::None,
+            (Self::Only(ports), Self::Only(other)) => ports.retain(|p| other.contains(p)),
+        }
+    }
+
+    /// Get the sorted ports which are allowed, or `None` if any port is allowed
+    fn allowed(&self) -> Option<Vec<u16>> {
+        match self {
+            Self::Any => None,
+            Self::None => Some(Vec::new()),
+            Self::Only(ports) => {
+                let mut ports = ports.clone();
+                ports.sort_unstable();
+                ports.dedup();
+                Some(ports)
+            }
+        }
+    }
+
+    /// Whether every port which is allowed by these ports for `access` is also allowed by `other`
+    fn is_subset_of(&self, other: &Ports, access: AccessNet) -> bool {
+        match (self.allowed(), other.allowed()) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(ports), Some(other)) => ports.iter().all(|p| {
+                // Connecting to port 0 always fails, so allowing it does not grant anything
+                (*p == 0 && access == AccessNet::ConnectTcp) || other.binary_search(p).is_ok()
+            }),
+        }
+    }
+
+    /// Get the ports for which a rule must be added when the access is handled
+    fn rule_ports(&self) -> &[u16] {
+        match self {
+            Self::Any | Self::None => &[],
+            Self::Only(ports) => ports,
+        }
+    }
+
+    /// Get the ports which were added, or `None` if any port is allowed
+    fn listed(&self) -> Option<&[u16]> {
+        match self {
+            Self::Any => None,
+            Self::None => Some(&[]),
+            Self::Only(ports) => Some(ports),
+        }
+    }
+}
+
+/// What to do when restricting using rules which grant full access, see
+/// [`Rules::on_fully_permissive`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PermissivePolicy {
+    /// Restrict anyway, which has no effect other than the cost of restricting
+    #[default]
+    Enforce,
+    /// Don't restrict, and report [`RulesetStatus::NotEnforced`] from
+    /// [`Rules::restrict_self_with_status`]
+    ///
+    /// With the `tracing` feature, a warning is emitted when such rules are added to a command.
+    Skip,
+    /// Fail with [`Error::FullyPermissive`], since the rules are likely to be misconfigured
+    Error,
+}
+
+/// The access which is granted to a path, see [`Rules::from_command_args`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccessMode {
+    /// See [`Rules::add_read_only`]
+    ReadOnly,
+    /// See [`Rules::add_read_write`]
+    ReadWrite,
+    /// See [`Rules::add_write_only`]
+    WriteOnly,
 }
 
 impl Rules {
@@ -92,192 +607,2458 @@ impl Rules {
         Default::default()
     }
 
+    /// Create rules which grant read-only access to the standard system directories, which most
+    /// commands need to run
+    ///
+    /// This grants `/usr`, `/etc`, `/dev`, `/bin`, `/lib`, `/lib64`, and `/proc/self`, skipping
+    /// those which do not exist on this host.  Since the rules are applied in the command,
+    /// `/proc/self` grants the `/proc` directory of the command itself, but not of any process that
+    /// it spawns.  Since read access includes executing files, this is enough to run dynamically
+    /// linked binaries from the system.  More paths can be added to the rules as usual:
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::system_readonly().add_read_write("/tmp/work");
+    /// ```
+    ///
+    /// Note that `/etc` often contains secrets (e.g., `/etc/shadow` is only protected by its
+    /// permissions), and `/dev` includes devices like `/dev/mem` for commands which are root.
+    pub fn system_readonly() -> Self {
+        let mut rules = Self::new();
+        for path in [
+            "/usr",
+            "/etc",
+            "/dev",
+            "/bin",
+            "/lib",
+            "/lib64",
+            "/proc/self",
+        ] {
+            if Path::new(path).exists() {
+                rules = rules.add_read_only(path);
+            }
+        }
+        rules
+    }
+
+    /// Create rules which grant read-only access to each file that the process with the given
+    /// `pid` currently has open
+    ///
+    /// This reads `/proc/<pid>/fd`, so the rules are a snapshot of the files which are open at the
+    /// time of the call.  Descriptors which do not refer to a path on the filesystem (e.g.,
+    /// sockets, pipes, and anonymous inodes) and files which have been deleted are skipped.
+    pub fn from_open_files_of(pid: u32) -> io::Result<Self> {
+        let mut rules = Self::new();
+        for entry in fs::read_dir(format!("/proc/{pid}/fd"))? {
+            // The descriptor may have been closed since the directory was read
+            let Ok(target) = fs::read_link(entry?.path()) else {
+                continue;
+            };
+
+            // Descriptors which are not backed by a path look like `socket:[1234]`
+            if !target.is_absolute() || target.as_os_str().as_bytes().ends_with(b" (deleted)") {
+                continue;
+            }
+
+            if !rules.read_only.contains(&target) {
+                rules = rules.add_read_only(target);
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Grant read access to each shared library which `binary` depends on, along with its dynamic
+    /// linker
+    ///
+    /// This parses the `DT_NEEDED` entries of the ELF file recursively, and resolves them in the
+    /// same way as the dynamic linker (see [`ld.so(8)`](https://www.man7.org/linux/man-pages/man8/ld.so.8.html)):
+    /// using `DT_RPATH`, `DT_RUNPATH`, the directories in `/etc/ld.so.conf`, and then the default
+    /// directories.  `LD_LIBRARY_PATH` is not used.  This grants a tight set of libraries, rather
+    /// than all of `/usr/lib`, but `binary` itself must still be granted separately.
+    ///
+    /// The libraries are a snapshot at the time of the call, so libraries which are loaded at
+    /// runtime using `dlopen` (e.g., plugins, or NSS modules for user lookups) are not covered,
+    /// and upgrading a library may require the rules to be created again.
+    ///
+    /// Fails if `binary` is not an ELF file, or if a library can not be found.
+    pub fn allow_shared_libs_of(mut self, binary: impl AsRef<Path>) -> io::Result<Self> {
+        merge_vec(&mut self.read_only, elf::shared_libs_of(binary.as_ref())?);
+        Ok(self)
+    }
+
+    /// Grant read access to everything which the dynamic linker needs to load shared libraries
+    /// from the system
+    ///
+    /// This grants `/etc/ld.so.cache`, `/etc/ld.so.preload`, the directories in `/etc/ld.so.conf`
+    /// (and the files that it includes), and the default library directories `/lib64`,
+    /// `/usr/lib64`, `/lib`, and `/usr/lib`, skipping those which do not exist on this host.  The
+    /// dynamic linker itself lives in one of these directories.  With this, a dynamically linked
+    /// binary only needs to be granted itself to run, rather than failing with a confusing "No
+    /// such file or directory" when its libraries can not be loaded.
+    ///
+    /// This is broader than [`Rules::allow_shared_libs_of`], since it grants every library on the
+    /// system, but it also covers libraries which are loaded at runtime using `dlopen`.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// let rules = Rules::new().with_dynamic_linker().add_read_only("/usr/bin/node");
+    /// Command::new("/usr/bin/node").restrict(Arc::new(rules)).status()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn with_dynamic_linker(mut self) -> Self {
+        merge_vec(&mut self.read_only, elf::linker_paths());
+        self
+    }
+
+    /// Create rules which grant `access` to each file that is named in the arguments of `cmd`
+    ///
+    /// This is a heuristic for wrapping simple tools which operate on the files that they are
+    /// given (e.g., `gzip file.txt`): any argument which names an existing path, relative to the
+    /// working directory of `cmd`, and for which `predicate` returns `true` is granted.  Since
+    /// arguments are not typed, this may grant access to an argument that was not meant as a
+    /// path (or miss a path that is embedded in an option such as `--output=file`), so
+    /// `predicate` should be used to select the arguments that are known to be files.
+    ///
+    /// Paths are canonicalised, so they do not depend on the working directory, and paths which
+    /// do not exist yet (e.g., output files) are skipped, as landlock can only grant access to
+    /// existing files.  Use `as_std` to inspect a [`tokio::process::Command`].
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use leucite::{AccessMode, Rules};
+    /// let mut cmd = Command::new("gzip");
+    /// cmd.arg("-k").arg("file.txt");
+    /// let rules = Rules::new()
+    ///     .add_read_only("/usr")
+    ///     .merge_beneath(Rules::from_command_args(&cmd, AccessMode::ReadWrite, |_| true));
+    /// ```
+    pub fn from_command_args(
+        cmd: &Command,
+        access: AccessMode,
+        mut predicate: impl FnMut(&Path) -> bool,
+    ) -> Self {
+        let cwd = cmd.get_current_dir().unwrap_or(Path::new("."));
+        let mut rules = Self::new();
+        for arg in cmd.get_args() {
+            let arg = Path::new(arg);
+            if arg.as_os_str().is_empty() || !predicate(arg) {
+                continue;
+            }
+
+            let Ok(path) = fs::canonicalize(cwd.join(arg)) else {
+                continue;
+            };
+            let paths = match access {
+                AccessMode::ReadOnly => &mut rules.read_only,
+                AccessMode::ReadWrite => &mut rules.read_write,
+                AccessMode::WriteOnly => &mut rules.write_only,
+            };
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        rules
+    }
+
     /// Add a read-only path to the rules
     pub fn add_read_only(mut self, p: impl Into<PathBuf>) -> Self {
         self.read_only.push(p.into());
         self
     }
 
+    /// Add a path to the rules from which files may be read and executed
+    ///
+    /// This always grants `Execute`, `ReadFile`, and `ReadDir`, regardless of
+    /// [`Rules::read_access`], so combined with [`Rules::read_access_bits`] it allows running the
+    /// binaries in one tree while denying the execution of files which are only readable:
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// let rules = Rules::new()
+    ///     .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+    ///     .add_read_execute("/usr/bin")
+    ///     .add_read_only("/srv/data");
+    ///
+    /// assert!(rules.missing_access("/usr/bin/python3", AccessFs::Execute).is_empty());
+    /// assert_eq!(
+    ///     rules.missing_access("/srv/data/script", AccessFs::Execute),
+    ///     AccessFs::Execute,
+    /// );
+    /// ```
+    pub fn add_read_execute(mut self, p: impl Into<PathBuf>) -> Self {
+        self.read_execute.push(p.into());
+        self
+    }
+
     /// Add a read/write path to the rules
     pub fn add_read_write(mut self, p: impl Into<PathBuf>) -> Self {
         self.read_write.push(p.into());
         self
     }
 
+    /// Add a read-only path to the rules, with some read/write paths beneath it
+    ///
+    /// This is useful to grant read-only access to a project, while still allowing writes to some
+    /// directories within it (e.g., `target/` or `node_modules/`).  The paths in
+    /// `writable_subpaths` are relative to `root`, and this fails with [`Error::NotBeneathRoot`]
+    /// if one of them is absolute or contains a `..` component, since it could then grant write
+    /// access outside of `root`.
+    ///
+    /// This works because landlock is additive: the rights granted on a path are the union of all
+    /// of the rules for that path and its parents, so a more specific rule can _add_ rights within
+    /// its subtree.  Note that the opposite is not possible, rights can never be _removed_ from a
+    /// subtree.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only_with_writable(
+    ///     "/tmp/project",
+    ///     &["target".into(), "node_modules".into()],
+    /// )?;
+    /// # Ok::<_, leucite::Error>(())
+    /// ```
+    pub fn add_read_only_with_writable(
+        mut self,
+        root: impl Into<PathBuf>,
+        writable_subpaths: &[PathBuf],
+    ) -> Result<Self, Error> {
+        let root = root.into();
+        if let Some(path) = writable_subpaths
+            .iter()
+            .find(|p| p.is_absolute() || p.components().any(|c| c == Component::ParentDir))
+        {
+            return Err(Error::NotBeneathRoot {
+                root,
+                path: path.clone(),
+            });
+        }
+        self.read_write
+            .extend(writable_subpaths.iter().map(|p| root.join(p)));
+        self.read_only.push(root);
+        Ok(self)
+    }
+
     /// Add a write-only path to the rules
     pub fn add_write_only(mut self, p: impl Into<PathBuf>) -> Self {
         self.write_only.push(p.into());
         self
     }
 
-    /// Add a port to which the command can connect port to the rules
-    pub fn add_connect_port(mut self, p: u16) -> Self {
-        self.connect_ports.push(p);
+    /// Add a path to the rules with exactly the given access rights
+    ///
+    /// Unlike the other methods to add paths, the rights are not affected by
+    /// [`Rules::read_access_bits`], [`Rules::write_access_bits`], or
+    /// [`Rules::allow_tmp_execution`].  This allows granting a combination of rights which the
+    /// other methods can't express, e.g., a writable directory in which existing files can not be
+    /// truncated, so that files can only be created and appended to:
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::{ABI, Access, AccessFs};
+    /// let rules = Rules::new().add_path_access(
+    ///     "/var/log/app",
+    ///     AccessFs::from_all(ABI::V4) & !AccessFs::Truncate,
+    /// );
+    ///
+    /// assert_eq!(
+    ///     rules.missing_access("/var/log/app/out.log", AccessFs::WriteFile | AccessFs::Truncate),
+    ///     AccessFs::Truncate,
+    /// );
+    /// ```
+    ///
+    /// Rights which are not handled by the [requested ABI](Rules::with_abi) are dropped, since
+    /// they can't be denied anyway (e.g., `Truncate` is always allowed with ABI 1 and 2).
+    pub fn add_path_access(
+        mut self,
+        p: impl Into<PathBuf>,
+        access: impl Into<BitFlags<AccessFs>>,
+    ) -> Self {
+        self.path_access.push((p.into(), access.into()));
         self
     }
 
-    /// Add a port to which the command can bind to the rules
-    pub fn add_bind_port(mut self, p: u16) -> Self {
-        self.bind_ports.push(p);
-        self
+    /// Add a single file which may be read to the rules
+    ///
+    /// This only grants `ReadFile`, so unlike [`Rules::add_read_only`], it doesn't grant the
+    /// right to list or execute anything, and is intended for a single file rather than a
+    /// directory.  Note that if `p` is a directory, every file beneath it may be read.
+    pub fn add_read_only_file(self, p: impl Into<PathBuf>) -> Self {
+        self.add_path_access(p, AccessFs::ReadFile)
     }
 
-    /// Restrict the _current process_ using these rules
-    ///
-    /// To restrict a _command's execution_, see [`CommandExt::restrict`]
+    /// Add a single file which may be read and written to the rules
     ///
-    /// # Deprecated
-    ///
-    /// This function will be removed in version 2.0.0. Prefer using the unsafe function
-    /// [`Rules::restrict_self`], which has the same functionality, but is unsafe.
+    /// This only grants `ReadFile`, `WriteFile`, and `Truncate`, which is useful to hand a command
+    /// a single scratch file in an otherwise read-only tree: the file can be overwritten, but no
+    /// new entries can be created or removed next to it.  Since no entries can be created, the
+    /// file must exist before the command opens it.  Note that if `p` is a directory, every file
+    /// beneath it may be read and written.
     ///
-    /// # SAFETY
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// let rules = Rules::new()
+    ///     .add_read_only("/srv/project")
+    ///     .add_read_write_file("/srv/project/output.txt");
     ///
-    /// Caller must ensure that all potential side-effects of calling this function have been
-    /// mitigated.  This includes, connections on ports that will be blocked, as well as file that
-    /// may no longer be accessible.
-    #[deprecate_until::deprecate_until(
-        since = "1.1.0",
-        remove = ">= 2.0.0",
-        note = "Prefer `Rules::restrict_self`"
-    )]
-    pub fn restrict(&self) -> Result<(), Error> {
-        // SAFETY: This is unsafe, but this function must remain until 2.0.0.
-        unsafe { self.restrict_self() }
+    /// assert!(rules
+    ///     .missing_access("/srv/project/output.txt", AccessFs::WriteFile)
+    ///     .is_empty());
+    /// assert_eq!(
+    ///     rules.missing_access("/srv/project/other.txt", AccessFs::WriteFile),
+    ///     AccessFs::WriteFile,
+    /// );
+    /// ```
+    pub fn add_read_write_file(self, p: impl Into<PathBuf>) -> Self {
+        self.add_path_access(
+            p,
+            AccessFs::ReadFile | AccessFs::WriteFile | AccessFs::Truncate,
+        )
     }
 
-    /// Restrict the _current process_ using these rules
+    /// Allow files to be renamed or linked from or to `p` across directories (`Refer`)
     ///
-    /// To restrict a _command's execution_, see [`CommandExt::restrict`]
+    /// Landlock denies renaming or linking a file into a different directory (e.g., writing a
+    /// temporary file and then renaming it over the target, to replace it atomically) with
+    /// `EXDEV` unless `Refer` is granted on _both_ the source and the destination directory, in
+    /// addition to the rights to remove the source and create the destination.  Renames within
+    /// the same directory never need `Refer`.
     ///
-    /// # SAFETY
+    /// [`Rules::add_read_write`] already grants `Refer`, since it is part of
+    /// [`AccessFs::from_write`] with ABI 2 and later, so this is only needed when the write
+    /// rights have been overridden using [`Rules::write_access_bits`], or when the paths are
+    /// added using [`Rules::add_path_access`].  With ABI 1 (see [`Rules::with_abi`]), `Refer`
+    /// can not be granted, and renaming or linking across directories is always denied.
     ///
-    /// Caller must ensure that all potential side-effects of calling this function have been
-    /// mitigated.  This includes, connections on ports that will be blocked, as well as file that
-    /// may no longer be accessible.
-    pub unsafe fn restrict_self(&self) -> Result<(), Error> {
-        let abi = ABI::V4;
-        let rules = Ruleset::default()
-            .handle_access(AccessFs::from_all(abi))
-            .map_err(Error::AccessFs)?
-            .handle_access(AccessNet::from_all(abi))
-            .map_err(Error::AcessNet)?
-            .create()
-            .map_err(Error::CreateRuleset)?;
-
-        let rules = if self.bind_ports.is_empty() {
-            rules.add_rule(NetPort::new(0, AccessNet::BindTcp))
-        } else {
-            rules.add_rules(
-                self.bind_ports
-                    .iter()
-                    .map(|p| Ok(NetPort::new(*p, AccessNet::BindTcp))),
-            )
-        }
-        .map_err(Error::SetBindPorts)?;
-
-        let rules = if self.connect_ports.is_empty() {
-            rules.add_rule(NetPort::new(0, AccessNet::ConnectTcp))
-        } else {
-            rules.add_rules(
-                self.connect_ports
-                    .iter()
-                    .map(|p| Ok(NetPort::new(*p, AccessNet::ConnectTcp))),
-            )
-        }
-        .map_err(Error::SetConnectPorts)?;
-
-        let status = rules
-            .add_rules(path_beneath_rules(
-                &self.read_only,
-                AccessFs::from_read(abi),
-            ))
-            .map_err(Error::AccessFs)?
-            .add_rules(path_beneath_rules(
-                &self.write_only,
-                AccessFs::from_write(abi),
-            ))
-            .map_err(Error::AccessFs)?
-            .add_rules(path_beneath_rules(
-                &self.read_write,
-                AccessFs::from_all(abi),
-            ))
-            .map_err(Error::AccessFs)?
-            .restrict_self()
-            .map_err(Error::RestrictThread)?;
-
-        if let RulesetStatus::NotEnforced = status.ruleset {
-            return Err(Error::LandlockNotSupported);
-        }
-        Ok(())
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// let rules = Rules::new()
+    ///     .write_access_bits(AccessFs::WriteFile | AccessFs::MakeReg | AccessFs::RemoveFile)
+    ///     .add_read_write("/srv/incoming")
+    ///     .add_read_write("/srv/data")
+    ///     .add_refer("/srv/incoming")
+    ///     .add_refer("/srv/data");
+    ///
+    /// assert!(rules.missing_access("/srv/data/file", AccessFs::Refer).is_empty());
+    /// ```
+    pub fn add_refer(self, p: impl Into<PathBuf>) -> Self {
+        self.add_path_access(p, AccessFs::Refer)
     }
-}
-
-/// Extension for [`Command`] or [`tokio::process::Command`] that restricts a command once it is
-/// spawned to be limited in its environment
-// Sealed as downstream implementations are very unlikely to be necessary and sealing this trait
-// allows us to add functions without being a breaking change
-pub trait CommandExt: private::Sealed {
-    /// Restrict the filesystem access for this command based on the provided rules
-    fn restrict(&mut self, rules: Arc<Rules>) -> &mut Self;
 
-    /// Restrict the filesystem access for this command based on the provided rules if `rules` is
-    /// `Some`
-    fn restrict_if(&mut self, rules: Option<Arc<Rules>>) -> &mut Self {
-        if let Some(rules) = rules {
-            self.restrict(rules)
-        } else {
-            self
-        }
+    /// Add a read-only path to the rules, failing if the path can not be opened
+    ///
+    /// Unlike [`Rules::add_read_only`], which defers any errors until the rules are applied, this
+    /// validates the path immediately.
+    pub fn try_add_read_only(self, p: impl Into<PathBuf>) -> Result<Self, Error> {
+        Ok(self.add_read_only(check_path(p.into())?))
     }
 
-    /// Restrict the maxmimum memory usage for the command
+    /// Add a read-only path to the rules, failing if the access would apply to more than the path
+    /// itself
     ///
-    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_DATA`
-    fn max_memory(&mut self, max_memory: MemorySize) -> &mut Self;
-
-    /// Restrict the maxmimum memory usage for the command if `max_memory` is `Some`
+    /// Landlock can not grant access to a single level of a directory (see
+    /// [Recursion](Rules#recursion)), so rather than silently granting access to every path
+    /// beneath a directory, this fails with [`Error::RecursiveDirectory`] if `p` is a directory,
+    /// and with [`Error::InvalidPath`] if it can not be opened.  Anything else (e.g., a file or a
+    /// device) is added as with [`Rules::add_read_only`].
     ///
-    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_DATA`
-    fn max_memory_if(&mut self, max_memory: Option<MemorySize>) -> &mut Self {
-        if let Some(max_memory) = max_memory {
-            self.max_memory(max_memory)
-        } else {
-            self
+    /// ```no_run
+    /// # use leucite::{Error, Rules};
+    /// let rules = Rules::new().try_add_read_only_shallow("/etc/hosts")?;
+    /// assert!(matches!(
+    ///     Rules::new().try_add_read_only_shallow("/etc"),
+    ///     Err(Error::RecursiveDirectory { .. }),
+    /// ));
+    /// # Ok::<_, Error>(())
+    /// ```
+    pub fn try_add_read_only_shallow(self, p: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = check_path(p.into())?;
+        if path.is_dir() {
+            return Err(Error::RecursiveDirectory { path });
         }
+        Ok(self.add_read_only(path))
     }
 
-    /// Restrict the maximum file size that the command may create
+    /// Add a read/write path to the rules, failing if the path can not be opened
     ///
-    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_FSIZE`
-    fn max_file_size(&mut self, max_file_size: MemorySize) -> &mut Self;
+    /// Unlike [`Rules::add_read_write`], which defers any errors until the rules are applied, this
+    /// validates the path immediately.
+    pub fn try_add_read_write(self, p: impl Into<PathBuf>) -> Result<Self, Error> {
+        Ok(self.add_read_write(check_path(p.into())?))
+    }
 
-    /// Restrict the maximum file size that the command may create if `max_file_size` is `Some`
+    /// Add a write-only path to the rules, failing if the path can not be opened
     ///
-    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_FSIZE`
-    fn max_file_size_if(&mut self, max_file_size: Option<MemorySize>) -> &mut Self {
-        if let Some(max_file_size) = max_file_size {
-            self.max_file_size(max_file_size)
-        } else {
-            self
-        }
+    /// Unlike [`Rules::add_write_only`], which defers any errors until the rules are applied, this
+    /// validates the path immediately.
+    pub fn try_add_write_only(self, p: impl Into<PathBuf>) -> Result<Self, Error> {
+        Ok(self.add_write_only(check_path(p.into())?))
     }
 
-    /// Restrict the maximum number of threads that the command may create
+    /// Add a port to which the command can connect port to the rules
     ///
-    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NPROC`
+    /// **Note:** the port is allowed for connections to any host, since landlock can not restrict
+    /// by address, see the [type-level docs](Rules#network).
+    ///
+    /// Only TCP ports are restricted: landlock (and so these rules) can not restrict UDP, so the
+    /// command may always send and receive UDP datagrams on any port (e.g., for DNS).  Run the
+    /// command in a new network namespace to deny UDP entirely.
+    pub fn add_connect_port(mut self, p: u16) -> Self {
+        self.connect_ports.add(p);
+        self
+    }
+
+    /// Add a port to which the command can connect on any host to the rules
+    ///
+    /// This is the same as [`Rules::add_connect_port`]: landlock can not restrict the address of
+    /// a connection, so a port is always allowed for every host, see the
+    /// [type-level docs](Rules#network).  Prefer this name where a reader might otherwise assume
+    /// that the port is only allowed for a particular service.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// // Allows HTTPS to every host, not only to `api.example.com`
+    /// let rules = Rules::new().add_connect_port_any_host(443);
+    ///
+    /// assert_eq!(rules, Rules::new().add_connect_port(443));
+    /// ```
+    pub fn add_connect_port_any_host(self, p: u16) -> Self {
+        self.add_connect_port(p)
+    }
+
+    /// Add a range of ports to which the command can connect to the rules
+    ///
+    /// This is the same as calling [`Rules::add_connect_port`] for each port in `range`, except
+    /// that ports which were already added are skipped.  Landlock has a rule for each port, so a
+    /// large range (e.g., the ephemeral range `32768..=60999`) adds that many rules, which makes
+    /// restricting take a few milliseconds longer.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new()
+    ///     .add_connect_port(8080)
+    ///     .add_connect_port_range(8000..=8099);
+    ///
+    /// assert_eq!(rules.connect_ports().map(<[u16]>::len), Some(100));
+    /// ```
+    pub fn add_connect_port_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.connect_ports.add_range(range);
+        self
+    }
+
+    /// Add a port to which the command can bind to the rules
+    ///
+    /// Like [`Rules::add_connect_port`], this only restricts TCP ports, and the port may be bound
+    /// on any local address.  Add port 0 to allow the command to bind to an ephemeral port which is
+    /// picked by the kernel.
+    pub fn add_bind_port(mut self, p: u16) -> Self {
+        self.bind_ports.add(p);
+        self
+    }
+
+    /// Add a range of ports to which the command can bind to the rules, see
+    /// [`Rules::add_connect_port_range`]
+    pub fn add_bind_port_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.bind_ports.add_range(range);
+        self
+    }
+
+    /// Remove a read-only path from the rules
+    ///
+    /// Only entries which are exactly equal to `p` are removed, so this can not narrow the access
+    /// granted by a parent directory.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let base = Rules::new().add_read_only("/usr").add_read_only("/srv/shared");
+    /// let narrowed = base.clone().remove_read_only("/srv/shared");
+    ///
+    /// assert_eq!(narrowed, Rules::new().add_read_only("/usr"));
+    /// ```
+    pub fn remove_read_only(mut self, p: impl AsRef<Path>) -> Self {
+        self.read_only.retain(|path| path != p.as_ref());
+        self
+    }
+
+    /// Remove a path from which files may be read and executed from the rules, see
+    /// [`Rules::remove_read_only`]
+    pub fn remove_read_execute(mut self, p: impl AsRef<Path>) -> Self {
+        self.read_execute.retain(|path| path != p.as_ref());
+        self
+    }
+
+    /// Remove a read/write path from the rules, see [`Rules::remove_read_only`]
+    pub fn remove_read_write(mut self, p: impl AsRef<Path>) -> Self {
+        self.read_write.retain(|path| path != p.as_ref());
+        self
+    }
+
+    /// Remove a write-only path from the rules, see [`Rules::remove_read_only`]
+    pub fn remove_write_only(mut self, p: impl AsRef<Path>) -> Self {
+        self.write_only.retain(|path| path != p.as_ref());
+        self
+    }
+
+    /// Remove a path which was added with [`Rules::add_path_access`] from the rules, see
+    /// [`Rules::remove_read_only`]
+    pub fn remove_path_access(mut self, p: impl AsRef<Path>) -> Self {
+        self.path_access.retain(|(path, _)| path != p.as_ref());
+        self
+    }
+
+    /// Remove a port to which the command can connect from the rules
+    ///
+    /// This has no effect if the rules allow connecting to any port (e.g., after
+    /// [`Rules::client_only`]), since they can not express "any port except this one".
+    pub fn remove_connect_port(mut self, p: u16) -> Self {
+        self.connect_ports.remove(p);
+        self
+    }
+
+    /// Remove a port to which the command can bind from the rules, see
+    /// [`Rules::remove_connect_port`]
+    pub fn remove_bind_port(mut self, p: u16) -> Self {
+        self.bind_ports.remove(p);
+        self
+    }
+
+    /// Get the read-only paths of the rules, in the order in which they were added
+    ///
+    /// Together with the other accessors, this allows inspecting a policy (e.g., to log it or
+    /// assert on it in tests).  See [`Rules::describe`] for a summary of what the rules grant.
+    ///
+    /// ```
+    /// # use std::path::PathBuf;
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").add_connect_port(443);
+    ///
+    /// assert_eq!(rules.read_only_paths(), [PathBuf::from("/usr")]);
+    /// assert_eq!(rules.connect_ports(), Some(&[443][..]));
+    /// assert_eq!(rules.client_only().connect_ports(), None);
+    /// ```
+    pub fn read_only_paths(&self) -> &[PathBuf] {
+        &self.read_only
+    }
+
+    /// Get the paths from which files may be read and executed, see [`Rules::read_only_paths`]
+    pub fn read_execute_paths(&self) -> &[PathBuf] {
+        &self.read_execute
+    }
+
+    /// Get the read/write paths of the rules, see [`Rules::read_only_paths`]
+    pub fn read_write_paths(&self) -> &[PathBuf] {
+        &self.read_write
+    }
+
+    /// Get the write-only paths of the rules, see [`Rules::read_only_paths`]
+    pub fn write_only_paths(&self) -> &[PathBuf] {
+        &self.write_only
+    }
+
+    /// Get the paths added by [`Rules::add_path_access`] along with their rights, see
+    /// [`Rules::read_only_paths`]
+    pub fn path_access(&self) -> &[(PathBuf, BitFlags<AccessFs>)] {
+        &self.path_access
+    }
+
+    /// Get the TCP ports to which the command may connect, or `None` if any port is allowed
+    ///
+    /// The ports are in the order in which they were added, and an empty list means that no port
+    /// may be used.
+    pub fn connect_ports(&self) -> Option<&[u16]> {
+        self.connect_ports.listed()
+    }
+
+    /// Get the TCP ports to which the command may bind, or `None` if any port is allowed, see
+    /// [`Rules::connect_ports`]
+    pub fn bind_ports(&self) -> Option<&[u16]> {
+        self.bind_ports.listed()
+    }
+
+    /// Configure the network access for an outbound client: the command may connect to any TCP
+    /// port, but may not bind to any port (so it can not listen for connections).
+    ///
+    /// This replaces any ports which have been added so far.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").client_only();
+    /// ```
+    pub fn client_only(mut self) -> Self {
+        self.connect_ports = Ports::Any;
+        self.bind_ports = Ports::None;
+        self
+    }
+
+    /// Don't restrict network access at all: the command may bind and connect to any TCP port
+    ///
+    /// This replaces any ports which have been added so far.
+    pub fn allow_all_network(mut self) -> Self {
+        self.connect_ports = Ports::Any;
+        self.bind_ports = Ports::Any;
+        self
+    }
+
+    /// Deny all network access: the command may neither bind nor connect to any TCP port
+    ///
+    /// This is the default for new rules, and replaces any ports which have been added so far.
+    /// Ports which are added afterwards are allowed again.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").deny_all_network();
+    /// assert_eq!(rules.bind_ports(), Some(&[][..]));
+    /// assert_eq!(rules.connect_ports(), Some(&[][..]));
+    /// ```
+    pub fn deny_all_network(mut self) -> Self {
+        self.connect_ports = Ports::None;
+        self.bind_ports = Ports::None;
+        self
+    }
+
+    /// Allow the command to bind to any TCP port, without changing the ports to which it may
+    /// connect
+    ///
+    /// This replaces any bind ports which have been added so far.
+    pub fn allow_all_bind(mut self) -> Self {
+        self.bind_ports = Ports::Any;
+        self
+    }
+
+    /// Allow the command to connect to any TCP port, without changing the ports to which it may
+    /// bind
+    ///
+    /// This replaces any connect ports which have been added so far.
+    pub fn allow_all_connect(mut self) -> Self {
+        self.connect_ports = Ports::Any;
+        self
+    }
+
+    /// Configure the network access for a server: the command may bind to the given TCP ports, but
+    /// may not connect to any port.
+    ///
+    /// This replaces any ports which have been added so far.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").server_only([8080, 8443]);
+    /// ```
+    pub fn server_only(mut self, bind_ports: impl IntoIterator<Item = u16>) -> Self {
+        self.bind_ports = Ports::None;
+        for port in bind_ports {
+            self.bind_ports.add(port);
+        }
+        self.connect_ports = Ports::None;
+        self
+    }
+
+    /// Merge the grants from `other` into these rules, keeping every grant from both
+    ///
+    /// Landlock computes the rights for a path as the _union_ of the rules for that path and all
+    /// of its parents, so every rule is kept, even when one path is beneath another:
+    ///
+    /// - If a nested path has _more_ rights than its parent (e.g., `/data` is read-only and
+    ///   `/data/out` is read/write), the nested rule adds rights within its subtree.
+    /// - If a nested path has _fewer_ rights than its parent (e.g., `/data` is read/write and
+    ///   `/data/out` is read-only), the nested rule has no effect, since rights can never be
+    ///   removed from a subtree.
+    ///
+    /// Identical grants are de-duplicated.  A port is allowed if it is allowed by either of the
+    /// rules, and a filesystem type is denied if it is denied by either of the rules.  The merged
+    /// rules are only in audit mode (see [`Rules::audit_only`]) if both of the rules are, so
+    /// merging never switches off enforcement.  For the same reason, if either of the rules
+    /// requests an ABI (see [`Rules::with_abi`]), the higher of the ABIs is requested, so that a
+    /// fragment with a lower ABI can not stop the rights of a higher ABI (e.g., TCP ports with ABI
+    /// 4) from being restricted.  Those rights are then only granted where they are added.
+    ///
+    /// Each path keeps the rights which its own rules grant on it.  The merged rules use the
+    /// [`Rules::read_access_bits`] and [`Rules::write_access_bits`] of these rules, and the paths
+    /// of rules which grant different rights for "read" or "write" access, or request a different
+    /// ABI, are stored as [`Rules::add_path_access`] where needed.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let base = Rules::new().add_read_only("/usr").add_read_only("/data");
+    /// let task = Rules::new().add_read_write("/data/out");
+    ///
+    /// // `/data` is read-only, except for `/data/out`
+    /// let rules = base.merge_beneath(task);
+    /// ```
+    pub fn merge_beneath(mut self, mut other: Rules) -> Self {
+        let merged = Rules {
+            abi: match (self.abi, other.abi) {
+                (None, None) => None,
+                _ => Some(i32::max(self.abi() as i32, other.abi() as i32)),
+            },
+            read_access: self.read_access,
+            write_access: self.write_access,
+            ..Rules::new()
+        };
+        let access = |rules: &Rules| {
+            (
+                rules.read_access(),
+                rules.write_access(),
+                rules.abi() as i32,
+            )
+        };
+
+        // The grants of either rules would change their rights if they were kept as they are
+        let mut pinned = Vec::new();
+        for rules in [&mut self, &mut other] {
+            if access(rules) != access(&merged) {
+                pinned.extend(rules.path_grants().map(|(p, a)| (p.to_path_buf(), a)));
+                for paths in [
+                    &mut rules.read_only,
+                    &mut rules.read_execute,
+                    &mut rules.read_write,
+                    &mut rules.write_only,
+                ] {
+                    paths.clear();
+                }
+                rules.path_access.clear();
+            }
+        }
+
+        self.abi = merged.abi;
+        merge_vec(&mut self.read_only, other.read_only);
+        merge_vec(&mut self.read_execute, other.read_execute);
+        merge_vec(&mut self.read_write, other.read_write);
+        merge_vec(&mut self.write_only, other.write_only);
+        merge_vec(&mut self.path_access, other.path_access);
+        self.bind_ports.merge(other.bind_ports);
+        self.connect_ports.merge(other.connect_ports);
+        merge_vec(&mut self.denied_fs_types, other.denied_fs_types);
+        // Merging must never switch off enforcement, so only stay in audit mode if both are
+        self.audit_only &= other.audit_only;
+        self.log_denials |= other.log_denials;
+        self.writable_noexec |= other.writable_noexec;
+        self.scope_abstract_unix_sockets |= other.scope_abstract_unix_sockets;
+        self.scope_signals |= other.scope_signals;
+        for (path, access) in pinned {
+            self.push_grant(path, access);
+        }
+        self
+    }
+
+    /// Request `abi` when restricting using these rules, instead of [`ABI::V4`]
+    ///
+    /// The requested ABI determines which rights are handled (and so denied unless granted), and
+    /// what [`Rules::read_access`] and [`Rules::write_access`] include by default.  If the running
+    /// kernel supports a lower ABI, only the rights which it supports are enforced (see
+    /// [`Rules::effective_abi`]), so a lower ABI does not make restricting succeed on more
+    /// kernels, but pins exactly what is enforced across kernels.
+    ///
+    /// Network rules require ABI 4 or later: with a lower ABI, network access is not restricted
+    /// at all, and any ports which were added are silently ignored.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::ABI;
+    /// let rules = Rules::new().add_read_only("/usr").with_abi(ABI::V1);
+    /// assert_eq!(rules.abi() as i32, 1);
+    /// ```
+    pub fn with_abi(mut self, abi: ABI) -> Self {
+        self.abi = Some(abi as i32);
+        self
+    }
+
+    /// Get the landlock ABI which is requested when restricting using these rules, see
+    /// [`Rules::with_abi`]
+    pub fn abi(&self) -> ABI {
+        self.abi.map_or(REQUESTED_ABI, ABI::from)
+    }
+
+    /// Combine these rules with `other`, keeping every grant from both
+    ///
+    /// This is the same as [`Rules::merge_beneath`], which describes how overlapping grants are
+    /// combined, and is intended for layering policies from reusable fragments:
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let system = Rules::new().add_read_only("/usr").add_read_only("/etc");
+    /// let task = Rules::new().add_read_write("/tmp/work").add_connect_port(443);
+    ///
+    /// let rules = system.merge(task);
+    /// ```
+    pub fn merge(self, other: Rules) -> Self {
+        self.merge_beneath(other)
+    }
+
+    /// Combine `other` into these rules in place, see [`Rules::merge`]
+    pub fn extend(&mut self, other: &Rules) {
+        *self = std::mem::take(self).merge_beneath(other.clone());
+    }
+
+    /// Keep only the grants of these rules which are also granted by `other`, so that the result
+    /// is at least as strict as both
+    ///
+    /// This is the counterpart of [`Rules::merge`], for enforcing several policies at once (e.g.,
+    /// an organization-wide baseline and the policy of a task): a command restricted by the result
+    /// may only do what both policies allow.  The result is a subset of each of the rules (see
+    /// [`Rules::is_subset_of`]).
+    ///
+    /// Access rights are intersected for each path, taking into account the rights which it
+    /// inherits from its ancestors in either of the rules, so that:
+    ///
+    /// - A path which is granted by only one of the rules is dropped, unless the other rules grant
+    ///   one of its ancestors.
+    /// - A path keeps only the rights which both rules grant, e.g., read/write intersected with
+    ///   read-only is read-only, and read-only intersected with write-only grants nothing.
+    /// - A path beneath a path of the other rules keeps the rights granted by its own rule and
+    ///   inherited from the other, e.g., `/data` read/write intersected with `/data/out` read-only
+    ///   is `/data/out` read-only.
+    ///
+    /// Paths are compared without accessing the filesystem in the same way as
+    /// [`Rules::is_subset_of`].  The remaining grants are stored as read-only, read/execute,
+    /// read/write or write-only when their rights match, and as [`Rules::add_path_access`]
+    /// otherwise.
+    ///
+    /// A port is allowed if it is allowed by both rules, and a filesystem type or scope is denied
+    /// if it is denied by either of the rules.  The rules are only enforced in audit mode if both
+    /// are (see [`Rules::audit_only`]), and the higher of the requested ABIs is requested, since it
+    /// restricts more.  Rights which are not handled by the lower ABI are only granted where the
+    /// rules with the higher ABI grant them.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let baseline = Rules::new()
+    ///     .add_read_write("/srv")
+    ///     .add_read_only("/usr")
+    ///     .add_connect_port(443);
+    /// let task = Rules::new()
+    ///     .add_read_only("/srv/data")
+    ///     .add_read_write("/tmp")
+    ///     .add_connect_port(443)
+    ///     .add_connect_port(80);
+    ///
+    /// let rules = baseline.intersect(task);
+    /// assert_eq!(rules.to_string(), "ro:/srv/data connect:[443] bind:[]");
+    /// ```
+    pub fn intersect(self, other: Rules) -> Self {
+        let grants: Vec<_> = self
+            .intersect_grants(&other)
+            .chain(other.intersect_grants(&self))
+            .collect();
+
+        let mut rules = Rules {
+            read_only: Vec::new(),
+            read_execute: Vec::new(),
+            read_write: Vec::new(),
+            write_only: Vec::new(),
+            path_access: Vec::new(),
+            abi: match (self.abi, other.abi) {
+                (None, None) => None,
+                _ => Some(i32::max(self.abi() as i32, other.abi() as i32)),
+            },
+            read_access: match (self.read_access, other.read_access) {
+                (None, None) => None,
+                _ => Some(self.read_access() & other.read_access()),
+            },
+            write_access: match (self.write_access, other.write_access) {
+                (None, None) => None,
+                _ => Some(self.write_access() & other.write_access()),
+            },
+            audit_only: self.audit_only && other.audit_only,
+            log_denials: self.log_denials || other.log_denials,
+            writable_noexec: self.writable_noexec || other.writable_noexec,
+            scope_abstract_unix_sockets: self.scope_abstract_unix_sockets
+                || other.scope_abstract_unix_sockets,
+            scope_signals: self.scope_signals || other.scope_signals,
+            ..self
+        };
+        rules.bind_ports.intersect(other.bind_ports);
+        rules.connect_ports.intersect(other.connect_ports);
+        merge_vec(&mut rules.denied_fs_types, other.denied_fs_types);
+
+        for (path, access) in grants {
+            rules.push_grant(path, access);
+        }
+        rules.normalize()
+    }
+
+    /// Grant exactly `access` on `path`, as read-only, read/execute, read/write or write-only
+    /// when the rights match, and as [`Rules::add_path_access`] otherwise
+    fn push_grant(&mut self, path: PathBuf, access: BitFlags<AccessFs>) {
+        let (read, write) = (self.read_access(), self.write_access());
+        if access == read {
+            merge_vec(&mut self.read_only, vec![path]);
+        } else if access == read_execute_access() {
+            merge_vec(&mut self.read_execute, vec![path]);
+        } else if access == self.writable_access(read | write) {
+            merge_vec(&mut self.read_write, vec![path]);
+        } else if access == self.writable_access(write) {
+            merge_vec(&mut self.write_only, vec![path]);
+        } else {
+            merge_vec(&mut self.path_access, vec![(path, access)]);
+        }
+    }
+
+    /// Iterate over each granted path along with the rights on it which are also granted by
+    /// `other`, skipping paths for which no right remains
+    fn intersect_grants<'a>(
+        &'a self,
+        other: &'a Rules,
+    ) -> impl Iterator<Item = (PathBuf, BitFlags<AccessFs>)> + 'a {
+        // Rights which are not handled by the ABI of `other` are granted everywhere by it
+        let unhandled = !AccessFs::from_all(other.abi());
+        self.path_grants().filter_map(move |(path, access)| {
+            let access = access & (other.granted_access(path) | unhandled);
+            (!access.is_empty()).then(|| (path.to_path_buf(), access))
+        })
+    }
+
+    /// Sort the paths and ports of the rules and remove any duplicates, so that rules which grant
+    /// the same access in a different order compare equal (see the [type-level docs](Rules#equality))
+    ///
+    /// Paths which were added by [`Rules::add_path_access`] more than once are combined into a
+    /// single entry with the rights of each.  This does not change what the rules grant.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let a = Rules::new().add_read_only("/usr").add_read_only("/etc").add_connect_port(443);
+    /// let b = Rules::new().add_connect_port(443).add_read_only("/etc").add_read_only("/usr");
+    ///
+    /// assert_ne!(a, b);
+    /// assert_eq!(a.normalize(), b.normalize());
+    /// ```
+    pub fn normalize(mut self) -> Self {
+        for paths in [
+            &mut self.read_only,
+            &mut self.read_execute,
+            &mut self.read_write,
+            &mut self.write_only,
+        ] {
+            paths.sort_unstable();
+            paths.dedup();
+        }
+        self.path_access.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.path_access
+            .dedup_by(|(path, access), (prev_path, prev_access)| {
+                let duplicate = path == prev_path;
+                if duplicate {
+                    *prev_access |= *access;
+                }
+                duplicate
+            });
+        for ports in [&mut self.bind_ports, &mut self.connect_ports] {
+            match ports {
+                // Allowing no ports is the same as the default of an empty list
+                Ports::None => *ports = Ports::default(),
+                Ports::Only(ports) => {
+                    ports.sort_unstable();
+                    ports.dedup();
+                }
+                Ports::Any => {}
+            }
+        }
+        self.denied_fs_types.sort_unstable();
+        self.denied_fs_types.dedup();
+        self
+    }
+
+    /// Resolve every path of the rules to its canonical form, following any symlinks
+    ///
+    /// Landlock grants access to the file which a path refers to when restricting, so a rule for a
+    /// symlink (e.g., a working directory under `/tmp` which is a symlink to `/private/tmp`)
+    /// applies to its target.  However, [`Rules::missing_access`] and [`Rules::is_subset_of`]
+    /// compare paths as they are written, and a symlink which is changed between creating the rules
+    /// and restricting would change what the rules grant.  Canonicalizing the rules up front makes
+    /// both consistent with what is enforced.
+    ///
+    /// This reads the filesystem, and fails if any of the paths does not exist or can not be
+    /// resolved.  The error includes the path.
+    ///
+    /// ```no_run
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_write("/var/tmp/link").canonicalize()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn canonicalize(mut self) -> io::Result<Self> {
+        for path in self.paths_mut() {
+            *path = fs::canonicalize(&*path)
+                .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        }
+        Ok(self)
+    }
+
+    /// Every path of these rules, in the order that [`Rules::check_filesystem_types`] checks them
+    fn paths_mut(&mut self) -> impl Iterator<Item = &mut PathBuf> {
+        (self.read_only.iter_mut())
+            .chain(&mut self.read_execute)
+            .chain(&mut self.read_write)
+            .chain(&mut self.write_only)
+            .chain(self.path_access.iter_mut().map(|(path, _)| path))
+    }
+
+    /// Override the access rights which are granted for "read" access (i.e., by
+    /// [`Rules::add_read_only`] and [`Rules::add_read_write`])
+    ///
+    /// By default, this is [`AccessFs::from_read`] for the requested ABI, which is the same for
+    /// all ABIs:
+    ///
+    /// | ABI   | Rights                                   |
+    /// |-------|------------------------------------------|
+    /// | V1-V6 | `Execute`, `ReadFile`, `ReadDir`         |
+    ///
+    /// Notably, this includes `Execute`.  Overriding the rights pins exactly what "read" means,
+    /// regardless of the ABI.  Rights which are not supported by the running kernel are not
+    /// enforced.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// // Allow reading, but not executing, files
+    /// let rules = Rules::new()
+    ///     .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+    ///     .add_read_only("/srv/data");
+    /// ```
+    pub fn read_access_bits(mut self, access: impl Into<BitFlags<AccessFs>>) -> Self {
+        self.read_access = Some(access.into());
+        self
+    }
+
+    /// Override the access rights which are granted for "write" access (i.e., by
+    /// [`Rules::add_write_only`] and [`Rules::add_read_write`])
+    ///
+    /// By default, this is [`AccessFs::from_write`] for the requested ABI, which grows with each
+    /// ABI:
+    ///
+    /// | ABI   | Rights                                                                       |
+    /// |-------|------------------------------------------------------------------------------|
+    /// | V1    | `WriteFile`, `RemoveDir`, `RemoveFile`, `MakeChar`, `MakeDir`, `MakeReg`,    |
+    /// |       | `MakeSock`, `MakeFifo`, `MakeBlock`, `MakeSym`                               |
+    /// | V2    | V1 and `Refer`                                                               |
+    /// | V3-V4 | V2 and `Truncate`                                                            |
+    /// | V5-V6 | V4 and `IoctlDev`                                                            |
+    ///
+    /// Overriding the rights pins exactly what "write" means, regardless of the ABI.  Rights which
+    /// are not supported by the running kernel are not enforced.
+    pub fn write_access_bits(mut self, access: impl Into<BitFlags<AccessFs>>) -> Self {
+        self.write_access = Some(access.into());
+        self
+    }
+
+    /// Choose whether files may be executed from writable paths (i.e., those granted by
+    /// [`Rules::add_read_write`] and [`Rules::add_write_only`]), such as a scratch directory in
+    /// `/tmp`
+    ///
+    /// **Warning:** allowing execution from a writable path lets the command write an arbitrary
+    /// binary and then run it, which turns any write access into the ability to run code that was
+    /// not installed on the host (e.g., a downloaded exploit).  Only allow this for commands which
+    /// genuinely need it, such as compilers which run the programs that they build.
+    ///
+    /// Execution is currently allowed by default, since [`Rules::read_access`] includes
+    /// `Execute`.  Disallowing it removes `Execute` from the rights granted on each writable path,
+    /// but since landlock rights can not be removed from a subtree, files beneath a writable path
+    /// may still be executed if one of its ancestors is granted read access (e.g., a scratch
+    /// directory in `/tmp` when `/` is read-only).  Merging rules disallows execution if either of
+    /// the rules disallows it.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// let rules = Rules::new()
+    ///     .add_read_only("/usr")
+    ///     .add_read_write("/tmp/scratch")
+    ///     .allow_tmp_execution(false);
+    ///
+    /// assert_eq!(
+    ///     rules.missing_access("/tmp/scratch/a.out", AccessFs::Execute),
+    ///     AccessFs::Execute,
+    /// );
+    /// ```
+    pub fn allow_tmp_execution(mut self, allow: bool) -> Self {
+        self.writable_noexec = !allow;
+        self
+    }
+
+    /// Whether files may be executed from writable paths, see [`Rules::allow_tmp_execution`]
+    pub fn allows_tmp_execution(&self) -> bool {
+        !self.writable_noexec
+    }
+
+    /// Get the rights which are granted on a writable path, given the rights that it is added with
+    pub(crate) fn writable_access(&self, access: BitFlags<AccessFs>) -> BitFlags<AccessFs> {
+        if self.writable_noexec {
+            access & !AccessFs::Execute
+        } else {
+            access
+        }
+    }
+
+    /// Don't enforce these rules when restricting, which is the closest analog to a "complain
+    /// mode" for developing a policy
+    ///
+    /// Landlock has no mode which logs accesses without denying them, so restricting using these
+    /// rules succeeds without restricting anything, and the command has full access.  Check the
+    /// paths that the command uses with [`Rules::missing_access`] to find out what _would_ be
+    /// denied.  See the [`Rules`] documentation for more information.
+    ///
+    /// Since nothing is enforced, these rules are never a subset of rules which are enforced (see
+    /// [`Rules::is_subset_of`]), and merging them into other rules also makes those rules audit
+    /// only.
+    pub fn audit_only(mut self) -> Self {
+        self.audit_only = true;
+        self
+    }
+
+    /// Log the accesses which are denied by these rules to the audit subsystem
+    ///
+    /// By default, landlock does not log denials in a command which is executed after
+    /// restricting, as the command may be expected to be denied.  Logging them allows a platform to
+    /// tell a user _why_ their command was denied, using `leucite::audit::AuditListener`
+    /// (with the `audit` feature) or `auditd`.  This requires landlock ABI 7 (Linux 6.15), and is
+    /// ignored on older kernels.  Denials are only logged if audit is enabled (see
+    /// `auditctl(8)`).
+    ///
+    /// With `auditd`, each denial is logged as a `LANDLOCK_ACCESS` record, which names the
+    /// landlock domain of the command, the rights which were denied (e.g., `fs.read_file`), and
+    /// the path or port.  A `LANDLOCK_DOMAIN` record describes each domain, including the PID and
+    /// executable of the process which created it, so the denials of a command can be found with
+    /// `ausearch -m LANDLOCK_ACCESS,LANDLOCK_DOMAIN -i`.  The audit subsystem drops records when
+    /// its backlog is full (see `auditctl -b`), so a command which is denied in a loop may not
+    /// have all of its denials logged.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// // Logs a denial of `fs.read_file` on `/etc/shadow`
+    /// Command::new("cat")
+    ///     .arg("/etc/shadow")
+    ///     .restrict(Arc::new(Rules::new().add_read_only("/usr").log_denials()))
+    ///     .status()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn log_denials(mut self) -> Self {
+        self.log_denials = true;
+        self
+    }
+
+    /// Deny connecting to abstract UNIX sockets which were created outside of the sandbox
+    ///
+    /// Abstract UNIX sockets don't exist on the filesystem, so path-based rules can't restrict
+    /// them, which lets a command talk to daemons on the host which listen on one (e.g., D-Bus or
+    /// X11).  Scoping denies connecting (or sending datagrams) to any abstract socket which was not
+    /// created by a process in the same or a nested sandbox.
+    ///
+    /// This requires landlock ABI 6 (Linux 6.12) on the running kernel, regardless of
+    /// [`Rules::with_abi`].  On older kernels, it is not enforced, and
+    /// [`Rules::restrict_self_with_status`] reports [`RulesetStatus::PartiallyEnforced`].
+    pub fn scope_abstract_unix_sockets(mut self) -> Self {
+        self.scope_abstract_unix_sockets = true;
+        self
+    }
+
+    /// Deny sending signals to processes outside of the sandbox
+    ///
+    /// The command may still signal itself and any process which it spawned after being
+    /// restricted.  Like [`Rules::scope_abstract_unix_sockets`], this requires landlock ABI 6 on
+    /// the running kernel and is not enforced on older kernels.
+    pub fn scope_signals(mut self) -> Self {
+        self.scope_signals = true;
+        self
+    }
+
+    /// Get the landlock scopes which are requested, see [`Rules::scope_abstract_unix_sockets`]
+    /// and [`Rules::scope_signals`]
+    fn scopes(&self) -> BitFlags<Scope> {
+        let mut scopes = BitFlags::empty();
+        if self.scope_abstract_unix_sockets {
+            scopes |= Scope::AbstractUnixSocket;
+        }
+        if self.scope_signals {
+            scopes |= Scope::Signal;
+        }
+        scopes
+    }
+
+    /// Whether these rules are not enforced, see [`Rules::audit_only`]
+    pub fn is_audit_only(&self) -> bool {
+        self.audit_only
+    }
+
+    /// Choose what happens when restricting using rules which grant full access (see
+    /// [`Rules::is_fully_permissive`])
+    ///
+    /// Restricting using such rules still costs several syscalls for each command that is spawned,
+    /// and is likely to be a misconfiguration.  By default, the rules are enforced anyway.
+    pub fn on_fully_permissive(mut self, policy: PermissivePolicy) -> Self {
+        self.permissive_policy = policy;
+        self
+    }
+
+    /// Whether restricting using these rules would have no effect
+    ///
+    /// This is the case when all of the following are true:
+    ///
+    /// - `/` is granted every access right which is handled by the rules: each right of the
+    ///   requested ABI (see [`AccessFs::from_all`]), along with the rights of
+    ///   [`Rules::read_access`] and [`Rules::write_access`].  The rights may be combined from
+    ///   several grants of `/`, e.g., [`Rules::add_read_only`] and [`Rules::add_write_only`].
+    /// - both binding and connecting are allowed on any port (see [`Rules::allow_all_network`])
+    /// - no filesystem types are denied
+    ///
+    /// A grant of each top level directory rather than of `/` itself is not considered to be
+    /// fully permissive, since new directories can't be created beneath `/`.
+    pub fn is_fully_permissive(&self) -> bool {
+        let handled = AccessFs::from_all(self.abi()) | self.read_access() | self.write_access();
+        self.granted_access(Path::new("/")).contains(handled)
+            && *self.ports(AccessNet::BindTcp) == Ports::Any
+            && *self.ports(AccessNet::ConnectTcp) == Ports::Any
+            && self.denied_fs_types.is_empty()
+            && self.scopes().is_empty()
+    }
+
+    /// Whether restricting using these rules should be skipped, since they are audit only or grant
+    /// full access
+    fn skip_enforcement(&self) -> Result<bool, Error> {
+        if self.audit_only {
+            return Ok(true);
+        }
+        match self.permissive_policy {
+            PermissivePolicy::Enforce => Ok(false),
+            PermissivePolicy::Skip => Ok(self.is_fully_permissive()),
+            PermissivePolicy::Error if self.is_fully_permissive() => Err(Error::FullyPermissive),
+            PermissivePolicy::Error => Ok(false),
+        }
+    }
+
+    /// Get the rights in `access` which would be denied for `path` by these rules
+    ///
+    /// Like landlock, a path is granted the rights of each of the paths that it is beneath.  The
+    /// paths are compared without accessing the filesystem in the same way as
+    /// [`Rules::is_subset_of`], so `path` should be absolute and not contain `..`.  Rights which
+    /// are not handled by the kernel are not denied, which is not taken into account here.
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// # use landlock::AccessFs;
+    /// let rules = Rules::new().add_read_only("/usr");
+    /// assert!(rules.missing_access("/usr/bin/sh", AccessFs::Execute).is_empty());
+    /// assert_eq!(
+    ///     rules.missing_access("/usr/lib", AccessFs::WriteFile),
+    ///     AccessFs::WriteFile,
+    /// );
+    /// ```
+    pub fn missing_access(
+        &self,
+        path: impl AsRef<Path>,
+        access: impl Into<BitFlags<AccessFs>>,
+    ) -> BitFlags<AccessFs> {
+        access.into() & !self.granted_access(path.as_ref())
+    }
+
+    /// Get the rights which are granted to `path` by these rules, as the union of the rights on
+    /// the path and each of its ancestors
+    fn granted_access(&self, path: &Path) -> BitFlags<AccessFs> {
+        if path.components().any(|c| c == Component::ParentDir) {
+            return BitFlags::empty();
+        }
+        self.path_grants()
+            .filter(|(p, _)| path.starts_with(p))
+            .fold(BitFlags::empty(), |acc, (_, access)| acc | access)
+    }
+
+    /// Check whether these rules grant no more than `ceiling`
+    ///
+    /// This is intended for platforms which accept policies from tenants, to reject a policy which
+    /// exceeds the bounds set by the platform before anything is spawned.  The rules are a subset
+    /// of `ceiling` when:
+    ///
+    /// - for each path which is granted by these rules, the access rights that `ceiling` grants on
+    ///   that path or any of its ancestors include the rights that are granted by these rules.
+    ///   Like landlock itself, the rights granted by several ancestors in `ceiling` are combined.
+    /// - each TCP port to which these rules allow binding or connecting is also allowed by
+    ///   `ceiling`
+    /// - each filesystem type which is denied by `ceiling` is also denied by these rules
+    /// - each scope which is requested by `ceiling` is also requested by these rules
+    /// - these rules request at least the ABI that `ceiling` requests, since the rights which are
+    ///   not handled by a lower ABI are granted everywhere
+    ///
+    /// Paths are compared by their components, so `/usr/` and `/usr/./` are the same path, but
+    /// `/usr` is not an ancestor of `/usrlocal`.  The filesystem is not accessed, so symlinks are
+    /// not resolved, and a path containing `..` is never considered to be beneath any other path
+    /// since it may escape it.  Relative paths are only beneath relative paths.
+    pub fn is_subset_of(&self, ceiling: &Rules) -> bool {
+        if ceiling.audit_only {
+            return true;
+        }
+
+        !self.audit_only
+            && self.abi() as i32 >= ceiling.abi() as i32
+            && self
+                .path_grants()
+                .all(|(path, access)| ceiling.granted_access(path).contains(access))
+            && [AccessNet::BindTcp, AccessNet::ConnectTcp]
+                .into_iter()
+                .all(|access| {
+                    self.ports(access)
+                        .is_subset_of(ceiling.ports(access), access)
+                })
+            && ceiling
+                .denied_fs_types
+                .iter()
+                .all(|t| self.denied_fs_types.contains(t))
+            && self.scopes().contains(ceiling.scopes())
+    }
+
+    /// Iterate over each granted path along with the access rights that are granted on it
+    fn path_grants(&self) -> impl Iterator<Item = (&Path, BitFlags<AccessFs>)> {
+        let (read, write) = (self.read_access(), self.write_access());
+        let grants = [
+            (&self.read_only, read),
+            (&self.read_execute, read_execute_access()),
+            (&self.write_only, self.writable_access(write)),
+            (&self.read_write, self.writable_access(read | write)),
+        ];
+        grants
+            .into_iter()
+            .flat_map(|(paths, access)| paths.iter().map(move |p| (p.as_path(), access)))
+            .chain(self.handled_path_access())
+    }
+
+    /// Iterate over the paths added by [`Rules::add_path_access`], along with their rights which
+    /// are handled by the requested ABI
+    fn handled_path_access(&self) -> impl Iterator<Item = (&Path, BitFlags<AccessFs>)> {
+        let handled = AccessFs::from_all(self.abi());
+        self.path_access
+            .iter()
+            .map(move |(path, access)| (path.as_path(), *access & handled))
+    }
+
+    /// Get the access rights which are granted for "read" access, see
+    /// [`Rules::read_access_bits`]
+    pub fn read_access(&self) -> BitFlags<AccessFs> {
+        self.read_access
+            .unwrap_or_else(|| AccessFs::from_read(self.abi()))
+    }
+
+    /// Get the access rights which are granted for "write" access, see
+    /// [`Rules::write_access_bits`]
+    pub fn write_access(&self) -> BitFlags<AccessFs> {
+        self.write_access
+            .unwrap_or_else(|| AccessFs::from_write(self.abi()))
+    }
+
+    /// Refuse to grant access to any path which resides on one of the given filesystem types
+    ///
+    /// Landlock can not restrict access based on the type of a filesystem, so instead each granted
+    /// path is checked using `statfs(2)` before the rules are applied, and restricting fails with
+    /// [`Error::DeniedFilesystem`] if any path resides on a denied filesystem.  This guards against
+    /// accidentally exposing network storage (see [`FsType::NETWORK`]) to untrusted code.
+    ///
+    /// Note that only the granted paths themselves are checked, not filesystems which are mounted
+    /// beneath them.
+    pub fn deny_filesystem_types(mut self, types: &[FsType]) -> Self {
+        self.denied_fs_types.extend_from_slice(types);
+        self
+    }
+
+    /// Check that none of the granted paths reside on a filesystem that is denied using
+    /// [`Rules::deny_filesystem_types`]
+    ///
+    /// This is also checked when the rules are applied, but can be used to fail early.  Paths which
+    /// can not be inspected are skipped, as they will fail when the rules are applied.
+    pub fn check_filesystem_types(&self) -> Result<(), Error> {
+        if self.denied_fs_types.is_empty() {
+            return Ok(());
+        }
+
+        for path in self
+            .read_only
+            .iter()
+            .chain(&self.read_execute)
+            .chain(&self.read_write)
+            .chain(&self.write_only)
+            .chain(self.path_access.iter().map(|(path, _)| path))
+        {
+            self.check_filesystem_type(path)?;
+        }
+        Ok(())
+    }
+
+    /// Make sure that `path` is not on a denied filesystem, skipping it if it can't be inspected
+    fn check_filesystem_type(&self, path: &Path) -> Result<(), Error> {
+        match FsType::of(path) {
+            Ok(fs_type) if self.denied_fs_types.contains(&fs_type) => {
+                Err(Error::DeniedFilesystem {
+                    path: path.to_path_buf(),
+                    fs_type,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Restrict the _current process_ using these rules
+    ///
+    /// To restrict a _command's execution_, see [`CommandExt::restrict`]
+    ///
+    /// # Deprecated
+    ///
+    /// This function will be removed in version 2.0.0. Prefer using the unsafe function
+    /// [`Rules::restrict_self`], which has the same functionality, but is unsafe, or
+    /// [`Rules::restrict_self_with_status`] to also get the [`RulesetStatus`].
+    ///
+    /// # SAFETY
+    ///
+    /// Caller must ensure that all potential side-effects of calling this function have been
+    /// mitigated.  This includes, connections on ports that will be blocked, as well as file that
+    /// may no longer be accessible.
+    #[deprecate_until::deprecate_until(
+        since = "1.1.0",
+        remove = ">= 2.0.0",
+        note = "Prefer `Rules::restrict_self`"
+    )]
+    pub fn restrict(&self) -> Result<(), Error> {
+        // SAFETY: This is unsafe, but this function must remain until 2.0.0.
+        unsafe { self.restrict_self() }
+    }
+
+    /// Restrict the _current process_ using these rules
+    ///
+    /// This fails with [`Error::LandlockNotSupported`] if nothing could be enforced, but does not
+    /// report whether the rules were only partially enforced, see
+    /// [`Rules::restrict_self_with_status`] for that.
+    ///
+    /// To restrict a _command's execution_, see [`CommandExt::restrict`]
+    ///
+    /// # SAFETY
+    ///
+    /// Caller must ensure that all potential side-effects of calling this function have been
+    /// mitigated.  This includes, connections on ports that will be blocked, as well as file that
+    /// may no longer be accessible.
+    pub unsafe fn restrict_self(&self) -> Result<(), Error> {
+        // SAFETY: The caller upholds the same requirements
+        unsafe { self.restrict_self_with_status() }.map(|_| ())
+    }
+
+    /// Restrict the _current process_ using these rules, returning the status of the restriction
+    ///
+    /// Landlock features which are not supported by the running kernel are not enforced, in which
+    /// case [`RestrictStatus::ruleset`] is [`RulesetStatus::PartiallyEnforced`], which is useful to
+    /// log when a sandbox is not as tight as expected.  See [`RestrictStatus::effective_abi`] for
+    /// the ABI which was actually used.  Like [`Rules::restrict_self`], this fails with
+    /// [`Error::LandlockNotSupported`] if nothing could be enforced.
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_self_with_status(&self) -> Result<RestrictStatus, Error> {
+        if self.skip_enforcement()? {
+            return Ok(self.unenforced_status());
+        }
+
+        self.check_filesystem_types()?;
+
+        // Each path is added on its own, so that a failure can be attributed to it
+        let mut ruleset = self.create_ruleset()?;
+        for (path, access) in self.path_grants() {
+            ruleset = ruleset
+                .add_rules(path_beneath_rules([path], access))
+                .map_err(|source| Error::AddPathRule {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+        }
+
+        self.restrict_status(self.restrict_ruleset(ruleset)?)
+    }
+
+    /// Restrict the _current process_ using these rules, returning a [`Restricted`] token as proof
+    ///
+    /// This behaves like [`Rules::restrict_self`].  Note that the token only proves that
+    /// restricting succeeded, not that the rules are enforced (e.g., if they are
+    /// [audit only](Rules::audit_only)), see [`Rules::restrict_self_with_status`] for that.
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_self_guarded(&self) -> Result<Restricted, Error> {
+        // SAFETY: The caller upholds the same requirements
+        unsafe { self.restrict_self() }?;
+        Ok(Restricted {
+            _not_send: std::marker::PhantomData,
+        })
+    }
+
+    /// Restrict the _current process_ using these rules, enforcing as much as the running kernel
+    /// supports rather than failing if it does not support landlock at all
+    ///
+    /// Rights which are not supported by the running kernel are never enforced (i.e., the
+    /// ruleset uses [`CompatLevel::BestEffort`](landlock::CompatLevel::BestEffort)), which
+    /// [`Rules::restrict_self`] reports as [`RulesetStatus::PartiallyEnforced`].  This goes one
+    /// step further and also succeeds with [`RulesetStatus::NotEnforced`] on kernels without
+    /// landlock, instead of failing with [`Error::LandlockNotSupported`], so that the same binary
+    /// can be deployed across kernels.  Check the returned status to find out what was enforced.
+    ///
+    /// To restrict a _command's execution_, see [`CommandExt::restrict_best_effort`]
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_best_effort(&self) -> Result<RestrictStatus, Error> {
+        // SAFETY: The caller upholds the same requirements
+        match unsafe { self.restrict_self_with_status() } {
+            Err(Error::LandlockNotSupported) => Ok(self.unenforced_status()),
+            res => res,
+        }
+    }
+
+    /// Restrict the _current process_ using these rules, failing with [`Error::InvalidPath`] if
+    /// any of the paths can not be opened
+    ///
+    /// [`Rules::restrict_self`] skips paths which can't be opened, in which case they are simply
+    /// not granted.  This checks each path first instead, like [`Rules::validate`].
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_strict(&self) -> Result<RestrictStatus, Error> {
+        for (path, _) in self.path_grants() {
+            check_path(path.to_path_buf())?;
+        }
+        // SAFETY: The caller upholds the same requirements
+        unsafe { self.restrict_self_with_status() }
+    }
+
+    /// Restrict the current thread using `ruleset`, which was created from these rules
+    fn restrict_ruleset(&self, ruleset: RulesetCreated) -> Result<RulesetStatus, Error> {
+        if self.log_denials && kernel_abi_version() >= 7 {
+            return restrict_self_logged(ruleset, self.abi());
+        }
+        ruleset
+            .restrict_self()
+            .map(|status| status.ruleset)
+            .map_err(Error::RestrictThread)
+    }
+
+    /// The status of rules which are not enforced, see [`Rules::skip_enforcement`]
+    fn unenforced_status(&self) -> RestrictStatus {
+        RestrictStatus {
+            ruleset: RulesetStatus::NotEnforced,
+            effective_abi: self.effective_abi(),
+        }
+    }
+
+    /// Create a ruleset which handles the access of these rules, with the rules for the ports
+    /// already added, so that only the rules for the paths are left to be added
+    fn create_ruleset(&self) -> Result<RulesetCreated, Error> {
+        let abi = self.abi();
+
+        // Network access which is allowed on any port must not be handled at all
+        let mut access_net = AccessNet::from_all(abi);
+        if *self.ports(AccessNet::BindTcp) == Ports::Any {
+            access_net.remove(AccessNet::BindTcp);
+        }
+        if *self.ports(AccessNet::ConnectTcp) == Ports::Any {
+            access_net.remove(AccessNet::ConnectTcp);
+        }
+
+        let rules = Ruleset::default()
+            .handle_access(AccessFs::from_all(abi) | self.read_access() | self.write_access())
+            .map_err(Error::AccessFs)?;
+        let rules = if access_net.is_empty() {
+            rules
+        } else {
+            rules.handle_access(access_net).map_err(Error::AcessNet)?
+        };
+        let scopes = self.scopes();
+        let rules = if scopes.is_empty() {
+            rules
+        } else {
+            rules.scope(scopes).map_err(Error::Scope)?
+        }
+        .create()
+        .map_err(Error::CreateRuleset)?;
+
+        let mut rules = rules;
+        for access in [AccessNet::BindTcp, AccessNet::ConnectTcp] {
+            for &port in self.ports(access).rule_ports() {
+                rules = rules
+                    .add_rule(NetPort::new(port, access))
+                    .map_err(|source| Error::AddPortRule {
+                        port,
+                        access,
+                        source,
+                    })?;
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Get the ports which are allowed for `access`, which is any port if the requested ABI can
+    /// not restrict network access
+    fn ports(&self, access: AccessNet) -> &Ports {
+        static ANY: Ports = Ports::Any;
+        match access {
+            _ if !AccessNet::from_all(self.abi()).contains(access) => &ANY,
+            AccessNet::BindTcp => &self.bind_ports,
+            AccessNet::ConnectTcp => &self.connect_ports,
+            _ => &ANY,
+        }
+    }
+
+    /// Convert the status of restricting using these rules, failing if nothing was enforced
+    fn restrict_status(&self, ruleset: RulesetStatus) -> Result<RestrictStatus, Error> {
+        if let RulesetStatus::NotEnforced = ruleset {
+            return Err(Error::LandlockNotSupported);
+        }
+        Ok(RestrictStatus {
+            ruleset,
+            effective_abi: self.effective_abi(),
+        })
+    }
+
+    /// Get the landlock ABI which will be enforced when restricting using these rules on the
+    /// running kernel.
+    ///
+    /// This is the highest ABI which is supported by the kernel, up to the ABI that is requested.
+    /// If landlock is not supported, this is [`ABI::Unsupported`].
+    pub fn effective_abi(&self) -> ABI {
+        ABI::from(i32::min(self.abi() as i32, kernel_abi() as i32))
+    }
+}
+
+/// The access rights which are granted by [`Rules::add_read_execute`]
+fn read_execute_access() -> BitFlags<AccessFs> {
+    AccessFs::Execute | AccessFs::ReadFile | AccessFs::ReadDir
+}
+
+/// Append each item in `other` to `vec` if it is not already present
+fn merge_vec<T: PartialEq>(vec: &mut Vec<T>, other: Vec<T>) {
+    for item in other {
+        if !vec.contains(&item) {
+            vec.push(item);
+        }
+    }
+}
+
+/// Make sure that `path` exists and can be opened in the same way that landlock will open it
+fn check_path(path: PathBuf) -> Result<PathBuf, Error> {
+    match PathFd::new(&path) {
+        Ok(_) => Ok(path),
+        Err(source) => Err(Error::InvalidPath { path, source }),
+    }
+}
+
+/// Extension for [`Command`] or [`tokio::process::Command`] that restricts a command once it is
+/// spawned to be limited in its environment
+// Sealed as downstream implementations are very unlikely to be necessary and sealing this trait
+// allows us to add functions without being a breaking change
+pub trait CommandExt: private::Sealed {
+    /// The spawned child process, either [`std::process::Child`] or
+    /// [`tokio::process::Child`](::tokio::process::Child)
+    type Child;
+
+    /// Restrict the filesystem access for this command based on the provided rules
+    ///
+    /// With the `tracing` feature, a `DEBUG` event describes the rules when they are added: the
+    /// requested and effective ABI, the number of paths, and the number of bind and connect ports
+    /// (which is not recorded if any port may be used).  Nothing is emitted once the command has
+    /// been forked, so whether the rules were enforced is only reported by
+    /// [`CommandExt::sandbox_audited`], as an `INFO` or `WARN` event when the audit is finished.
+    fn restrict(&mut self, rules: Arc<Rules>) -> &mut Self;
+
+    /// Restrict the filesystem access for this command based on the provided rules, without
+    /// failing to spawn if the running kernel does not support landlock, see
+    /// [`Rules::restrict_best_effort`]
+    fn restrict_best_effort(&mut self, rules: Arc<Rules>) -> &mut Self;
+
+    /// Restrict this command using each of the given rules in turn, stacking them
+    ///
+    /// Landlock rulesets stack: each one can only take access away, never grant it, so an access
+    /// is allowed only if _every_ one of the rules allows it.  The result is the intersection of
+    /// the rules, which is more restrictive than any one of them, and not the union that
+    /// [`Rules::merge`] creates.  This is useful to apply a fixed outer policy on top of a policy
+    /// for each command.  Landlock supports at most 16 stacked rulesets per process, including
+    /// any which were applied to the parent.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// let platform = Arc::new(Rules::new().add_read_only("/usr").add_read_write("/tmp"));
+    /// let task = Arc::new(Rules::new().add_read_only("/").add_read_write("/tmp/task"));
+    ///
+    /// // `/usr` is read-only, and only `/tmp/task` is writable
+    /// Command::new("/usr/bin/ls").restrict_all([platform, task]).status()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn restrict_all(&mut self, rules: impl IntoIterator<Item = Arc<Rules>>) -> &mut Self;
+
+    /// Restrict the filesystem access for this command based on the provided rules if `rules` is
+    /// `Some`
+    fn restrict_if(&mut self, rules: Option<Arc<Rules>>) -> &mut Self {
+        if let Some(rules) = rules {
+            self.restrict(rules)
+        } else {
+            self
+        }
+    }
+
+    /// Set the working directory of this command to `workdir`, and restrict it based on the
+    /// provided rules, with `workdir` added as a read/write path
+    ///
+    /// A command almost always needs to write to its working directory, so this sets both at once
+    /// rather than repeating the path in [`Command::current_dir`] and [`Rules::add_read_write`],
+    /// where the two can drift apart.  The path is taken explicitly, rather than read back from
+    /// the command, so that a command whose working directory was never set doesn't silently
+    /// grant write access to the working directory of the parent.  A relative `workdir` is
+    /// resolved against the working directory of the parent, like [`Command::current_dir`].
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// let rules = Arc::new(Rules::new().add_read_only("/usr"));
+    /// Command::new("/usr/bin/touch")
+    ///     .arg("output")
+    ///     .restrict_with_workdir(rules, "/tmp/job")
+    ///     .status()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn restrict_with_workdir(&mut self, rules: Arc<Rules>, workdir: impl AsRef<Path>) -> &mut Self;
+
+    /// Restrict the filesystem access for this command based on the provided rules, and spawn it
+    ///
+    /// This behaves like `.restrict(rules).spawn()`, except that if restricting the command fails,
+    /// the child reports the [`Error`] back through a pipe before failing.  The returned error then
+    /// wraps a [`ChildError`], which tells e.g. an unsupported kernel apart from a path on a denied
+    /// filesystem, where [`CommandExt::restrict`] only results in an opaque OS error.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{ChildError, CommandExt, RestrictErrorKind, Rules};
+    /// let rules = Arc::new(Rules::new().add_read_only("/usr"));
+    /// match Command::new("/usr/bin/ls").spawn_restricted(rules) {
+    ///     Ok(child) => { /* ... */ }
+    ///     Err(e) => match ChildError::of(&e) {
+    ///         Some(e) if e.kind() == RestrictErrorKind::LandlockNotSupported => { /* ... */ }
+    ///         _ => return Err(e),
+    ///     },
+    /// }
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn spawn_restricted(&mut self, rules: Arc<Rules>) -> io::Result<Self::Child>;
+
+    /// Restrict the filesystem access for this command based on the provided rules if `rules` is
+    /// `Some`, and spawn it
+    ///
+    /// See [`CommandExt::spawn_restricted`] for how a failure to restrict is reported.
+    fn spawn_restricted_if(&mut self, rules: Option<Arc<Rules>>) -> io::Result<Self::Child>;
+
+    /// Restrict the filesystem access for this command based on rules which were prepared ahead of
+    /// time, see [`PreparedRules`]
+    fn restrict_prepared(&mut self, rules: PreparedRules) -> &mut Self;
+
+    /// Restrict this command using the rules which are configured by the environment variables of
+    /// the current process, see [`Rules::from_env`]
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use leucite::CommandExt;
+    /// Command::new("/usr/bin/ls").inherit_rules_from_env()?.spawn()?;
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    fn inherit_rules_from_env(&mut self) -> Result<&mut Self, RulesFromEnvError> {
+        Ok(self.restrict(Arc::new(Rules::from_env()?)))
+    }
+
+    /// Apply each of the limits which are set in `limits` to the command
+    fn resource_limits(&mut self, limits: &ResourceLimits) -> &mut Self {
+        // A hard limit without a soft one limits both, like a limit without a hard one
+        if let Some(hard) = limits.max_memory_hard {
+            let soft = limits.max_memory.unwrap_or(hard);
+            self.resource_limit_soft_hard(Resource::Memory, soft, hard);
+        } else {
+            self.resource_limit_if(Resource::Memory, limits.max_memory);
+        }
+        if let Some(hard) = limits.max_file_size_hard {
+            let soft = limits.max_file_size.unwrap_or(hard);
+            self.resource_limit_soft_hard(Resource::FileSize, soft, hard);
+        } else {
+            self.resource_limit_if(Resource::FileSize, limits.max_file_size);
+        }
+        self.resource_limit_if(Resource::AddressSpace, limits.max_address_space)
+            .resource_limit_if(Resource::Processes, limits.max_threads)
+            .resource_limit_if(Resource::CpuTime, limits.max_cpu_time)
+            .resource_limit_if(Resource::CoreSize, limits.max_core_size)
+            .resource_limit_if(Resource::StackSize, limits.max_stack_size)
+            .resource_limit_if(Resource::OpenFiles, limits.max_open_files)
+            .resource_limit_if(Resource::Processes, limits.max_processes)
+            .resource_limit_if(Resource::PendingSignals, limits.max_pending_signals)
+            .resource_limit_if(Resource::MessageQueueBytes, limits.max_message_queue_bytes)
+            .resource_limit_if(Resource::LockedMemory, limits.max_locked_memory);
+        if let Some(value) = limits.min_nice {
+            self.min_nice(value);
+        }
+        self
+    }
+
+    /// Set the limit of `resource` for the command, in the unit of the resource (see
+    /// [`Resource`])
+    ///
+    /// This is the same as the setter of the resource (e.g., [`CommandExt::max_memory`] for
+    /// [`Resource::Memory`]), but also accepts [`LimitValue::Unlimited`] to remove the limit
+    /// rather than set it, e.g., when the parent itself runs with a limit.
+    ///
+    /// ```no_run
+    /// # use leucite::{CommandExt, LimitValue, MemorySize, Resource};
+    /// # use std::process::Command;
+    /// let child = Command::new("make")
+    ///     .resource_limit(Resource::Memory, LimitValue::Unlimited)
+    ///     .max_file_size(MemorySize::from_gib(1))
+    ///     .spawn()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn resource_limit(&mut self, resource: Resource, value: LimitValue) -> &mut Self {
+        // Exceeding the hard limit of the CPU times sends `SIGKILL` rather than `SIGXCPU`, so
+        // leave a second between them
+        let hard = match resource {
+            Resource::CpuTime => value.to_rlim().saturating_add(1),
+            Resource::RtCpuTime => value.to_rlim().saturating_add(1_000_000),
+            _ => value.to_rlim(),
+        };
+        self.resource_limit_soft_hard(resource, value, hard.into())
+    }
+
+    /// Set the limit of `resource` for the command if `value` is `Some`, see
+    /// [`CommandExt::resource_limit`]
+    fn resource_limit_if(&mut self, resource: Resource, value: Option<LimitValue>) -> &mut Self {
+        if let Some(value) = value {
+            self.resource_limit(resource, value)
+        } else {
+            self
+        }
+    }
+
+    /// Set the limit of `resource` for the command, with separate soft and hard limits
+    ///
+    /// Like [`CommandExt::max_memory_soft_hard`], the command may raise its own soft limit up to
+    /// `hard`, but never beyond it, and spawning the command fails with `EINVAL` if `soft` is
+    /// greater than `hard`.  Unlike [`CommandExt::resource_limit`], the hard limit of the CPU
+    /// times is exactly `hard`.
+    fn resource_limit_soft_hard(
+        &mut self,
+        resource: Resource,
+        soft: LimitValue,
+        hard: LimitValue,
+    ) -> &mut Self;
+
+    /// Restrict the filesystem and network access for this command based on the provided rules,
+    /// and apply each of the limits which are set in `limits`
+    ///
+    /// This is a shorthand for `.restrict(rules).resource_limits(limits)`.  See
+    /// [`SandboxProfile`] to also configure the scheduling, environment, and so on.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, MemorySize, ResourceLimits, Rules};
+    /// let rules = Arc::new(Rules::new().add_read_only("/usr"));
+    /// let limits = ResourceLimits::new()
+    ///     .max_memory(MemorySize::from_mb(100))
+    ///     .max_cpu_time(10);
+    ///
+    /// Command::new("/usr/bin/ls")
+    ///     .restrict_and_limit(rules, &limits)
+    ///     .spawn()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn restrict_and_limit(&mut self, rules: Arc<Rules>, limits: &ResourceLimits) -> &mut Self {
+        self.restrict(rules).resource_limits(limits)
+    }
+
+    /// Restrict the maxmimum memory usage for the command
+    ///
+    /// This limits the heap and private writable mappings, but not shared or file-backed
+    /// mappings, see [`CommandExt::max_address_space`] for a hard ceiling.
+    ///
+    /// To remove the limit rather than set it, e.g., when the parent itself runs with a limit, see
+    /// [`CommandExt::resource_limit`].
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_DATA`
+    fn max_memory(&mut self, max_memory: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum memory usage for the command, with separate soft and hard limits
+    ///
+    /// [`CommandExt::max_memory`] sets both limits to the same value, so the command can never
+    /// raise its limit.  With a lower `soft` limit, allocations beyond `soft` fail as usual, but
+    /// the command may raise its own soft limit up to `hard` using `setrlimit(2)` (e.g., a runtime
+    /// which grows its heap on demand), while `hard` can never be exceeded.  Spawning the command
+    /// fails with `EINVAL` if `soft` is greater than `hard`.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_DATA`
+    fn max_memory_soft_hard(&mut self, soft: MemorySize, hard: MemorySize) -> &mut Self;
+
+    /// Restrict the maxmimum memory usage for the command if `max_memory` is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_DATA`
+    fn max_memory_if(&mut self, max_memory: Option<MemorySize>) -> &mut Self {
+        if let Some(max_memory) = max_memory {
+            self.max_memory(max_memory)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the maximum size of the virtual address space of the command
+    ///
+    /// Unlike [`CommandExt::max_memory`], which only limits the heap and private writable
+    /// mappings (`RLIMIT_DATA`), this limits _all_ of the memory which the command maps, including
+    /// shared and file-backed mappings, its stack, and its executable and libraries.  This is
+    /// often what is wanted for a hard memory ceiling, since a command can escape
+    /// [`CommandExt::max_memory`] using shared mappings.  However, it also counts memory which is
+    /// reserved but never used, so runtimes which reserve a large address space upfront (e.g.,
+    /// JavaScript engines, Go, or programs using AddressSanitizer) may fail to start with a tight
+    /// limit.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_AS`
+    fn max_address_space(&mut self, size: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum size of the virtual address space of the command if `size` is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_AS`
+    fn max_address_space_if(&mut self, size: Option<MemorySize>) -> &mut Self {
+        if let Some(size) = size {
+            self.max_address_space(size)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the maximum file size that the command may create
+    ///
+    /// A write which would extend a file beyond `max_file_size` writes as much as fits, and the
+    /// command is then sent `SIGXFSZ` when it tries to write more, which terminates it by
+    /// default.  If the command handles or ignores the signal, the write fails with `EFBIG`
+    /// instead.  A limit of zero prevents the command from writing to any regular file, although it
+    /// may still create empty files.  Pipes, sockets, and terminals are not affected.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_FSIZE`
+    fn max_file_size(&mut self, max_file_size: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum file size that the command may create, with separate soft and hard
+    /// limits
+    ///
+    /// Like [`CommandExt::max_memory_soft_hard`], the command may raise its own soft limit up to
+    /// `hard`, but never beyond it.  Spawning the command fails with `EINVAL` if `soft` is greater
+    /// than `hard`.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_FSIZE`
+    fn max_file_size_soft_hard(&mut self, soft: MemorySize, hard: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum file size that the command may create if `max_file_size` is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_FSIZE`
+    fn max_file_size_if(&mut self, max_file_size: Option<MemorySize>) -> &mut Self {
+        if let Some(max_file_size) = max_file_size {
+            self.max_file_size(max_file_size)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the maximum number of threads that the command may create
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NPROC`
     fn max_threads(&mut self, max_threads: u64) -> &mut Self;
 
-    /// Restrict the maximum number of threads that the command may create if `max_threads` is `Some`
+    /// Restrict the maximum number of threads that the command may create if `max_threads` is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NPROC`
+    fn max_threads_if(&mut self, max_threads: Option<u64>) -> &mut Self {
+        if let Some(max_threads) = max_threads {
+            self.max_threads(max_threads)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the amount of CPU time that the command may use, in seconds
+    ///
+    /// Once the command has used `seconds` of CPU time, it is sent `SIGXCPU`, which terminates it
+    /// by default.  If the command handles the signal instead, it is killed with `SIGKILL` after
+    /// one more second of CPU time.
+    ///
+    /// Unlike [`CommandExt::hard_deadline`], this only counts the time that the command spends
+    /// running, so a command which sleeps or waits for input is not affected.  The limit applies
+    /// to each process separately, so a command which spawns workers gives each of them their own
+    /// budget, see [`CommandExt::max_cpu_bandwidth`] for a limit across all of them.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_CPU`
+    fn max_cpu_time(&mut self, seconds: u64) -> &mut Self;
+
+    /// Restrict the amount of CPU time that the command may use, in seconds, if `seconds` is
+    /// `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_CPU`
+    fn max_cpu_time_if(&mut self, seconds: Option<u64>) -> &mut Self {
+        if let Some(seconds) = seconds {
+            self.max_cpu_time(seconds)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the amount of CPU time that the command may use under a real-time scheduling
+    /// policy without making a blocking system call
+    ///
+    /// This stops a runaway `SCHED_FIFO` or `SCHED_RR` command from starving the rest of the host.
+    /// Like [`CommandExt::max_cpu_time`], the command is sent `SIGXCPU` once it reaches the limit,
+    /// and is killed with `SIGKILL` after one more second.  The time is reset whenever the command
+    /// blocks, and is not counted at all under a normal scheduling policy.  The resolution of the
+    /// limit is one microsecond.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_RTTIME`
+    fn max_rt_cpu_time(&mut self, time: Duration) -> &mut Self;
+
+    /// Restrict the highest real-time priority that the command may set for itself using
+    /// `sched_setscheduler(2)` or `sched_setparam(2)`
+    ///
+    /// A priority of 0 prevents an unprivileged command from using a real-time scheduling policy
+    /// at all.  Processes with `CAP_SYS_NICE` (e.g., running as root) are not restricted.  Raising
+    /// the limit above the current hard limit requires `CAP_SYS_RESOURCE`, and otherwise makes
+    /// spawning the command fail.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_RTPRIO`
+    fn max_rt_priority(&mut self, priority: u64) -> &mut Self;
+
+    /// Restrict the number of signals which may be queued for the user of the command
+    ///
+    /// Real-time signals (and signals sent using `sigqueue(3)`) are queued rather than merged, so
+    /// a command could otherwise exhaust the kernel memory used for queued signals.  Once the limit
+    /// is reached, queueing a signal fails with `EAGAIN`.  Like [`CommandExt::max_processes`], the
+    /// limit is counted per real user ID, not per command.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_SIGPENDING`
+    fn max_pending_signals(&mut self, count: u64) -> &mut Self;
+
+    /// Restrict the number of bytes which may be allocated for the POSIX message queues of the
+    /// user of the command
+    ///
+    /// The size of a queue is reserved when it is created using `mq_open(3)`, including the
+    /// overhead of each message, so creating a queue which would exceed the limit fails with
+    /// `EMFILE`.  Like [`CommandExt::max_processes`], the limit is counted per real user ID, not
+    /// per command.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_MSGQUEUE`
+    fn max_message_queue_bytes(&mut self, size: MemorySize) -> &mut Self;
+
+    /// Restrict the amount of memory which the command may lock into RAM using `mlock(2)`,
+    /// `mlockall(2)`, or `MAP_LOCKED`
+    ///
+    /// Locked memory can not be swapped out, so a command could otherwise pin memory that the
+    /// rest of the host needs.  Locking more than `size` fails with `ENOMEM` (or `EAGAIN` for
+    /// `mmap(2)`).  Processes with `CAP_IPC_LOCK` (e.g., running as root) are not restricted.
+    ///
+    /// This does not replace [`CommandExt::max_memory`] or [`CommandExt::max_address_space`]:
+    /// locked memory still counts towards those limits, which bound how much memory may be mapped
+    /// at all, while this bounds how much of it may be pinned.  So this only has an effect if it
+    /// is lower than the other limits.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_MEMLOCK`
+    fn max_locked_memory(&mut self, size: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum size of the core dump which is written when the command crashes
+    ///
+    /// A core dump which would be larger than `size` is not written at all.  Note that this limit
+    /// is not enforced when `/proc/sys/kernel/core_pattern` pipes core dumps to a program (e.g.,
+    /// `systemd-coredump`), use [`CommandExt::no_core_dumps`] to disable core dumps in that case.
+    ///
+    /// See [`core(5)`](https://www.man7.org/linux/man-pages/man5/core.5.html), and
+    /// [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_CORE`
+    fn max_core_size(&mut self, size: MemorySize) -> &mut Self;
+
+    /// Don't write a core dump when the command crashes
+    ///
+    /// Crash-prone untrusted commands (e.g., when fuzzing) can otherwise quickly fill up the disk
+    /// with core dumps in their working directory.  This also prevents core dumps from being piped
+    /// to a program, if `/proc/sys/kernel/core_pattern` is configured to do so.
+    ///
+    /// See [`core(5)`](https://www.man7.org/linux/man-pages/man5/core.5.html)
+    fn no_core_dumps(&mut self) -> &mut Self;
+
+    /// Restrict the maximum size of the stack of the command
+    ///
+    /// A command which recurses too deeply is killed with `SIGSEGV` once its stack reaches this
+    /// size, rather than using up memory until it reaches [`CommandExt::max_memory`] (which does
+    /// not include the stack).  The limit also determines the default size of the stack of new
+    /// threads for many programs, so setting it too low may cause them to fail to start.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_STACK`
+    fn max_stack_size(&mut self, size: MemorySize) -> &mut Self;
+
+    /// Restrict the maximum size of the stack of the command if `size` is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_STACK`
+    fn max_stack_size_if(&mut self, size: Option<MemorySize>) -> &mut Self {
+        if let Some(size) = size {
+            self.max_stack_size(size)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the number of file descriptors that the command may have open at once
+    ///
+    /// Landlock only restricts which paths may be opened, not how many times, so an untrusted
+    /// command could otherwise exhaust the file descriptors of the host.  Once the limit is
+    /// reached, opening a file fails with `EMFILE`.  The limit counts the highest file descriptor
+    /// rather than the number of open files, so it includes the standard streams.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NOFILE`
+    fn max_open_files(&mut self, count: u64) -> &mut Self;
+
+    /// Restrict the number of file descriptors that the command may have open at once if `count`
+    /// is `Some`
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NOFILE`
+    fn max_open_files_if(&mut self, count: Option<u64>) -> &mut Self {
+        if let Some(count) = count {
+            self.max_open_files(count)
+        } else {
+            self
+        }
+    }
+
+    /// Restrict the number of processes that the user of the command may have, which stops the
+    /// command from fork-bombing the host
+    ///
+    /// **Note:** `RLIMIT_NPROC` is counted per real user ID, not per command.  Every process (and
+    /// thread) of the user counts towards the limit, including those which are not related to the
+    /// command, so a command which runs as the same user as the parent may be unable to fork at
+    /// all if the user already has `count` processes.  Run the command as a dedicated user (e.g.,
+    /// using [`uid`](std::os::unix::process::CommandExt::uid)) for the limit to apply to only that
+    /// command.  The limit is not enforced for processes with `CAP_SYS_ADMIN` or
+    /// `CAP_SYS_RESOURCE`, e.g., those running as root.
+    ///
+    /// Since threads are processes to the kernel, this is the same limit as
+    /// [`CommandExt::max_threads`].
     ///
     /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NPROC`
-    fn max_threads_if(&mut self, max_threads: Option<u64>) -> &mut Self {
-        if let Some(max_threads) = max_threads {
-            self.max_threads(max_threads)
+    fn max_processes(&mut self, count: u64) -> &mut Self;
+
+    /// Restrict the number of processes that the user of the command may have if `count` is
+    /// `Some`
+    ///
+    /// See [`CommandExt::max_processes`]
+    fn max_processes_if(&mut self, count: Option<u64>) -> &mut Self {
+        if let Some(count) = count {
+            self.max_processes(count)
         } else {
             self
         }
     }
+
+    /// Set the score which the OOM killer uses to pick a process to kill when the system is out
+    /// of memory.
+    ///
+    /// `adj` must be in the range `-1000..=1000`.  Raising the score makes the command _more_
+    /// likely to be killed under memory pressure, which is useful to make sure that untrusted
+    /// commands are killed before other services on the host.  Lowering the score below its
+    /// current value requires `CAP_SYS_RESOURCE`.
+    ///
+    /// This writes to `/proc/self/oom_score_adj`, so it must be called _before_
+    /// [`CommandExt::restrict`], unless the rules grant write access to that file.
+    ///
+    /// See [`proc(5)`](https://www.man7.org/linux/man-pages/man5/proc_pid_oom_score_adj.5.html)
+    fn oom_score_adj(&mut self, adj: i32) -> &mut Self;
+
+    /// Run the command with the `SCHED_IDLE` scheduling policy
+    ///
+    /// Processes using `SCHED_IDLE` only run when no other process wants the CPU, which is
+    /// stronger than a high nice value.  This keeps sandboxed background jobs from ever preempting
+    /// other work on the host.
+    ///
+    /// Note that an unprivileged process may switch itself back to `SCHED_OTHER` if its nice value
+    /// is within the range allowed by `RLIMIT_NICE`, so that limit should also be set (see
+    /// [`CommandExt::min_nice`]) if the command can not be trusted.
+    ///
+    /// See [`sched(7)`](https://www.man7.org/linux/man-pages/man7/sched.7.html)
+    fn idle_priority(&mut self) -> &mut Self;
+
+    /// Set the nice value of the command, which ranges from `-20` (the highest priority) to `19`
+    /// (the lowest priority)
+    ///
+    /// A higher nice value gives the command a smaller share of the CPU when other processes want
+    /// to run, so running untrusted jobs with a higher nice value than the supervisor keeps them
+    /// from starving it.  Values outside of the range are clamped.  Lowering the nice value below
+    /// that of the parent requires `CAP_SYS_NICE`, or a high enough `RLIMIT_NICE`, and otherwise
+    /// makes spawning the command fail.
+    ///
+    /// This only sets the value that the command starts with, which it may raise further, but
+    /// never lower again below the limit of [`CommandExt::min_nice`].
+    ///
+    /// See [`setpriority(2)`](https://www.man7.org/linux/man-pages/man2/setpriority.2.html)
+    fn nice(&mut self, value: i32) -> &mut Self;
+
+    /// Restrict the lowest nice value (i.e., the highest priority) that the command may set for
+    /// itself using `setpriority(2)` or `nice(2)`
+    ///
+    /// Unlike [`CommandExt::nice`], this doesn't change the nice value of the command, it bounds
+    /// how far the command may _lower_ it.  The kernel stores the limit as `RLIMIT_NICE`, which
+    /// is `20 - value`, so a `value` of `19` prevents the command from lowering its nice value at
+    /// all.  Processes with `CAP_SYS_NICE` (e.g., running as root) are not restricted.
+    ///
+    /// If the current hard limit is already lower (it is `0` by default, which forbids lowering
+    /// the nice value at all), the limit is left unchanged, since it is already stricter than
+    /// `value`, and raising it would require `CAP_SYS_RESOURCE`.
+    ///
+    /// See [`getrlimit(2)`](https://www.man7.org/linux/man-pages/man2/prlimit.2.html) and `RLIMIT_NICE`
+    fn min_nice(&mut self, value: i32) -> &mut Self;
+
+    /// Detach the command from the controlling terminal of the parent by running it in a new
+    /// session (see [`setsid(2)`](https://www.man7.org/linux/man-pages/man2/setsid.2.html))
+    ///
+    /// A command which shares the controlling terminal of its parent may be able to inject input
+    /// into that terminal using the `TIOCSTI` ioctl, which is a classic sandbox escape on kernels
+    /// which do not restrict it.  Detaching the terminal prevents this, at the cost of the command
+    /// no longer being able to interact with the terminal (e.g., for job control).
+    ///
+    /// Since the command becomes the leader of a new session and process group, this can not be
+    /// combined with [`process_group`](std::os::unix::process::CommandExt::process_group), which
+    /// causes spawning to fail.
+    fn detach_controlling_tty(&mut self) -> &mut Self;
+
+    /// Run the command as the user `uid`, dropping the privileges of the parent
+    ///
+    /// Unlike [`uid`](std::os::unix::process::CommandExt::uid), which the standard library applies
+    /// before any of the hooks of this trait, this runs in the order in which it is called, so
+    /// privileged steps like [`CommandExt::chroot`] or [`CommandExt::add_tmpfs`] can be called
+    /// first.  Once the user has been changed, the group can no longer be changed, so
+    /// [`CommandExt::set_gid`] and [`CommandExt::supplementary_groups`] must be called _before_
+    /// this, or spawning the command fails with `EPERM`.
+    ///
+    /// Call this before [`CommandExt::restrict`], so that the rules are applied to the new user
+    /// (which is the order of [`Phase::DropPrivileges`] when using [`SandboxProfile::set_uid`]).  Landlock does
+    /// not depend on the user, but any step which is added after the rules must be allowed by
+    /// both.  Changing the user requires `CAP_SETUID`.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// let status = Command::new("id")
+    ///     .set_gid(65534)
+    ///     .supplementary_groups(&[])
+    ///     .set_uid(65534)
+    ///     .restrict(Arc::new(Rules::new().add_read_execute("/usr")))
+    ///     .status()?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    ///
+    /// See [`setuid(2)`](https://www.man7.org/linux/man-pages/man2/setuid.2.html)
+    fn set_uid(&mut self, uid: u32) -> &mut Self;
+
+    /// Run the command as the group `gid`, see [`CommandExt::set_uid`]
+    ///
+    /// This must be called before [`CommandExt::set_uid`], and requires `CAP_SETGID`.
+    ///
+    /// See [`setgid(2)`](https://www.man7.org/linux/man-pages/man2/setgid.2.html)
+    fn set_gid(&mut self, gid: u32) -> &mut Self;
+
+    /// Replace the supplementary groups of the command with `groups`, see [`CommandExt::set_uid`]
+    ///
+    /// The command otherwise keeps the supplementary groups of the parent even after changing its
+    /// user and group, which may grant it access to files of those groups (e.g., `root`'s
+    /// groups).  Pass an empty list to drop all of them.  This must be called before
+    /// [`CommandExt::set_uid`], and requires `CAP_SETGID`.
+    ///
+    /// See [`setgroups(2)`](https://www.man7.org/linux/man-pages/man2/setgroups.2.html)
+    fn supplementary_groups(&mut self, groups: &[u32]) -> &mut Self;
+
+    /// Prevent the command, and every process that it executes, from gaining privileges through
+    /// `execve`, e.g., by executing a setuid or setgid binary, or one with file capabilities
+    ///
+    /// Restricting using landlock already sets this attribute, but this sets it even when no
+    /// rules are applied (e.g., with [`CommandExt::restrict_if`] and `None`, or rules which are
+    /// [audit only](Rules::audit_only)).  The attribute can never be unset.
+    ///
+    /// See [`prctl(2)`](https://www.man7.org/linux/man-pages/man2/prctl.2.html) and
+    /// `PR_SET_NO_NEW_PRIVS`
+    fn no_new_privs(&mut self) -> &mut Self;
+
+    /// Limit the command, along with every process that it spawns, to `quota` of CPU time in each
+    /// `period` by writing to `cpu.max` in its cgroup
+    ///
+    /// Unlike [`CommandExt::max_cpu_time`], which sets the per-process `RLIMIT_CPU` and so gives
+    /// every child process its own budget, this applies to the whole cgroup, so a command can not
+    /// get more CPU time by spawning workers.  It is a rate limit rather than a total: once the
+    /// cgroup has used `quota` in the current period, it is throttled until the next one, but it
+    /// is never killed.  For example, a quota of 50ms per 100ms period allows at most half of one
+    /// CPU, and a quota of 200ms allows up to two CPUs.  The kernel requires both durations to be
+    /// at least 1ms, and the period to be at most 1s.
+    ///
+    /// This requires the `cpu` controller of the cgroup v2 hierarchy, and the command to already
+    /// be in its own cgroup (e.g., by a [`SandboxProfile::hook`] in [`Phase::Privileged`], or
+    /// using `clone3(2)` with `CLONE_INTO_CGROUP`), since the limit would otherwise apply to the
+    /// parent as well.  This crate never moves the command into a cgroup itself, so without such a
+    /// step, spawning the command always fails with `EBUSY`.  It fails with `EOPNOTSUPP` if cgroup
+    /// v2 is not mounted.
+    ///
+    /// See [`cgroups(7)`](https://www.man7.org/linux/man-pages/man7/cgroups.7.html) and the
+    /// [kernel documentation](https://docs.kernel.org/admin-guide/cgroup-v2.html#cpu-interface-files)
+    fn max_cpu_bandwidth(&mut self, quota: Duration, period: Duration) -> &mut Self;
+
+    /// Kill the command once `deadline` of wall-clock time has elapsed since it was spawned
+    ///
+    /// This arms a kernel timer in the command using
+    /// [`setitimer(2)`](https://www.man7.org/linux/man-pages/man2/setitimer.2.html), which sends
+    /// `SIGALRM` to the command when it expires.  Since the command terminates itself, this works
+    /// even if the parent is busy or its async runtime is starved, and does not require a watchdog
+    /// task.  POSIX timers (`timer_create(2)`) and `timerfd`s are not used because they do not
+    /// survive `exec`.
+    ///
+    /// Note that the command can disarm the timer (e.g., using `alarm(0)`) or handle `SIGALRM`
+    /// itself, so this should be combined with a CPU time limit for untrusted commands.  The
+    /// resolution of the deadline is one microsecond.
+    fn hard_deadline(&mut self, deadline: Duration) -> &mut Self;
+
+    /// Change the root directory of the command to `root`, and start it in `cwd`, which is
+    /// resolved _within_ the new root
+    ///
+    /// [`Command::current_dir`] is applied by the standard library _before_ any of the hooks
+    /// which are added by this trait, so it is resolved outside of the new root, and the working
+    /// directory that it sets would no longer be reachable once the root is changed.  This takes
+    /// ownership of the working directory instead: any directory set with `current_dir` must
+    /// still exist on the host, but the command always starts in `cwd`.
+    ///
+    /// The hooks of this trait run in the order in which they are added, so calling this before
+    /// [`CommandExt::restrict`] means that the paths in the [`Rules`] are resolved within the new
+    /// root, while calling it afterwards resolves them on the host, and requires the rules to
+    /// grant access to `root`.  The program to execute is always resolved within the new root.
+    ///
+    /// Changing the root requires `CAP_SYS_CHROOT`, and does not prevent a privileged command from
+    /// escaping it, so it should be combined with [`CommandExt::restrict`].
+    ///
+    /// See [`chroot(2)`](https://www.man7.org/linux/man-pages/man2/chroot.2.html)
+    fn chroot(&mut self, root: impl Into<PathBuf>, cwd: impl Into<PathBuf>) -> &mut Self;
+
+    /// Change the root directory of the command to `root`, and start it at the new root, so that
+    /// `root` is the only part of the filesystem that the command can see
+    ///
+    /// This is the same as [`CommandExt::chroot`] with a working directory of `/`, and has the
+    /// same requirements: it needs `CAP_SYS_CHROOT`, and should be called before
+    /// [`CommandExt::restrict`], so that the rules are resolved within the new root.  Unlike
+    /// landlock, which denies access to paths that are not granted, this hides them entirely, so
+    /// the command can't even tell whether they exist.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, Rules};
+    /// // `/srv/job` contains the program and its inputs
+    /// let rules = Arc::new(Rules::new().add_read_write("/"));
+    /// Command::new("/run")
+    ///     .chroot_to("/srv/job")
+    ///     .restrict(rules)
+    ///     .spawn()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn chroot_to(&mut self, root: impl Into<PathBuf>) -> &mut Self {
+        self.chroot(root, "/")
+    }
+
+    /// Mount a private tmpfs of at most `size` at `path`, which must be an existing directory, so
+    /// that the command gets a writable scratch directory which does not touch the host's disk
+    ///
+    /// The command runs in a new mount namespace, so the tmpfs is only visible to the command (and
+    /// the processes that it spawns), hides anything which is in `path` on the host, and vanishes
+    /// along with its contents once the command exits.  Files in it count towards the memory of
+    /// the host, but not towards [`CommandExt::max_memory`].
+    ///
+    /// Creating the namespace and mounting requires `CAP_SYS_ADMIN`, otherwise spawning the
+    /// command fails with [`io::ErrorKind::PermissionDenied`].  Landlock forbids mounting once a
+    /// process is restricted, so this must be called before [`CommandExt::restrict`], and the
+    /// rules must grant access to `path` (e.g., using [`Rules::add_read_write`]), which then
+    /// applies to the tmpfs.  [`Command::current_dir`] is applied before the tmpfs is mounted,
+    /// so a working directory of `path` would still refer to the directory on the host.
+    ///
+    /// ```no_run
+    /// # use std::{process::Command, sync::Arc};
+    /// # use leucite::{CommandExt, MemorySize, Rules};
+    /// let rules = Arc::new(Rules::new().add_read_only("/usr").add_read_write("/tmp"));
+    /// Command::new("/usr/bin/cc")
+    ///     .add_tmpfs("/tmp", MemorySize::from_mib(64))
+    ///     .restrict(rules)
+    ///     .spawn()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// See [`tmpfs(5)`](https://www.man7.org/linux/man-pages/man5/tmpfs.5.html) and
+    /// [`mount_namespaces(7)`](https://www.man7.org/linux/man-pages/man7/mount_namespaces.7.html)
+    fn add_tmpfs(&mut self, path: impl Into<PathBuf>, size: MemorySize) -> &mut Self;
+
+    /// Run the command in a new IPC namespace, so that it can not access the System V IPC objects
+    /// (shared memory segments, semaphores, and message queues) or POSIX message queues of the
+    /// host or of other commands
+    ///
+    /// Neither landlock nor resource limits restrict System V IPC, so this prevents concurrent
+    /// sandboxed commands from interfering with each other through it.  Creating the namespace
+    /// requires `CAP_SYS_ADMIN`, otherwise spawning the command fails with
+    /// [`io::ErrorKind::PermissionDenied`].
+    ///
+    /// See [`ipc_namespaces(7)`](https://www.man7.org/linux/man-pages/man7/ipc_namespaces.7.html)
+    fn private_ipc(&mut self) -> &mut Self;
+
+    /// Replace the whole environment of the command with `vars`
+    ///
+    /// This clears the environment which would be inherited from the parent, along with any
+    /// variables which were set before, and then sets each of `vars`, so that the command runs
+    /// with a fully specified environment (e.g., for reproducible builds).
+    fn env_exact<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>;
+
+    /// Clear the environment of the command, except for the variables named in `keys`, which keep
+    /// their values from the environment of the current process
+    ///
+    /// This is the same as [`Command::env_clear`] followed by [`Command::env`] for each of `keys`
+    /// which is set in the current process, so a sandboxed command gets a minimal environment
+    /// without leaking secrets (e.g., tokens in the environment of a CI runner).  Keys which are
+    /// not set are skipped, and values do not need to be valid UTF-8.  Like
+    /// [`CommandExt::env_exact`], this also removes any variables which were set on the command
+    /// before, so call [`Command::env`] afterwards to add more.
+    ///
+    /// ```no_run
+    /// # use std::process::Command;
+    /// # use leucite::CommandExt;
+    /// Command::new("make")
+    ///     .clear_env_except(&["PATH", "HOME", "LANG"])
+    ///     .env("CI", "1")
+    ///     .status()?;
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn clear_env_except(&mut self, keys: &[&str]) -> &mut Self;
+
+    /// Apply all of the rules, limits, and environment from `profile` to this command
+    ///
+    /// The environment is applied first, then each of the steps in the child run in the order of
+    /// their [`Phase`], so that the rules are applied last and the other steps are not affected by
+    /// them.  See [`SandboxProfile`] for more information.
+    fn sandbox(&mut self, profile: &SandboxProfile) -> &mut Self;
+
+    /// Apply `profile` to this command like [`CommandExt::sandbox`], and record what was applied
+    ///
+    /// Once the command has been spawned, pass its process ID to [`PendingAudit::finish`] to get
+    /// a [`SandboxAudit`], which includes whether the rules were actually enforced in the child.
+    /// The child reports this through a pipe before executing the command, so restricting using
+    /// the rules of the profile must not be denied by any hook.
+    ///
+    /// ```no_run
+    /// # use leucite::{CommandExt, Rules, SandboxProfile};
+    /// # use std::process::Command;
+    /// let profile = SandboxProfile::new().restrict(Rules::new().add_read_only("/usr").into());
+    ///
+    /// let mut cmd = Command::new("/usr/bin/true");
+    /// let pending = cmd.sandbox_audited(&profile)?;
+    /// let child = cmd.spawn()?;
+    /// let audit = pending.finish(child.id())?;
+    /// # std::io::Result::Ok(())
+    /// ```
+    fn sandbox_audited(&mut self, profile: &SandboxProfile) -> io::Result<PendingAudit>;
 }
 
 // This is okay since all of the functions have idential implementations for both StdCommand and
@@ -286,6 +3067,8 @@ macro_rules! impl_cmd {
     ($($t: tt)+) => {
         impl private::Sealed for Command {}
         impl CommandExt for Command {
+            type Child = std::process::Child;
+
             $($t)+
         }
 
@@ -293,6 +3076,8 @@ macro_rules! impl_cmd {
         impl private::Sealed for TokioCommand {}
         #[cfg(feature = "tokio")]
         impl CommandExt for TokioCommand {
+            type Child = ::tokio::process::Child;
+
             $($t)+
         }
     }
@@ -300,6 +3085,8 @@ macro_rules! impl_cmd {
 
 impl_cmd! {
     fn restrict(&mut self, rules: Arc<Rules>) -> &mut Self {
+        #[cfg(feature = "tracing")]
+        trace::restrict(&rules);
         // SAFETY: We are restricting in a new process with nothing running, so restricting will
         // break nothing
         let inner = move || unsafe { rules.restrict_self() }.map_err(io::Error::other);
@@ -308,27 +3095,417 @@ impl_cmd! {
         unsafe { self.pre_exec(inner) }
     }
 
-    fn max_memory(&mut self, max_memory: MemorySize) -> &mut Self {
+    fn restrict_with_workdir(
+        &mut self,
+        rules: Arc<Rules>,
+        workdir: impl AsRef<Path>,
+    ) -> &mut Self {
+        // The rules are applied after changing directory, so a relative path must be resolved
+        // against the working directory of the parent first
+        let workdir = workdir.as_ref();
+        let workdir = std::env::current_dir()
+            .map_or_else(|_| workdir.to_path_buf(), |cwd| cwd.join(workdir));
+        let rules = Arc::unwrap_or_clone(rules).add_read_write(&workdir);
+        self.current_dir(workdir).restrict(Arc::new(rules))
+    }
+
+    fn restrict_all(&mut self, rules: impl IntoIterator<Item = Arc<Rules>>) -> &mut Self {
+        let rules: Vec<_> = rules.into_iter().collect();
+        #[cfg(feature = "tracing")]
+        rules.iter().for_each(|rules| trace::restrict(rules));
+        let inner = move || {
+            for rules in &rules {
+                // SAFETY: We are restricting in a new process with nothing running, so
+                // restricting will break nothing
+                unsafe { rules.restrict_self() }.map_err(io::Error::other)?;
+            }
+            Ok(())
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn spawn_restricted(&mut self, rules: Arc<Rules>) -> io::Result<Self::Child> {
+        #[cfg(feature = "tracing")]
+        trace::restrict(&rules);
+        let pipe = Arc::new(ErrorPipe::new()?);
+        let (hook_pipe, hook_rules) = (Arc::clone(&pipe), Arc::clone(&rules));
+        let inner = move || {
+            // SAFETY: We are restricting in a new process with nothing running, so restricting
+            // will break nothing
+            unsafe { hook_rules.restrict_self() }.map_err(|e| {
+                hook_pipe.report(&hook_rules, &e);
+                io::Error::other(e)
+            })
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) };
+
+        self.spawn().map_err(|e| match pipe.take(&rules) {
+            Some(child) => io::Error::new(e.kind(), child),
+            None => e,
+        })
+    }
+
+    fn spawn_restricted_if(&mut self, rules: Option<Arc<Rules>>) -> io::Result<Self::Child> {
+        match rules {
+            Some(rules) => self.spawn_restricted(rules),
+            None => self.spawn(),
+        }
+    }
+
+    fn restrict_best_effort(&mut self, rules: Arc<Rules>) -> &mut Self {
+        #[cfg(feature = "tracing")]
+        trace::restrict(&rules);
+        // SAFETY: We are restricting in a new process with nothing running, so restricting will
+        // break nothing
+        let inner = move || {
+            unsafe { rules.restrict_best_effort() }
+                .map(|_| ())
+                .map_err(io::Error::other)
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn restrict_prepared(&mut self, rules: PreparedRules) -> &mut Self {
+        // SAFETY: We are restricting in a new process with nothing running, so restricting will
+        // break nothing
+        let inner = move || unsafe { rules.restrict_self() }.map_err(io::Error::other);
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn resource_limit_soft_hard(
+        &mut self,
+        resource: Resource,
+        soft: LimitValue,
+        hard: LimitValue,
+    ) -> &mut Self {
         // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
         // `exec`.
         unsafe {
-            self.pre_exec(move || Limit::Data.limit(max_memory.bytes()))
+            self.pre_exec(move || resource.limit().limit_soft(soft, hard))
         }
     }
 
+    fn max_memory(&mut self, max_memory: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::Memory, max_memory.into())
+    }
+
+    fn max_memory_soft_hard(&mut self, soft: MemorySize, hard: MemorySize) -> &mut Self {
+        self.resource_limit_soft_hard(Resource::Memory, soft.into(), hard.into())
+    }
+
+    fn max_address_space(&mut self, size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::AddressSpace, size.into())
+    }
+
     fn max_file_size(&mut self, max_file_size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::FileSize, max_file_size.into())
+    }
+
+    fn max_file_size_soft_hard(&mut self, soft: MemorySize, hard: MemorySize) -> &mut Self {
+        self.resource_limit_soft_hard(Resource::FileSize, soft.into(), hard.into())
+    }
+
+    fn max_threads(&mut self, max_threads: u64) -> &mut Self {
+        self.resource_limit(Resource::Processes, max_threads.into())
+    }
+
+    fn max_core_size(&mut self, size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::CoreSize, size.into())
+    }
+
+    fn no_core_dumps(&mut self) -> &mut Self {
+        // A limit of 1 is a special value which also disables piping core dumps to a program,
+        // while a core dump is never small enough to be written to a file
         // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
         // `exec`.
         unsafe {
-            self.pre_exec(move || Limit::FileSize.limit(max_file_size.bytes()))
+            self.pre_exec(|| Limit::Core.limit(1))
         }
     }
 
-    fn max_threads(&mut self, max_threads: u64) -> &mut Self {
+    fn max_stack_size(&mut self, size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::StackSize, size.into())
+    }
+
+    fn max_open_files(&mut self, count: u64) -> &mut Self {
+        self.resource_limit(Resource::OpenFiles, count.into())
+    }
+
+    fn max_processes(&mut self, count: u64) -> &mut Self {
+        self.resource_limit(Resource::Processes, count.into())
+    }
+
+    fn max_cpu_time(&mut self, seconds: u64) -> &mut Self {
+        self.resource_limit(Resource::CpuTime, seconds.into())
+    }
+
+    fn max_rt_cpu_time(&mut self, time: Duration) -> &mut Self {
+        let micros = u64::try_from(time.as_micros()).unwrap_or(u64::MAX);
+        self.resource_limit(Resource::RtCpuTime, micros.into())
+    }
+
+    fn max_rt_priority(&mut self, priority: u64) -> &mut Self {
+        self.resource_limit(Resource::RtPriority, priority.into())
+    }
+
+    fn max_pending_signals(&mut self, count: u64) -> &mut Self {
+        self.resource_limit(Resource::PendingSignals, count.into())
+    }
+
+    fn max_message_queue_bytes(&mut self, size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::MessageQueueBytes, size.into())
+    }
+
+    fn max_locked_memory(&mut self, size: MemorySize) -> &mut Self {
+        self.resource_limit(Resource::LockedMemory, size.into())
+    }
+
+    fn oom_score_adj(&mut self, adj: i32) -> &mut Self {
+        // Format the value before forking, so that nothing is allocated in the child
+        let adj = adj.to_string();
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || process::set_oom_score_adj(adj.as_bytes()))
+        }
+    }
+
+    fn idle_priority(&mut self) -> &mut Self {
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(process::set_idle_priority)
+        }
+    }
+
+    fn nice(&mut self, value: i32) -> &mut Self {
+        let value = value.clamp(-20, 19);
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(move || process::set_nice(value)) }
+    }
+
+    fn min_nice(&mut self, value: i32) -> &mut Self {
+        let limit = (20 - value.clamp(-20, 19)) as u64;
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || {
+                // A lower hard limit is already stricter, and raising it would require privileges
+                if limit > Limit::Nice.hard_limit()? {
+                    return Ok(());
+                }
+                Limit::Nice.limit(limit)
+            })
+        }
+    }
+
+    fn detach_controlling_tty(&mut self) -> &mut Self {
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(process::new_session)
+        }
+    }
+
+    fn set_uid(&mut self, uid: u32) -> &mut Self {
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || process::set_uid(uid))
+        }
+    }
+
+    fn set_gid(&mut self, gid: u32) -> &mut Self {
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || process::set_gid(gid))
+        }
+    }
+
+    fn supplementary_groups(&mut self, groups: &[u32]) -> &mut Self {
+        // Copy the groups before forking, so that nothing is allocated in the child
+        let groups = groups.to_vec();
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || process::set_groups(&groups))
+        }
+    }
+
+    fn no_new_privs(&mut self) -> &mut Self {
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(process::set_no_new_privs)
+        }
+    }
+
+    fn hard_deadline(&mut self, deadline: Duration) -> &mut Self {
+        // A zero timer would disarm the timer rather than firing immediately
+        let deadline = deadline.max(Duration::from_micros(1));
+        let deadline = libc::timeval {
+            tv_sec: deadline.as_secs() as libc::time_t,
+            tv_usec: deadline.subsec_micros() as libc::suseconds_t,
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe {
+            self.pre_exec(move || process::set_deadline(deadline))
+        }
+    }
+
+    fn max_cpu_bandwidth(&mut self, quota: Duration, period: Duration) -> &mut Self {
+        // Look up the hierarchy before forking, so that nothing is allocated in the child
+        let cpu_max = CpuMax::new(quota, period);
+        let inner = move || match &cpu_max {
+            Ok(cpu_max) => process::set_cpu_max(cpu_max),
+            Err(e) => Err(io::Error::from_raw_os_error(
+                e.raw_os_error().unwrap_or(libc::EOPNOTSUPP),
+            )),
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn chroot(&mut self, root: impl Into<PathBuf>, cwd: impl Into<PathBuf>) -> &mut Self {
+        // Convert the paths before forking, so that nothing is allocated in the child.  A path
+        // with an interior nul is reported as invalid when spawning.
+        let root = CString::new(root.into().into_os_string().into_vec());
+        let cwd = CString::new(cwd.into().into_os_string().into_vec());
+        let inner = move || match (&root, &cwd) {
+            (Ok(root), Ok(cwd)) => process::change_root(root, cwd),
+            _ => Err(io::ErrorKind::InvalidInput.into()),
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn add_tmpfs(&mut self, path: impl Into<PathBuf>, size: MemorySize) -> &mut Self {
+        // Format the options before forking, so that nothing is allocated in the child.  A path
+        // with an interior nul is reported as invalid when spawning.
+        let path = CString::new(path.into().into_os_string().into_vec());
+        let options = CString::new(format!("size={},mode=1777", size.bytes()))
+            .expect("the options do not contain a nul");
+        let inner = move || match &path {
+            Ok(path) => process::mount_tmpfs(path, &options),
+            Err(_) => Err(io::ErrorKind::InvalidInput.into()),
+        };
+        // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
+        // `exec`.
+        unsafe { self.pre_exec(inner) }
+    }
+
+    fn private_ipc(&mut self) -> &mut Self {
         // SAFETY: We don't allocate or de-allocate memory in the forked process before the call to
         // `exec`.
         unsafe {
-            self.pre_exec(move || Limit::NumberProcesses.limit(max_threads))
+            self.pre_exec(|| process::unshare(libc::CLONE_NEWIPC))
         }
     }
+
+    fn env_exact<I, K, V>(&mut self, vars: I) -> &mut Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.env_clear().envs(vars)
+    }
+
+    fn clear_env_except(&mut self, keys: &[&str]) -> &mut Self {
+        self.env_clear();
+        for key in keys {
+            if let Some(value) = std::env::var_os(key) {
+                self.env(key, value);
+            }
+        }
+        self
+    }
+
+    fn sandbox(&mut self, profile: &SandboxProfile) -> &mut Self {
+        if profile.env_clear {
+            self.env_clear();
+        }
+        self.envs(profile.envs.iter().map(|(k, v)| (k, v)));
+
+        for phase in Phase::ALL {
+            match phase {
+                Phase::Privileged => {
+                    if let Some(adj) = profile.oom_score_adj {
+                        self.oom_score_adj(adj);
+                    }
+                    for (path, size) in &profile.tmpfs {
+                        self.add_tmpfs(path, *size);
+                    }
+                    if let Some((root, cwd)) = &profile.chroot {
+                        CommandExt::chroot(self, root, cwd);
+                    }
+                }
+                Phase::Namespaces => {
+                    if profile.private_ipc {
+                        self.private_ipc();
+                    }
+                    if profile.detach_controlling_tty {
+                        self.detach_controlling_tty();
+                    }
+                }
+                Phase::Limits => {
+                    self.resource_limits(&profile.limits);
+                    if let Some((quota, period)) = profile.max_cpu_bandwidth {
+                        self.max_cpu_bandwidth(quota, period);
+                    }
+                    if let Some(value) = profile.nice {
+                        self.nice(value);
+                    }
+                    if profile.idle_priority {
+                        self.idle_priority();
+                    }
+                    if let Some(deadline) = profile.hard_deadline {
+                        self.hard_deadline(deadline);
+                    }
+                }
+                Phase::DropPrivileges => {
+                    if let Some(groups) = &profile.supplementary_groups {
+                        self.supplementary_groups(groups);
+                    }
+                    if let Some(gid) = profile.gid {
+                        self.set_gid(gid);
+                    }
+                    if let Some(uid) = profile.uid {
+                        self.set_uid(uid);
+                    }
+                }
+                Phase::Restrict => {
+                    self.restrict_if(profile.rules.clone());
+                }
+            }
+
+            for (_, hook) in profile.hooks.iter().filter(|(p, _)| *p == phase) {
+                let hook = Arc::clone(hook);
+                // SAFETY: The caller of `SandboxProfile::hook` guarantees that the hook doesn't
+                // allocate or de-allocate memory.
+                unsafe { self.pre_exec(move || hook()) };
+            }
+        }
+
+        self
+    }
+
+    fn sandbox_audited(&mut self, profile: &SandboxProfile) -> io::Result<PendingAudit> {
+        let (profile, pending) = PendingAudit::new(profile)?;
+        self.sandbox(&profile);
+        Ok(pending)
+    }
 }
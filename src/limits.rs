@@ -0,0 +1,193 @@
+use crate::LimitValue;
+
+/// A set of resource limits which can be applied to a command at once, see
+/// [`CommandExt::resource_limits`](crate::CommandExt::resource_limits)
+///
+/// Each limit is unset by default, in which case the command inherits the limit of the parent.
+/// Like [`CommandExt::resource_limit`](crate::CommandExt::resource_limit), each limit accepts a
+/// count, a [`MemorySize`](crate::MemorySize), or [`LimitValue::Unlimited`].
+///
+/// ```
+/// # use leucite::{MemorySize, ResourceLimits};
+/// let limits = ResourceLimits::new()
+///     .max_memory(MemorySize::from_mb(100))
+///     .max_cpu_time(10)
+///     .max_open_files(64);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct ResourceLimits {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_memory: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_memory_hard: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_address_space: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_file_size: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_file_size_hard: Option<LimitValue>,
+    pub(crate) max_threads: Option<LimitValue>,
+    pub(crate) max_cpu_time: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_core_size: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_stack_size: Option<LimitValue>,
+    pub(crate) max_open_files: Option<LimitValue>,
+    pub(crate) max_processes: Option<LimitValue>,
+    pub(crate) max_pending_signals: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_message_queue_bytes: Option<LimitValue>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_support::byte_limit"))]
+    pub(crate) max_locked_memory: Option<LimitValue>,
+    pub(crate) min_nice: Option<i32>,
+}
+
+impl ResourceLimits {
+    /// Create a set of limits in which no limit is set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restrict the maximum memory usage, see [`CommandExt::max_memory`](crate::CommandExt::max_memory)
+    pub fn max_memory(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_memory = Some(size.into());
+        self.max_memory_hard = None;
+        self
+    }
+
+    /// Restrict the maximum memory usage with separate soft and hard limits, see
+    /// [`CommandExt::max_memory_soft_hard`](crate::CommandExt::max_memory_soft_hard)
+    ///
+    /// The hard limit is named `max_memory_hard` when serialized.
+    pub fn max_memory_soft_hard(
+        mut self,
+        soft: impl Into<LimitValue>,
+        hard: impl Into<LimitValue>,
+    ) -> Self {
+        self.max_memory = Some(soft.into());
+        self.max_memory_hard = Some(hard.into());
+        self
+    }
+
+    /// Restrict the maximum size of the virtual address space, see
+    /// [`CommandExt::max_address_space`](crate::CommandExt::max_address_space)
+    pub fn max_address_space(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_address_space = Some(size.into());
+        self
+    }
+
+    /// Restrict the maximum file size that may be created, see
+    /// [`CommandExt::max_file_size`](crate::CommandExt::max_file_size)
+    pub fn max_file_size(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_file_size = Some(size.into());
+        self.max_file_size_hard = None;
+        self
+    }
+
+    /// Restrict the maximum file size that may be created with separate soft and hard limits,
+    /// see [`CommandExt::max_file_size_soft_hard`](crate::CommandExt::max_file_size_soft_hard)
+    ///
+    /// The hard limit is named `max_file_size_hard` when serialized.
+    pub fn max_file_size_soft_hard(
+        mut self,
+        soft: impl Into<LimitValue>,
+        hard: impl Into<LimitValue>,
+    ) -> Self {
+        self.max_file_size = Some(soft.into());
+        self.max_file_size_hard = Some(hard.into());
+        self
+    }
+
+    /// Restrict the maximum number of threads, see
+    /// [`CommandExt::max_threads`](crate::CommandExt::max_threads)
+    pub fn max_threads(mut self, count: impl Into<LimitValue>) -> Self {
+        self.max_threads = Some(count.into());
+        self
+    }
+
+    /// Restrict the amount of CPU time, in seconds, see
+    /// [`CommandExt::max_cpu_time`](crate::CommandExt::max_cpu_time)
+    pub fn max_cpu_time(mut self, seconds: impl Into<LimitValue>) -> Self {
+        self.max_cpu_time = Some(seconds.into());
+        self
+    }
+
+    /// Restrict the maximum size of core dumps, see
+    /// [`CommandExt::max_core_size`](crate::CommandExt::max_core_size)
+    pub fn max_core_size(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_core_size = Some(size.into());
+        self
+    }
+
+    /// Restrict the maximum size of the stack, see
+    /// [`CommandExt::max_stack_size`](crate::CommandExt::max_stack_size)
+    pub fn max_stack_size(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_stack_size = Some(size.into());
+        self
+    }
+
+    /// Restrict the maximum number of open file descriptors, see
+    /// [`CommandExt::max_open_files`](crate::CommandExt::max_open_files)
+    pub fn max_open_files(mut self, count: impl Into<LimitValue>) -> Self {
+        self.max_open_files = Some(count.into());
+        self
+    }
+
+    /// Restrict the maximum number of processes of the real user, see
+    /// [`CommandExt::max_processes`](crate::CommandExt::max_processes)
+    pub fn max_processes(mut self, count: impl Into<LimitValue>) -> Self {
+        self.max_processes = Some(count.into());
+        self
+    }
+
+    /// Restrict the number of signals which may be queued for the real user, see
+    /// [`CommandExt::max_pending_signals`](crate::CommandExt::max_pending_signals)
+    pub fn max_pending_signals(mut self, count: impl Into<LimitValue>) -> Self {
+        self.max_pending_signals = Some(count.into());
+        self
+    }
+
+    /// Restrict the size of the POSIX message queues of the real user, see
+    /// [`CommandExt::max_message_queue_bytes`](crate::CommandExt::max_message_queue_bytes)
+    pub fn max_message_queue_bytes(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_message_queue_bytes = Some(size.into());
+        self
+    }
+
+    /// Restrict the amount of memory which may be locked into RAM, see
+    /// [`CommandExt::max_locked_memory`](crate::CommandExt::max_locked_memory)
+    pub fn max_locked_memory(mut self, size: impl Into<LimitValue>) -> Self {
+        self.max_locked_memory = Some(size.into());
+        self
+    }
+
+    /// Restrict the lowest nice value that may be set, see
+    /// [`CommandExt::min_nice`](crate::CommandExt::min_nice)
+    pub fn min_nice(mut self, value: i32) -> Self {
+        self.min_nice = Some(value);
+        self
+    }
+
+    /// The name of each limit which is stored by `prlimit(2)` with the suffix of its unit, if
+    /// any, and its value in that unit
+    pub(crate) fn entries(&self) -> [(&'static str, &'static str, Option<LimitValue>); 14] {
+        [
+            ("max_memory", "_bytes", self.max_memory),
+            ("max_memory_hard", "_bytes", self.max_memory_hard),
+            ("max_address_space", "_bytes", self.max_address_space),
+            ("max_file_size", "_bytes", self.max_file_size),
+            ("max_file_size_hard", "_bytes", self.max_file_size_hard),
+            ("max_threads", "", self.max_threads),
+            ("max_cpu_time", "_s", self.max_cpu_time),
+            ("max_core_size", "_bytes", self.max_core_size),
+            ("max_stack_size", "_bytes", self.max_stack_size),
+            ("max_open_files", "", self.max_open_files),
+            ("max_processes", "", self.max_processes),
+            ("max_pending_signals", "", self.max_pending_signals),
+            ("max_message_queue_bytes", "", self.max_message_queue_bytes),
+            ("max_locked_memory", "_bytes", self.max_locked_memory),
+        ]
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    fmt,
+    os::fd::{AsFd, AsRawFd},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use landlock::{
+    path_beneath_rules, AccessFs, BitFlags, PathBeneath, PathFd, RulesetCreatedAttr, ABI,
+};
+
+use crate::{Error, RestrictStatus, Rules};
+
+/// [`Rules`] whose paths have been opened ahead of time, for spawning many commands which only
+/// differ by their working directory
+///
+/// A landlock ruleset can't be shared across `fork`, so applying [`Rules`] to a command opens and
+/// inspects each of its paths in the child, every time that a command is spawned.  Preparing the
+/// rules does this once in the parent, and the open file descriptors are inherited by each child
+/// instead (they are closed on `exec`).  A per-command working directory can then be granted
+/// cheaply using [`PreparedRules::with_working_dir`], which only clones a reference to the
+/// prepared paths.
+///
+/// The savings scale with the number of paths in the rules, which can be measured using
+/// `cargo bench --bench prepared`.  On one machine, spawning `true` took:
+///
+/// | Paths | [`Rules`] | [`PreparedRules`] |
+/// |-------|-----------|-------------------|
+/// | 11    | 204µs     | 216µs             |
+/// | 67    | 296µs     | 240µs             |
+/// | 515   | 826µs     | 469µs             |
+///
+/// So preparing rules is only worth it for larger rulesets, or for paths which are slow to open
+/// (e.g., on network filesystems).
+///
+/// ```no_run
+/// # use std::process::Command;
+/// # use leucite::{CommandExt, Rules};
+/// let base = Rules::new()
+///     .add_read_only("/usr")
+///     .add_read_only("/etc")
+///     .prepare()?;
+///
+/// for dir in ["/tmp/run-1", "/tmp/run-2"] {
+///     Command::new("gcc")
+///         .current_dir(dir)
+///         .restrict_prepared(base.with_working_dir(dir))
+///         .spawn()?
+///         .wait()?;
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct PreparedRules {
+    rules: Arc<Rules>,
+    paths: Arc<[(PathFd, BitFlags<AccessFs>)]>,
+    working_dir: Option<PathBuf>,
+}
+
+impl Rules {
+    /// Open each of the paths in these rules ahead of time, see [`PreparedRules`]
+    ///
+    /// Unlike [`Rules::restrict_self`], which skips paths that can't be opened, this fails if any
+    /// of the paths can not be opened or is on a denied filesystem.
+    pub fn prepare(self) -> Result<PreparedRules, Error> {
+        self.check_filesystem_types()?;
+
+        // Rights which only apply to directories can't be granted on a file
+        let file_access = AccessFs::from_file(ABI::V6);
+        let paths = self
+            .path_grants()
+            .map(|(path, access)| {
+                let fd = PathFd::new(path).map_err(|source| Error::InvalidPath {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+                let access = match path.metadata() {
+                    Ok(meta) if meta.is_file() => access & file_access,
+                    _ => access,
+                };
+                Ok((fd, access))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(PreparedRules {
+            rules: Arc::new(self),
+            paths,
+            working_dir: None,
+        })
+    }
+}
+
+impl PreparedRules {
+    /// Grant read/write access to `dir`, replacing any working directory that was granted before
+    ///
+    /// Unlike the prepared paths, `dir` is opened when the rules are applied.
+    pub fn with_working_dir(&self, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            rules: Arc::clone(&self.rules),
+            paths: Arc::clone(&self.paths),
+            working_dir: Some(dir.into()),
+        }
+    }
+
+    /// Get the rules which were prepared, without the working directory
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// Restrict the _current process_ using these rules
+    ///
+    /// To restrict a _command's execution_, see [`CommandExt::restrict_prepared`](crate::CommandExt::restrict_prepared)
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_self(&self) -> Result<(), Error> {
+        // SAFETY: The caller upholds the same requirements
+        unsafe { self.restrict_self_with_status() }.map(|_| ())
+    }
+
+    /// Restrict the _current process_ using these rules, returning the status of the restriction
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Rules::restrict_self`]
+    pub unsafe fn restrict_self_with_status(&self) -> Result<RestrictStatus, Error> {
+        let rules = &self.rules;
+        if rules.skip_enforcement()? {
+            return Ok(rules.unenforced_status());
+        }
+
+        if let Some(dir) = &self.working_dir {
+            if !rules.denied_fs_types.is_empty() {
+                rules.check_filesystem_type(dir)?;
+            }
+        }
+
+        // The prepared paths were opened in the same order as the paths of the rules
+        let mut ruleset = rules.create_ruleset()?;
+        for ((fd, access), (path, _)) in self.paths.iter().zip(rules.path_grants()) {
+            ruleset = ruleset
+                .add_rule(PathBeneath::new(fd, *access))
+                .map_err(|source| Error::AddPathRule {
+                    path: path.to_path_buf(),
+                    source,
+                })?;
+        }
+        if let Some(dir) = &self.working_dir {
+            let access = rules.writable_access(rules.read_access() | rules.write_access());
+            ruleset = ruleset
+                .add_rules(path_beneath_rules([dir], access))
+                .map_err(|source| Error::AddPathRule {
+                    path: dir.clone(),
+                    source,
+                })?;
+        }
+
+        rules.restrict_status(rules.restrict_ruleset(ruleset)?)
+    }
+}
+
+impl fmt::Debug for PreparedRules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let paths: Vec<_> = (self.paths.iter())
+            .map(|(fd, access)| (fd.as_fd().as_raw_fd(), access))
+            .collect();
+        f.debug_struct("PreparedRules")
+            .field("rules", &self.rules)
+            .field("paths", &paths)
+            .field("working_dir", &self.working_dir)
+            .finish()
+    }
+}
@@ -1,4 +1,4 @@
-use std::{io, ptr};
+use std::{fmt, io, ops::Sub, ptr, str::FromStr};
 
 use libc::rlimit;
 
@@ -21,6 +21,23 @@ macro_rules! impl_memsz {
 }
 
 impl MemorySize {
+    /// No memory at all, e.g., to prevent all writes using
+    /// [`CommandExt::max_file_size`](crate::CommandExt::max_file_size)
+    pub const ZERO: Self = Self(0);
+
+    /// The largest size which can be represented
+    ///
+    /// As a resource limit, this is the same value as `RLIM_INFINITY`, so it means that there is
+    /// no limit at all, rather than a very large one.  `/proc/<pid>/limits` shows it as
+    /// `unlimited`.  Prefer [`LimitValue::Unlimited`] to express that intent.
+    pub const MAX: Self = Self(u64::MAX);
+
+    /// Whether this is [`MemorySize::ZERO`]
+    #[inline]
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
     impl_memsz!(from_bytes => bytes * 1);
 
     impl_memsz!(from_kb => kilobytes * 1000);
@@ -32,17 +49,362 @@ impl MemorySize {
     impl_memsz!(from_gib => gibibytes * 1024 * 1024 * 1024);
 }
 
-pub(crate) fn read_errno() -> io::Error {
-    io::Error::last_os_error()
+impl MemorySize {
+    /// Get `fraction` of the total physical memory of the host, e.g., `0.25` for a quarter of it
+    ///
+    /// This makes a limit such as [`CommandExt::max_memory`](crate::CommandExt::max_memory)
+    /// portable across hosts with different amounts of memory.  `fraction` is clamped to be
+    /// between 0 and 1, and must not be NaN.  Note that this is the memory of the host, which
+    /// ignores any memory limit of the cgroup that the current process runs in (e.g., of a
+    /// container).
+    ///
+    /// See [`sysinfo(2)`](https://www.man7.org/linux/man-pages/man2/sysinfo.2.html)
+    ///
+    /// ```
+    /// # use leucite::MemorySize;
+    /// let quarter = MemorySize::fraction_of_total(0.25)?;
+    /// assert!(quarter <= MemorySize::fraction_of_total(1.0)?);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn fraction_of_total(fraction: f64) -> io::Result<Self> {
+        if fraction.is_nan() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the fraction of memory is NaN",
+            ));
+        }
+
+        // SAFETY: `sysinfo` is plain data, for which all zeroes is a valid value
+        let mut info: libc::sysinfo = unsafe { std::mem::zeroed() };
+        // SAFETY: `info` is a valid `sysinfo`, and any error is reported through `errno`
+        if unsafe { libc::sysinfo(&mut info) } != 0 {
+            return Err(read_errno());
+        }
+        let total = (info.totalram as u64).saturating_mul(u64::from(info.mem_unit));
+
+        // The conversion back to an integer saturates
+        Ok(Self((total as f64 * fraction.clamp(0.0, 1.0)) as u64))
+    }
+
+    /// Format the size in the largest binary unit (`KiB`, `MiB`, `GiB`, ...) which it fills, e.g.,
+    /// `1.5 GiB`.  This is the same as the [`Display`](fmt::Display) implementation.
+    pub fn to_human_binary(self) -> String {
+        self.to_string()
+    }
+
+    /// Format the size in the largest decimal unit (`KB`, `MB`, `GB`, ...) which it fills, e.g.,
+    /// `100 MB`
+    pub fn to_human_decimal(self) -> String {
+        struct Decimal(u64);
+        impl fmt::Display for Decimal {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt_scaled(self.0, 1000, ["B", "KB", "MB", "GB", "TB", "PB", "EB"], f)
+            }
+        }
+        Decimal(self.0).to_string()
+    }
+}
+
+/// Formats the size in the largest binary unit which it fills, rounded to one decimal place
+///
+/// ```
+/// # use leucite::MemorySize;
+/// assert_eq!(MemorySize::from_mib(100).to_string(), "100 MiB");
+/// assert_eq!(MemorySize::from_mib(1536).to_string(), "1.5 GiB");
+/// assert_eq!(MemorySize::from_bytes(512).to_string(), "512 B");
+/// ```
+impl fmt::Display for MemorySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_binary(self.0, f)
+    }
+}
+
+/// An error from parsing a [`MemorySize`] from a string
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ParseMemorySizeError {
+    #[error("invalid number in memory size {0:?}")]
+    InvalidNumber(String),
+    #[error(
+        "unknown unit in memory size {0:?}, expected one of B, KB, MB, GB, TB, PB, EB, KiB, MiB, \
+         GiB, TiB, PiB, or EiB"
+    )]
+    UnknownUnit(String),
+    #[error("memory size {0:?} is too large")]
+    TooLarge(String),
 }
 
-fn into_rlimit(n: u64) -> libc::rlimit {
-    rlimit {
-        rlim_cur: n,
-        rlim_max: n,
+/// Parse a size such as `100MB`, `1.5 GiB`, or `4096`
+///
+/// The unit is case-insensitive, and may be separated from the number by whitespace.  `KB`, `MB`,
+/// `GB`, `TB`, `PB` and `EB` are decimal units (powers of 1000), while `KiB`, `MiB`, `GiB`, `TiB`,
+/// `PiB` and `EiB` are binary units (powers of 1024), so that every size which is formatted by
+/// [`Display`](fmt::Display) or [`MemorySize::to_human_decimal`] can be parsed again.  A number
+/// without a unit is in bytes.  Fractional sizes are rounded down to a whole number of bytes.
+///
+/// ```
+/// # use leucite::MemorySize;
+/// assert_eq!("100MB".parse(), Ok(MemorySize::from_mb(100)));
+/// assert_eq!("1.5 gib".parse(), Ok(MemorySize::from_mib(1536)));
+/// assert_eq!("2 TiB".parse(), Ok(MemorySize::from_gib(2048)));
+/// ```
+impl FromStr for MemorySize {
+    type Err = ParseMemorySizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let trimmed = s.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+
+        let unit: u64 = match unit.trim_start().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "kb" => 1000,
+            "mb" => 1000 * 1000,
+            "gb" => 1000u64.pow(3),
+            "tb" => 1000u64.pow(4),
+            "pb" => 1000u64.pow(5),
+            "eb" => 1000u64.pow(6),
+            "kib" => 1024,
+            "mib" => 1024 * 1024,
+            "gib" => 1024u64.pow(3),
+            "tib" => 1024u64.pow(4),
+            "pib" => 1024u64.pow(5),
+            "eib" => 1024u64.pow(6),
+            _ => return Err(ParseMemorySizeError::UnknownUnit(s.to_string())),
+        };
+
+        let invalid = || ParseMemorySizeError::InvalidNumber(s.to_string());
+        let (whole, fraction) = number.split_once('.').unwrap_or((number, ""));
+        if (whole.is_empty() && fraction.is_empty()) || fraction.contains('.') {
+            return Err(invalid());
+        }
+        let digits = |d: &str| -> Result<u128, _> {
+            d.bytes().try_fold(0u128, |n, b| {
+                n.checked_mul(10)
+                    .and_then(|n| n.checked_add(u128::from(b - b'0')))
+                    .ok_or_else(|| ParseMemorySizeError::TooLarge(s.to_string()))
+            })
+        };
+        // Digits beyond the precision of a byte don't change the result, and could overflow
+        let fraction = &fraction[..fraction.len().min(12)];
+        // The fraction is below 10^12 and the unit below 2^64, so only the sum can overflow
+        let fraction = digits(fraction)? * u128::from(unit) / 10u128.pow(fraction.len() as u32);
+        let bytes = digits(whole)?
+            .checked_mul(u128::from(unit))
+            .and_then(|n| n.checked_add(fraction));
+
+        bytes
+            .and_then(|n| u64::try_from(n).ok())
+            .map(Self)
+            .ok_or_else(|| ParseMemorySizeError::TooLarge(s.to_string()))
+    }
+}
+
+/// The signed difference between two [`MemorySize`]s, e.g., when comparing the limits of two
+/// profiles
+///
+/// This is created by subtracting one [`MemorySize`] from another, and is displayed with its sign
+/// and a binary unit (e.g., `+100 MiB` or `-1.5 GiB`).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct MemorySizeDelta {
+    negative: bool,
+    magnitude: MemorySize,
+}
+
+impl MemorySizeDelta {
+    /// Whether the size has decreased
+    #[inline]
+    pub const fn is_negative(self) -> bool {
+        self.negative
+    }
+
+    /// The absolute amount by which the size has changed
+    #[inline]
+    pub const fn magnitude(self) -> MemorySize {
+        self.magnitude
     }
 }
 
+impl Sub for MemorySize {
+    type Output = MemorySizeDelta;
+
+    fn sub(self, rhs: Self) -> MemorySizeDelta {
+        MemorySizeDelta {
+            negative: self < rhs,
+            magnitude: Self(self.0.abs_diff(rhs.0)),
+        }
+    }
+}
+
+impl fmt::Display for MemorySizeDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.negative { "-" } else { "+" })?;
+        fmt_binary(self.magnitude.0, f)
+    }
+}
+
+/// Write `bytes` using the largest binary unit which is not greater than it, with at most one
+/// decimal place
+fn fmt_binary(bytes: u64, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt_scaled(
+        bytes,
+        1024,
+        ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+        f,
+    )
+}
+
+/// Format `bytes` with the largest of `units` (each `step` times the previous) which it fills,
+/// rounded to the nearest tenth
+fn fmt_scaled(bytes: u64, step: u64, units: [&str; 7], f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (mut exp, mut unit) = (0, 1u64);
+    while exp + 1 < units.len() && bytes / unit >= step {
+        exp += 1;
+        unit *= step;
+    }
+    let (whole, rem) = (bytes / unit, bytes % unit);
+    // Rounded to the nearest tenth, computed in 128 bits so that it can not overflow
+    let tenths = ((rem as u128 * 10 + unit as u128 / 2) / unit as u128) as u64;
+    match tenths {
+        0 => write!(f, "{whole} {}", units[exp]),
+        10 => write!(f, "{} {}", whole + 1, units[exp]),
+        tenths => write!(f, "{whole}.{tenths} {}", units[exp]),
+    }
+}
+
+/// The value of a resource limit, which is either bounded or explicitly unlimited
+///
+/// Every resource limit can be set to a `LimitValue` using
+/// [`CommandExt::resource_limit`](crate::CommandExt::resource_limit), while the setter of each
+/// limit (e.g., [`CommandExt::max_memory`](crate::CommandExt::max_memory)) only accepts a bounded
+/// count or [`MemorySize`].  [`LimitValue::Unlimited`] sets the limit to
+/// `RLIM_INFINITY`, e.g., to raise a limit which the command would otherwise inherit from the
+/// parent, or to make it explicit in a policy that a resource is not limited.  Raising a hard limit
+/// above the current one requires `CAP_SYS_RESOURCE`, and otherwise makes spawning the command
+/// fail.
+///
+/// ```no_run
+/// # use leucite::{CommandExt, LimitValue, Resource};
+/// # use std::process::Command;
+/// let child = Command::new("make")
+///     .resource_limit(Resource::Memory, LimitValue::Unlimited)
+///     .resource_limit(Resource::FileSize, LimitValue::Bounded(1 << 30))
+///     .spawn()?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum LimitValue {
+    /// No limit at all, i.e., `RLIM_INFINITY`
+    Unlimited,
+    /// A limit of the given count, in the unit of the resource (e.g., bytes or seconds)
+    ///
+    /// Note that `Bounded(u64::MAX)` is the same as [`LimitValue::Unlimited`] to the kernel.
+    Bounded(u64),
+}
+
+impl LimitValue {
+    /// Whether this is [`LimitValue::Unlimited`]
+    #[inline]
+    pub const fn is_unlimited(self) -> bool {
+        matches!(self, Self::Unlimited)
+    }
+
+    /// The value which is passed to `prlimit(2)`
+    #[inline]
+    pub(crate) const fn to_rlim(self) -> libc::rlim_t {
+        match self {
+            Self::Unlimited => libc::RLIM_INFINITY,
+            Self::Bounded(value) => value,
+        }
+    }
+}
+
+impl From<u64> for LimitValue {
+    fn from(value: u64) -> Self {
+        Self::Bounded(value)
+    }
+}
+
+impl From<MemorySize> for LimitValue {
+    fn from(size: MemorySize) -> Self {
+        Self::Bounded(size.bytes())
+    }
+}
+
+impl fmt::Display for LimitValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unlimited => f.write_str("unlimited"),
+            Self::Bounded(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A resource whose usage can be limited using
+/// [`CommandExt::resource_limit`](crate::CommandExt::resource_limit)
+///
+/// The limit of each resource is in its own unit, which is given below along with the setter that
+/// describes the limit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Resource {
+    /// Bytes, see [`CommandExt::max_memory`](crate::CommandExt::max_memory)
+    Memory,
+    /// Bytes, see [`CommandExt::max_address_space`](crate::CommandExt::max_address_space)
+    AddressSpace,
+    /// Bytes, see [`CommandExt::max_file_size`](crate::CommandExt::max_file_size)
+    FileSize,
+    /// A count, see [`CommandExt::max_processes`](crate::CommandExt::max_processes) and
+    /// [`CommandExt::max_threads`](crate::CommandExt::max_threads)
+    Processes,
+    /// Seconds, see [`CommandExt::max_cpu_time`](crate::CommandExt::max_cpu_time)
+    CpuTime,
+    /// Microseconds, see [`CommandExt::max_rt_cpu_time`](crate::CommandExt::max_rt_cpu_time)
+    RtCpuTime,
+    /// A priority, see [`CommandExt::max_rt_priority`](crate::CommandExt::max_rt_priority)
+    RtPriority,
+    /// A count, see [`CommandExt::max_pending_signals`](crate::CommandExt::max_pending_signals)
+    PendingSignals,
+    /// Bytes, see
+    /// [`CommandExt::max_message_queue_bytes`](crate::CommandExt::max_message_queue_bytes)
+    MessageQueueBytes,
+    /// Bytes, see [`CommandExt::max_locked_memory`](crate::CommandExt::max_locked_memory)
+    LockedMemory,
+    /// Bytes, see [`CommandExt::max_core_size`](crate::CommandExt::max_core_size)
+    CoreSize,
+    /// Bytes, see [`CommandExt::max_stack_size`](crate::CommandExt::max_stack_size)
+    StackSize,
+    /// A count, see [`CommandExt::max_open_files`](crate::CommandExt::max_open_files)
+    OpenFiles,
+}
+
+impl Resource {
+    /// The limit which is set for this resource
+    pub(crate) fn limit(self) -> Limit {
+        match self {
+            Self::Memory => Limit::Data,
+            Self::AddressSpace => Limit::AddressSpace,
+            Self::FileSize => Limit::FileSize,
+            Self::Processes => Limit::NumberProcesses,
+            Self::CpuTime => Limit::Cpu,
+            Self::RtCpuTime => Limit::RTTime,
+            Self::RtPriority => Limit::RTPrio,
+            Self::PendingSignals => Limit::SignalPending,
+            Self::MessageQueueBytes => Limit::MessageQueue,
+            Self::LockedMemory => Limit::MemoryLock,
+            Self::CoreSize => Limit::Core,
+            Self::StackSize => Limit::Stack,
+            Self::OpenFiles => Limit::NumberFiles,
+        }
+    }
+}
+
+pub(crate) fn read_errno() -> io::Error {
+    io::Error::last_os_error()
+}
+
 #[repr(u32)]
 #[allow(unused)]
 pub(crate) enum Limit {
@@ -75,8 +437,50 @@ impl Limit {
     ///
     /// Caller must ensure that any resources that are already allocated by the process fall below
     /// the limit set by this call.
-    pub(crate) unsafe fn limit(self, size: u64) -> io::Result<()> {
-        let limit = into_rlimit(size);
+    pub(crate) unsafe fn limit(self, value: impl Into<LimitValue>) -> io::Result<()> {
+        let value = value.into();
+        // SAFETY: the caller upholds the same requirements
+        unsafe { self.limit_soft(value, value) }
+    }
+
+    /// Get the current hard limit
+    pub(crate) fn hard_limit(self) -> io::Result<libc::rlim_t> {
+        let mut limit = rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `limit` is a valid `rlimit` to write the current limits to, and any error is
+        // returned through `errno`
+        let ret = unsafe {
+            libc::prlimit(
+                0,
+                libc::__rlimit_resource_t::from(self),
+                ptr::null(),
+                &mut limit as *mut rlimit,
+            )
+        };
+
+        if ret == 0 {
+            return Ok(limit.rlim_max);
+        }
+
+        Err(read_errno())
+    }
+
+    /// Set the soft limit to `soft` and the hard limit to `hard`
+    ///
+    /// # SAFETY
+    ///
+    /// See [`Limit::limit`]
+    pub(crate) unsafe fn limit_soft(
+        self,
+        soft: impl Into<LimitValue>,
+        hard: impl Into<LimitValue>,
+    ) -> io::Result<()> {
+        let limit = rlimit {
+            rlim_cur: soft.into().to_rlim(),
+            rlim_max: hard.into().to_rlim(),
+        };
         // SAFETY: this function should never crash based on input.  Any error is returned through
         // `errno` and we are handling that properly.
         let ret = unsafe {
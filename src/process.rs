@@ -0,0 +1,280 @@
+//! Functions which modify the attributes of the _current process_.  These are intended to be
+//! called from within `pre_exec`, so they must not allocate or de-allocate memory.
+use std::{
+    ffi::CStr,
+    io,
+    os::fd::{AsRawFd, RawFd},
+};
+
+use crate::{
+    cgroup::{own_cgroup, CpuMax},
+    prlimit::read_errno,
+};
+
+/// Write `value` into `/proc/self/oom_score_adj`
+///
+/// `value` should be the ASCII representation of the score (e.g., `b"500"`), which is expected to
+/// be formatted before forking.
+pub(crate) fn set_oom_score_adj(value: &[u8]) -> io::Result<()> {
+    // SAFETY: The path is a valid, nul-terminated string and any error is reported through `errno`
+    let fd = unsafe {
+        libc::open(
+            c"/proc/self/oom_score_adj".as_ptr(),
+            libc::O_WRONLY | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(read_errno());
+    }
+
+    // SAFETY: `value` is valid for `value.len()` bytes and `fd` was opened above
+    let ret = unsafe { libc::write(fd, value.as_ptr().cast(), value.len()) };
+    let res = if ret < 0 { Err(read_errno()) } else { Ok(()) };
+
+    // SAFETY: `fd` was opened above and is not used after this point
+    unsafe { libc::close(fd) };
+
+    res
+}
+
+/// Set the scheduling policy of the current process to `SCHED_IDLE`
+pub(crate) fn set_idle_priority() -> io::Result<()> {
+    let param = libc::sched_param { sched_priority: 0 };
+    // SAFETY: `param` is a valid `sched_param` and any error is reported through `errno`
+    let ret = unsafe { libc::sched_setscheduler(0, libc::SCHED_IDLE, &param) };
+    if ret != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Set the nice value of the current process to `value`
+pub(crate) fn set_nice(value: libc::c_int) -> io::Result<()> {
+    // SAFETY: `setpriority` has no memory safety requirements and any error is reported through
+    // `errno`
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, value) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Start a new session, which detaches the current process from its controlling terminal
+pub(crate) fn new_session() -> io::Result<()> {
+    // SAFETY: `setsid` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::setsid() } < 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Move the current process into new namespaces, see `unshare(2)`
+pub(crate) fn unshare(flags: libc::c_int) -> io::Result<()> {
+    // SAFETY: `unshare` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::unshare(flags) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+extern "C" {
+    // Not exposed by `libc` for all targets
+    fn setitimer(
+        which: libc::c_int,
+        new_value: *const libc::itimerval,
+        old_value: *mut libc::itimerval,
+    ) -> libc::c_int;
+}
+
+/// Arm a timer which sends `SIGALRM` to the current process once `deadline` has elapsed
+///
+/// The default action of `SIGALRM` is to terminate the process, and unlike POSIX timers (see
+/// `timer_create(2)`), the timer set by `setitimer` is preserved across `exec`.
+pub(crate) fn set_deadline(deadline: libc::timeval) -> io::Result<()> {
+    // SAFETY: `SIG_DFL` is a valid disposition for `SIGALRM`
+    if unsafe { libc::signal(libc::SIGALRM, libc::SIG_DFL) } == libc::SIG_ERR {
+        return Err(read_errno());
+    }
+
+    let timer = libc::itimerval {
+        it_interval: libc::timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        },
+        it_value: deadline,
+    };
+    // SAFETY: `timer` is a valid `itimerval`, and the old value may be null
+    if unsafe { setitimer(libc::ITIMER_REAL, &timer, std::ptr::null_mut()) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Set the supplementary groups of the current process, see `setgroups(2)`
+pub(crate) fn set_groups(groups: &[libc::gid_t]) -> io::Result<()> {
+    // SAFETY: `groups` is valid for `groups.len()` IDs and any error is reported through `errno`
+    if unsafe { libc::setgroups(groups.len(), groups.as_ptr()) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Set the real, effective, and saved group ID of the current process, see `setgid(2)`
+pub(crate) fn set_gid(gid: libc::gid_t) -> io::Result<()> {
+    // SAFETY: `setgid` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::setgid(gid) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Set the real, effective, and saved user ID of the current process, see `setuid(2)`
+pub(crate) fn set_uid(uid: libc::uid_t) -> io::Result<()> {
+    // SAFETY: `setuid` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::setuid(uid) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Set the `no_new_privs` attribute of the current process, see `prctl(2)`
+pub(crate) fn set_no_new_privs() -> io::Result<()> {
+    // SAFETY: `prctl` has no memory safety requirements and any error is reported through `errno`
+    if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Change the root directory of the current process to `root`, then change the working directory
+/// to `cwd`, which is resolved within the new root
+pub(crate) fn change_root(root: &CStr, cwd: &CStr) -> io::Result<()> {
+    // SAFETY: `root` is a valid, nul-terminated string and any error is reported through `errno`
+    if unsafe { libc::chroot(root.as_ptr()) } != 0 {
+        return Err(read_errno());
+    }
+    // SAFETY: `cwd` is a valid, nul-terminated string and any error is reported through `errno`
+    if unsafe { libc::chdir(cwd.as_ptr()) } != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Move the current process into a new mount namespace, and mount a new tmpfs at `target` within
+/// it using the mount `options` (e.g., `b"size=1048576,mode=1777"`)
+///
+/// All mounts are made private first, so that the tmpfs is not propagated back to the host.
+pub(crate) fn mount_tmpfs(target: &CStr, options: &CStr) -> io::Result<()> {
+    unshare(libc::CLONE_NEWNS)?;
+
+    // SAFETY: The paths are valid, nul-terminated strings, and any error is reported through
+    // `errno`.  The null source, type, and data are ignored when changing the propagation type.
+    let ret = unsafe {
+        libc::mount(
+            std::ptr::null(),
+            c"/".as_ptr(),
+            std::ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            std::ptr::null(),
+        )
+    };
+    if ret != 0 {
+        return Err(read_errno());
+    }
+
+    // SAFETY: Each argument is a valid, nul-terminated string, and any error is reported through
+    // `errno`
+    let ret = unsafe {
+        libc::mount(
+            c"tmpfs".as_ptr(),
+            target.as_ptr(),
+            c"tmpfs".as_ptr(),
+            libc::MS_NOSUID | libc::MS_NODEV,
+            options.as_ptr().cast(),
+        )
+    };
+    if ret != 0 {
+        return Err(read_errno());
+    }
+    Ok(())
+}
+
+/// Write `cpu_max.value` into the `cpu.max` file of the cgroup of the current process
+///
+/// This fails with `EBUSY` if the current process is still in the same cgroup as the parent, since
+/// the limit would then apply to the parent as well.  All paths are resolved relative to the
+/// directories opened by the parent, so this also works after a call to [`change_root`].
+pub(crate) fn set_cpu_max(cpu_max: &CpuMax) -> io::Result<()> {
+    let mut contents = [0u8; 4096];
+    let len = read_file_at(cpu_max.proc.as_raw_fd(), c"self/cgroup", &mut contents)?;
+    let cgroup = own_cgroup(&contents[..len])
+        .ok_or_else(|| io::Error::from_raw_os_error(libc::EOPNOTSUPP))?;
+    if cgroup == cpu_max.parent.as_slice() {
+        return Err(io::Error::from_raw_os_error(libc::EBUSY));
+    }
+
+    // The cgroup is absolute within the hierarchy, but it has to be relative to the mount point
+    let cgroup = cgroup.strip_prefix(b"/").unwrap_or(cgroup);
+    let mut path = [0u8; libc::PATH_MAX as usize];
+    let mut len = 0;
+    let sep: &[u8] = if cgroup.is_empty() { b"" } else { b"/" };
+    for part in [cgroup, sep, b"cpu.max\0"] {
+        let dest = path
+            .get_mut(len..len + part.len())
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ENAMETOOLONG))?;
+        dest.copy_from_slice(part);
+        len += part.len();
+    }
+    let path = CStr::from_bytes_with_nul(&path[..len])
+        .map_err(|_| io::Error::from_raw_os_error(libc::EINVAL))?;
+
+    // SAFETY: The path is a valid, nul-terminated string, the directory was opened by the parent
+    // and any error is reported through `errno`
+    let fd = unsafe {
+        libc::openat(
+            cpu_max.mount.as_raw_fd(),
+            path.as_ptr(),
+            libc::O_WRONLY | libc::O_CLOEXEC,
+        )
+    };
+    if fd < 0 {
+        return Err(read_errno());
+    }
+
+    // SAFETY: `value` is valid for `value.len()` bytes and `fd` was opened above
+    let ret = unsafe { libc::write(fd, cpu_max.value.as_ptr().cast(), cpu_max.value.len()) };
+    let res = if ret < 0 { Err(read_errno()) } else { Ok(()) };
+
+    // SAFETY: `fd` was opened above and is not used after this point
+    unsafe { libc::close(fd) };
+
+    res
+}
+
+/// Read as much of the file at `path` relative to `dir` as fits into `buf`, returning the number of
+/// bytes read
+fn read_file_at(dir: RawFd, path: &CStr, buf: &mut [u8]) -> io::Result<usize> {
+    // SAFETY: The path is a valid, nul-terminated string and any error is reported through `errno`
+    let fd = unsafe { libc::openat(dir, path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(read_errno());
+    }
+
+    let mut len = 0;
+    let res = loop {
+        // SAFETY: `buf[len..]` is valid for writes of its length and `fd` was opened above
+        let ret = unsafe { libc::read(fd, buf[len..].as_mut_ptr().cast(), buf.len() - len) };
+        match ret {
+            0 => break Ok(len),
+            n if n < 0 => break Err(read_errno()),
+            n => len += n as usize,
+        }
+        if len == buf.len() {
+            break Ok(len);
+        }
+    };
+
+    // SAFETY: `fd` was opened above and is not used after this point
+    unsafe { libc::close(fd) };
+
+    res
+}
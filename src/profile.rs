@@ -0,0 +1,322 @@
+use std::{ffi::OsString, fmt, io, path::PathBuf, sync::Arc, time::Duration};
+
+use crate::{CommandExt, LimitValue, MemorySize, ResourceLimits, Rules};
+
+/// A complete description of how a command should be sandboxed: the rules, limits, and
+/// environment for the command.
+///
+/// This separates the definition of a sandbox policy from the construction of the commands it
+/// applies to, so a profile can be passed into code which builds and spawns commands without that
+/// code needing to know the details of the policy.
+///
+/// ```no_run
+/// # use leucite::{Rules, SandboxProfile, MemorySize};
+/// # use std::process::Command;
+/// let profile = SandboxProfile::new()
+///     .restrict(
+///         Rules::new()
+///             .add_read_only("/usr")
+///             .add_read_write("/tmp/foo")
+///             .into(),
+///     )
+///     .max_memory(MemorySize::from_mb(100))
+///     .env_clear()
+///     .env("PATH", "/usr/bin");
+///
+/// let mut cmd = Command::new("bash");
+/// profile.wrap(&mut cmd).current_dir("/tmp/foo").spawn()?.wait()?;
+/// # std::io::Result::Ok(())
+/// ```
+///
+/// # Phases
+///
+/// When a profile is applied, each step runs in the child in a fixed order of [`Phase`]s, no
+/// matter the order in which the profile was built, so that a step which needs privileges always
+/// runs before privileges are dropped, and the rules are always applied last.  Custom steps can be
+/// added to a phase using [`SandboxProfile::hook`].  Each step of the profile runs in the phase
+/// which is listed for it in [`Phase`].
+///
+/// Steps which are added to the command directly using [`CommandExt`] rather than through the
+/// profile are not ordered with the phases: they run in the order in which they are added,
+/// before or after all of the steps of the profile.
+#[derive(Clone, Default)]
+pub struct SandboxProfile {
+    pub(crate) rules: Option<Arc<Rules>>,
+    pub(crate) limits: ResourceLimits,
+    pub(crate) max_cpu_bandwidth: Option<(Duration, Duration)>,
+    pub(crate) nice: Option<i32>,
+    pub(crate) chroot: Option<(PathBuf, PathBuf)>,
+    pub(crate) tmpfs: Vec<(PathBuf, MemorySize)>,
+    pub(crate) private_ipc: bool,
+    pub(crate) uid: Option<u32>,
+    pub(crate) gid: Option<u32>,
+    pub(crate) supplementary_groups: Option<Vec<u32>>,
+    pub(crate) oom_score_adj: Option<i32>,
+    pub(crate) idle_priority: bool,
+    pub(crate) detach_controlling_tty: bool,
+    pub(crate) hard_deadline: Option<Duration>,
+    pub(crate) env_clear: bool,
+    pub(crate) envs: Vec<(OsString, OsString)>,
+    pub(crate) hooks: Vec<(Phase, Hook)>,
+}
+
+pub(crate) type Hook = Arc<dyn Fn() -> io::Result<()> + Send + Sync>;
+
+/// A phase in which steps are run in the child before the command is executed, see
+/// [`SandboxProfile::hook`]
+///
+/// The phases run in the order in which they are declared here.  Within a phase, the steps of the
+/// profile run first, followed by any hooks in the order in which they were added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Phase {
+    /// Steps which require the privileges of the parent: lowering the OOM score (see
+    /// [`SandboxProfile::oom_score_adj`]), mounting filesystems (see
+    /// [`SandboxProfile::add_tmpfs`]), and then changing the root directory (see
+    /// [`SandboxProfile::chroot`]), or custom steps like joining a cgroup
+    Privileged,
+    /// Steps which move the command into new namespaces or sessions (see
+    /// [`SandboxProfile::private_ipc`] and [`SandboxProfile::detach_controlling_tty`])
+    Namespaces,
+    /// Resource limits, scheduling, and timers (see [`SandboxProfile::resource_limits`] and the
+    /// limits which it includes, [`SandboxProfile::max_cpu_bandwidth`],
+    /// [`SandboxProfile::nice`], [`SandboxProfile::idle_priority`], and
+    /// [`SandboxProfile::hard_deadline`])
+    Limits,
+    /// Steps which drop privileges, after which privileged steps would fail: replacing the
+    /// supplementary groups, and then changing the group and the user (see
+    /// [`SandboxProfile::set_uid`])
+    DropPrivileges,
+    /// The filesystem and network rules (see [`SandboxProfile::restrict`]), which are applied
+    /// last so that no other step is denied by them
+    Restrict,
+}
+
+impl Phase {
+    /// Each phase, in the order in which they run
+    pub const ALL: [Phase; 5] = [
+        Self::Privileged,
+        Self::Namespaces,
+        Self::Limits,
+        Self::DropPrivileges,
+        Self::Restrict,
+    ];
+}
+
+impl SandboxProfile {
+    /// Create a new [`SandboxProfile`] which applies no restrictions
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Restrict the filesystem and network access of the command, see [`CommandExt::restrict`]
+    pub fn restrict(mut self, rules: Arc<Rules>) -> Self {
+        self.rules = Some(rules);
+        self
+    }
+
+    /// Apply each of the limits which are set in `limits` to the command, replacing any limits
+    /// which were set before, see [`CommandExt::resource_limits`]
+    pub fn resource_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Get the resource limits which are applied to the command
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Restrict the maximum memory usage of the command, see [`CommandExt::max_memory`]
+    pub fn max_memory(mut self, max_memory: impl Into<LimitValue>) -> Self {
+        self.limits = self.limits.max_memory(max_memory);
+        self
+    }
+
+    /// Restrict the maximum file size that the command may create, see
+    /// [`CommandExt::max_file_size`]
+    pub fn max_file_size(mut self, max_file_size: impl Into<LimitValue>) -> Self {
+        self.limits = self.limits.max_file_size(max_file_size);
+        self
+    }
+
+    /// Restrict the maximum number of threads that the command may create, see
+    /// [`CommandExt::max_threads`]
+    pub fn max_threads(mut self, max_threads: impl Into<LimitValue>) -> Self {
+        self.limits = self.limits.max_threads(max_threads);
+        self
+    }
+
+    /// Restrict the amount of CPU time that the command may use, in seconds, see
+    /// [`CommandExt::max_cpu_time`]
+    pub fn max_cpu_time(mut self, seconds: impl Into<LimitValue>) -> Self {
+        self.limits = self.limits.max_cpu_time(seconds);
+        self
+    }
+
+    /// Limit the command to `quota` of CPU time in each `period`, see
+    /// [`CommandExt::max_cpu_bandwidth`]
+    ///
+    /// The command must be moved into its own cgroup by a [`Phase::Privileged`] hook (see
+    /// [`SandboxProfile::hook`]), or spawning it always fails with `EBUSY`.
+    pub fn max_cpu_bandwidth(mut self, quota: Duration, period: Duration) -> Self {
+        self.max_cpu_bandwidth = Some((quota, period));
+        self
+    }
+
+    /// Set the nice value of the command, see [`CommandExt::nice`]
+    pub fn nice(mut self, value: i32) -> Self {
+        self.nice = Some(value);
+        self
+    }
+
+    /// Change the root directory of the command to `root`, and start it in `cwd`, see
+    /// [`CommandExt::chroot`]
+    ///
+    /// The root is changed after any tmpfs has been mounted (see [`SandboxProfile::add_tmpfs`]),
+    /// so their paths are on the host, and before the rules are applied, so the paths of the
+    /// rules are resolved within the new root.
+    pub fn chroot(mut self, root: impl Into<PathBuf>, cwd: impl Into<PathBuf>) -> Self {
+        self.chroot = Some((root.into(), cwd.into()));
+        self
+    }
+
+    /// Change the root directory of the command to `root`, and start it at the new root, see
+    /// [`CommandExt::chroot_to`]
+    pub fn chroot_to(self, root: impl Into<PathBuf>) -> Self {
+        self.chroot(root, "/")
+    }
+
+    /// Mount a private tmpfs of at most `size` at `path`, see [`CommandExt::add_tmpfs`]
+    pub fn add_tmpfs(mut self, path: impl Into<PathBuf>, size: MemorySize) -> Self {
+        self.tmpfs.push((path.into(), size));
+        self
+    }
+
+    /// Run the command in a new IPC namespace, see [`CommandExt::private_ipc`]
+    pub fn private_ipc(mut self) -> Self {
+        self.private_ipc = true;
+        self
+    }
+
+    /// Run the command as the user `uid`, see [`CommandExt::set_uid`]
+    ///
+    /// Unlike on a command, this may be combined with [`SandboxProfile::set_gid`] and
+    /// [`SandboxProfile::supplementary_groups`] in any order, since the groups are always changed
+    /// before the user.
+    pub fn set_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Run the command as the group `gid`, see [`CommandExt::set_gid`]
+    pub fn set_gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    /// Replace the supplementary groups of the command with `groups`, see
+    /// [`CommandExt::supplementary_groups`]
+    pub fn supplementary_groups(mut self, groups: &[u32]) -> Self {
+        self.supplementary_groups = Some(groups.to_vec());
+        self
+    }
+
+    /// Set the OOM score adjustment for the command, see [`CommandExt::oom_score_adj`]
+    pub fn oom_score_adj(mut self, adj: i32) -> Self {
+        self.oom_score_adj = Some(adj);
+        self
+    }
+
+    /// Run the command with the `SCHED_IDLE` scheduling policy, see
+    /// [`CommandExt::idle_priority`]
+    pub fn idle_priority(mut self) -> Self {
+        self.idle_priority = true;
+        self
+    }
+
+    /// Detach the command from the controlling terminal, see
+    /// [`CommandExt::detach_controlling_tty`]
+    pub fn detach_controlling_tty(mut self) -> Self {
+        self.detach_controlling_tty = true;
+        self
+    }
+
+    /// Kill the command once `deadline` has elapsed, see [`CommandExt::hard_deadline`]
+    pub fn hard_deadline(mut self, deadline: Duration) -> Self {
+        self.hard_deadline = Some(deadline);
+        self
+    }
+
+    /// Clear the environment of the command before setting any variables from
+    /// [`SandboxProfile::env`]
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self
+    }
+
+    /// Set an environment variable for the command
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
+    }
+
+    /// Run `hook` in the child during `phase`, before the command is executed
+    ///
+    /// Hooks run after the steps of the profile in the same phase, in the order in which they were
+    /// added.  Any error returned by a hook causes spawning the command to fail with that error.
+    ///
+    /// # SAFETY
+    ///
+    /// `hook` runs in the forked child, so it has the same requirements as
+    /// [`pre_exec`](std::os::unix::process::CommandExt::pre_exec): in particular, it must not
+    /// allocate or de-allocate memory.
+    pub unsafe fn hook(
+        mut self,
+        phase: Phase,
+        hook: impl Fn() -> io::Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.push((phase, Arc::new(hook)));
+        self
+    }
+
+    /// Apply this profile to `cmd`
+    ///
+    /// This works for both [`std::process::Command`] and `tokio::process::Command`, and is
+    /// equivalent to [`CommandExt::sandbox`].
+    pub fn wrap<'a, C: CommandExt>(&self, cmd: &'a mut C) -> &'a mut C {
+        cmd.sandbox(self)
+    }
+}
+
+impl fmt::Debug for SandboxProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SandboxProfile")
+            .field("rules", &self.rules)
+            .field("limits", &self.limits)
+            .field("max_cpu_bandwidth", &self.max_cpu_bandwidth)
+            .field("nice", &self.nice)
+            .field("chroot", &self.chroot)
+            .field("tmpfs", &self.tmpfs)
+            .field("private_ipc", &self.private_ipc)
+            .field("uid", &self.uid)
+            .field("gid", &self.gid)
+            .field("supplementary_groups", &self.supplementary_groups)
+            .field("oom_score_adj", &self.oom_score_adj)
+            .field("idle_priority", &self.idle_priority)
+            .field("detach_controlling_tty", &self.detach_controlling_tty)
+            .field("hard_deadline", &self.hard_deadline)
+            .field("env_clear", &self.env_clear)
+            .field("envs", &self.envs)
+            // The hooks can't be printed, so only show the phase of each
+            .field(
+                "hooks",
+                &self
+                    .hooks
+                    .iter()
+                    .map(|(phase, _)| phase)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    sync::Arc,
+    time::SystemTime,
+};
+
+use landlock::RulesetStatus;
+
+use crate::{prlimit::read_errno, ExposureReport, Phase, SandboxProfile, REQUESTED_ABI};
+
+/// A record of the sandbox which was applied to a single spawned command
+///
+/// Unlike an [`ExposureReport`], which describes what a policy _would_ expose, this is produced
+/// each time a command is spawned, and records what actually happened: the static parts are
+/// gathered in the parent before forking, and whether the rules were enforced is reported back by
+/// the child before it executes the command.  It is intended to be stored in an audit log or
+/// passed to an attestation pipeline.  See [`CommandExt::sandbox_audited`](crate::CommandExt::sandbox_audited).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct SandboxAudit {
+    /// The process ID of the command
+    pub pid: u32,
+    /// When this record was completed, right after the command was spawned
+    pub recorded_at: SystemTime,
+    /// The paths, ports, and limits which were exposed to the command
+    pub exposure: ExposureReport,
+    /// The OOM score adjustment of the command, if it was set
+    pub oom_score_adj: Option<i32>,
+    /// Whether the command runs with the `SCHED_IDLE` scheduling policy
+    pub idle_priority: bool,
+    /// Whether the command was detached from the controlling terminal in a new session
+    pub detach_controlling_tty: bool,
+    /// The landlock ABI which was requested, see [`Rules::with_abi`](crate::Rules::with_abi)
+    pub requested_abi: i32,
+    /// The landlock ABI which was enforced by the running kernel, or `None` if the profile has no
+    /// rules
+    pub effective_abi: Option<i32>,
+    /// How the rules were enforced, or `None` if the profile has no rules
+    pub enforcement: Option<Enforcement>,
+}
+
+/// How the rules of a sandbox were enforced, see [`landlock::RulesetStatus`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Enforcement {
+    /// All of the requested restrictions were enforced
+    FullyEnforced,
+    /// Some of the requested restrictions were not supported by the running kernel
+    PartiallyEnforced,
+    /// The rules were not enforced at all (e.g., because they are
+    /// [audit only](crate::Rules::audit_only))
+    NotEnforced,
+}
+
+impl Enforcement {
+    fn to_byte(status: RulesetStatus) -> u8 {
+        match status {
+            RulesetStatus::FullyEnforced => 0,
+            RulesetStatus::PartiallyEnforced => 1,
+            RulesetStatus::NotEnforced => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::FullyEnforced),
+            1 => Some(Self::PartiallyEnforced),
+            2 => Some(Self::NotEnforced),
+            _ => None,
+        }
+    }
+}
+
+/// A [`SandboxAudit`] which will be completed once the command has been spawned, see
+/// [`CommandExt::sandbox_audited`](crate::CommandExt::sandbox_audited)
+#[derive(Debug)]
+pub struct PendingAudit {
+    profile: SandboxProfile,
+    /// The read end of the pipe to which the child writes the enforcement status
+    status: OwnedFd,
+}
+
+impl PendingAudit {
+    /// Create a pending audit for `profile`, along with the profile to apply to the command, which
+    /// reports the enforcement status through a pipe instead of applying the rules directly
+    pub(crate) fn new(profile: &SandboxProfile) -> io::Result<(SandboxProfile, Self)> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` is valid for two file descriptors and any error is reported through `errno`
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) } != 0 {
+            return Err(read_errno());
+        }
+        // SAFETY: Both file descriptors were just opened and are not owned by anything else
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+        let mut wrapped = profile.clone();
+        if let Some(rules) = wrapped.rules.take() {
+            let write = Arc::new(write);
+            let hook = move || {
+                // SAFETY: We are restricting in a new process with nothing running, so restricting
+                // will break nothing
+                let status =
+                    unsafe { rules.restrict_self_with_status() }.map_err(io::Error::other)?;
+                let byte = Enforcement::to_byte(status.ruleset);
+                // SAFETY: `byte` is valid for one byte, and `write` is kept open by the closure
+                if unsafe { libc::write(write.as_raw_fd(), [byte].as_ptr().cast(), 1) } != 1 {
+                    return Err(read_errno());
+                }
+                Ok(())
+            };
+            // The rules must be applied before any other hooks of the same phase
+            wrapped.hooks.insert(0, (Phase::Restrict, Arc::new(hook)));
+        }
+
+        let pending = Self {
+            profile: profile.clone(),
+            status: read,
+        };
+        Ok((wrapped, pending))
+    }
+
+    /// Complete the record for the command with the process ID `pid`, which must have been spawned
+    /// successfully
+    ///
+    /// Each spawn of the command reports its status once, so this should be called exactly once
+    /// after each spawn.  Fails if the command was not spawned since the status would be missing.
+    pub fn finish(self, pid: u32) -> io::Result<SandboxAudit> {
+        let rules = self.profile.rules.as_deref();
+        let enforcement = match rules {
+            Some(_) => Some(self.read_status()?),
+            None => None,
+        };
+
+        #[cfg(feature = "tracing")]
+        crate::trace::enforced(pid, enforcement);
+
+        Ok(SandboxAudit {
+            pid,
+            recorded_at: SystemTime::now(),
+            exposure: self.profile.exposure_report(),
+            oom_score_adj: self.profile.oom_score_adj,
+            idle_priority: self.profile.idle_priority,
+            detach_controlling_tty: self.profile.detach_controlling_tty,
+            requested_abi: rules.map_or(REQUESTED_ABI, |r| r.abi()) as i32,
+            effective_abi: rules.map(|r| r.effective_abi() as i32),
+            enforcement,
+        })
+    }
+
+    /// Read the enforcement status which was written by the child
+    fn read_status(&self) -> io::Result<Enforcement> {
+        let mut byte = 0u8;
+        // SAFETY: `byte` is valid for one byte, and `status` is open
+        let ret = unsafe { libc::read(self.status.as_raw_fd(), (&mut byte as *mut u8).cast(), 1) };
+        match ret {
+            1 => Enforcement::from_byte(byte).ok_or_else(|| io::ErrorKind::InvalidData.into()),
+            0 => Err(io::ErrorKind::UnexpectedEof.into()),
+            _ => match read_errno() {
+                e if e.kind() == io::ErrorKind::WouldBlock => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the sandboxed command has not been spawned",
+                )),
+                e => Err(e),
+            },
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use landlock::{Access, AccessFs, AccessNet, BitFlags, ABI};
+
+use crate::{describe::write_access, FsType, LimitValue, Rules, SandboxProfile};
+
+/// A summary of everything that a sandbox exposes to a command
+///
+/// This is intended to be attached to a security review, or stored as an attestation of what a
+/// sandboxed command could do.  See [`Rules::exposure_report`] and
+/// [`SandboxProfile::exposure_report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub struct ExposureReport {
+    /// Paths beneath which files may be read or directories may be listed
+    pub readable: Vec<PathBuf>,
+    /// Paths beneath which files may be written, created, or removed
+    pub writable: Vec<PathBuf>,
+    /// Paths beneath which files may be executed
+    pub executable: Vec<PathBuf>,
+    /// Filesystem rights which the running kernel does not restrict for these rules (e.g.,
+    /// `truncate` before ABI 3), and which are so allowed beneath every path
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serde_support::serialize_access")
+    )]
+    pub unrestricted_access: BitFlags<AccessFs>,
+    /// TCP ports to which the command may bind, or `None` if binding is not restricted
+    pub bind_ports: Option<Vec<u16>>,
+    /// TCP ports to which the command may connect, or `None` if connecting is not restricted
+    pub connect_ports: Option<Vec<u16>>,
+    /// Filesystems on which access is never granted
+    pub denied_filesystems: Vec<FsType>,
+    /// Resource limits which are applied to the command, keyed by the name of the method of
+    /// [`ResourceLimits`](crate::ResourceLimits) which sets them, in bytes or seconds (or
+    /// `u64::MAX` if they are unlimited), and the timers `hard_deadline`,
+    /// `max_cpu_bandwidth_quota`, and `max_cpu_bandwidth_period` (in microseconds)
+    pub limits: BTreeMap<&'static str, u64>,
+}
+
+impl ExposureReport {
+    /// A report for a command which is not restricted at all
+    fn unrestricted() -> Self {
+        Self {
+            readable: vec!["/".into()],
+            writable: vec!["/".into()],
+            executable: vec!["/".into()],
+            unrestricted_access: BitFlags::all(),
+            bind_ports: None,
+            connect_ports: None,
+            denied_filesystems: Vec::new(),
+            limits: BTreeMap::new(),
+        }
+    }
+}
+
+impl Rules {
+    /// Summarise everything that these rules expose to a command on the running kernel
+    ///
+    /// Only what the kernel enforces (see [`Rules::effective_abi`]) is reported as restricted:
+    /// rights which it doesn't handle are reported in
+    /// [`unrestricted_access`](ExposureReport::unrestricted_access), and ports are reported as
+    /// unrestricted before ABI 4.  Rules which are [audit only](Rules::audit_only), or which are
+    /// used on a kernel without landlock, are reported as unrestricted.
+    pub fn exposure_report(&self) -> ExposureReport {
+        let effective = self.effective_abi();
+        if self.audit_only || matches!(effective, ABI::Unsupported) {
+            return ExposureReport::unrestricted();
+        }
+        let handled = AccessFs::from_all(effective);
+        let ports = |access| {
+            if AccessNet::from_all(effective).contains(access) {
+                self.ports(access).allowed()
+            } else {
+                None
+            }
+        };
+
+        let mut report = ExposureReport {
+            readable: Vec::new(),
+            writable: Vec::new(),
+            executable: Vec::new(),
+            unrestricted_access: BitFlags::all() & !handled,
+            bind_ports: ports(AccessNet::BindTcp),
+            connect_ports: ports(AccessNet::ConnectTcp),
+            denied_filesystems: self.denied_fs_types.clone(),
+            limits: BTreeMap::new(),
+        };
+
+        for (path, access) in self.path_grants() {
+            let access = access & handled;
+            let add = |paths: &mut Vec<PathBuf>| {
+                if !paths.iter().any(|p| p == path) {
+                    paths.push(path.to_path_buf());
+                }
+            };
+
+            if access.intersects(AccessFs::ReadFile | AccessFs::ReadDir) {
+                add(&mut report.readable);
+            }
+            if access.intersects(!(AccessFs::ReadFile | AccessFs::ReadDir | AccessFs::Execute)) {
+                add(&mut report.writable);
+            }
+            if access.contains(AccessFs::Execute) {
+                add(&mut report.executable);
+            }
+        }
+
+        report
+    }
+}
+
+impl SandboxProfile {
+    /// Summarise everything that this profile exposes to a command
+    ///
+    /// A profile without any rules does not restrict filesystem or network access, which is
+    /// reported as `/` being readable, writable, and executable, with any port being allowed.
+    pub fn exposure_report(&self) -> ExposureReport {
+        let mut report = match &self.rules {
+            Some(rules) => rules.exposure_report(),
+            None => ExposureReport::unrestricted(),
+        };
+
+        let limits = self
+            .limits
+            .entries()
+            .into_iter()
+            .map(|(name, _, limit)| (name, limit.map(LimitValue::to_rlim)));
+        let timers = [
+            (
+                "hard_deadline",
+                self.hard_deadline.map(|d| d.as_micros() as u64),
+            ),
+            (
+                "max_cpu_bandwidth_quota",
+                self.max_cpu_bandwidth.map(|(q, _)| q.as_micros() as u64),
+            ),
+            (
+                "max_cpu_bandwidth_period",
+                self.max_cpu_bandwidth.map(|(_, p)| p.as_micros() as u64),
+            ),
+        ];
+        report.limits.extend(
+            limits
+                .chain(timers)
+                .filter_map(|(name, limit)| Some((name, limit?))),
+        );
+
+        report
+    }
+}
+
+impl fmt::Display for ExposureReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, paths) in [
+            ("readable", &self.readable),
+            ("writable", &self.writable),
+            ("executable", &self.executable),
+        ] {
+            writeln!(f, "{name}:")?;
+            for path in paths {
+                writeln!(f, "  {}", path.display())?;
+            }
+        }
+
+        if !self.unrestricted_access.is_empty() {
+            write!(f, "unrestricted rights: ")?;
+            write_access(f, self.unrestricted_access)?;
+            writeln!(f)?;
+        }
+
+        for (name, ports) in [
+            ("bind ports", &self.bind_ports),
+            ("connect ports", &self.connect_ports),
+        ] {
+            write!(f, "{name}: ")?;
+            match ports {
+                None => writeln!(f, "any")?,
+                Some(ports) if ports.is_empty() => writeln!(f, "none")?,
+                Some(ports) => {
+                    let ports = ports.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+                    writeln!(f, "{}", ports.join(", "))?
+                }
+            }
+        }
+
+        if !self.denied_filesystems.is_empty() {
+            writeln!(f, "denied filesystems:")?;
+            for fs_type in &self.denied_filesystems {
+                writeln!(f, "  {:#x}", fs_type.magic())?;
+            }
+        }
+
+        if !self.limits.is_empty() {
+            writeln!(f, "limits:")?;
+            for (name, limit) in &self.limits {
+                writeln!(f, "  {name}: {limit}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
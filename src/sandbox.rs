@@ -0,0 +1,76 @@
+use std::{io, sync::Arc};
+
+use crate::{CommandExt, ResourceLimits, Rules, SandboxProfile};
+
+/// Rules and resource limits which are applied to commands together
+///
+/// This is a reusable, cloneable value to pass around instead of the rules and limits separately.
+/// With the `serde` feature, it can be loaded from a configuration file as an object with the
+/// optional fields `rules` (see [`Rules`]) and `limits`, whose fields are named after the methods
+/// of [`ResourceLimits`] (each is a number, a size string like `"512MiB"`, or `"unlimited"`).  See
+/// [`SandboxProfile`] to also configure the scheduling, environment, and other attributes of the
+/// command.
+///
+/// ```no_run
+/// # use std::process::Command;
+/// # use leucite::{MemorySize, ResourceLimits, Rules, Sandbox};
+/// let sandbox = Sandbox::new(
+///     Rules::new().add_read_only("/usr").into(),
+///     ResourceLimits::new().max_memory(MemorySize::from_mb(100)),
+/// );
+///
+/// sandbox.spawn(Command::new("/usr/bin/ls").arg("/usr"))?.wait()?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct Sandbox {
+    rules: Arc<Rules>,
+    limits: ResourceLimits,
+}
+
+impl Sandbox {
+    /// Create a sandbox which restricts commands using `rules` and applies `limits` to them
+    pub fn new(rules: Arc<Rules>, limits: ResourceLimits) -> Self {
+        Self { rules, limits }
+    }
+
+    /// Get the rules of the sandbox
+    pub fn rules(&self) -> &Arc<Rules> {
+        &self.rules
+    }
+
+    /// Get the resource limits of the sandbox
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Get a profile which applies the rules and limits of this sandbox, to configure the
+    /// command further
+    pub fn profile(&self) -> SandboxProfile {
+        SandboxProfile::new()
+            .restrict(Arc::clone(&self.rules))
+            .resource_limits(self.limits)
+    }
+
+    /// Apply the rules and limits of this sandbox to `cmd`
+    ///
+    /// This works for both [`std::process::Command`] and `tokio::process::Command`, and applies
+    /// [`Sandbox::profile`] to it, so the limits are applied before the rules.
+    pub fn apply<'a, C: CommandExt>(&self, cmd: &'a mut C) -> &'a mut C {
+        self.profile().wrap(cmd)
+    }
+
+    /// Apply the rules and limits of this sandbox to `cmd`, and spawn it
+    ///
+    /// This applies the limits in the same way as [`Sandbox::apply`], but restricts the command
+    /// using [`CommandExt::spawn_restricted`], so that an error while restricting is reported as
+    /// a [`ChildError`](crate::ChildError).
+    pub fn spawn<C: CommandExt>(&self, cmd: &mut C) -> io::Result<C::Child> {
+        SandboxProfile::new()
+            .resource_limits(self.limits)
+            .wrap(cmd)
+            .spawn_restricted(Arc::clone(&self.rules))
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::symlink},
+    path::{Path, PathBuf},
+};
+
+use crate::{prlimit::read_errno, MemorySize};
+
+/// A scratch directory for sandboxed commands, which can be cheaply reset between runs
+///
+/// This is useful when multiple commands should share a working directory (e.g., the output of a
+/// compile step which is used by a run step), but the directory should be wiped before it is used
+/// for something else, without the cost of creating a new directory (or tmpfs) each time.
+///
+/// ```no_run
+/// # use leucite::{Rules, ScratchDir};
+/// let scratch = ScratchDir::new("/tmp/judge")?;
+/// let rules = Rules::new().add_read_only("/usr").add_read_write(scratch.path());
+///
+/// // ... compile and run a submission in `scratch` ...
+///
+/// scratch.persist_to("/var/lib/judge/submission-1")?;
+/// scratch.reset()?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    mounted: bool,
+}
+
+impl ScratchDir {
+    /// Use `path` as a scratch directory, creating it if it does not exist.
+    ///
+    /// The directory is _not_ removed when this is dropped.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+        Ok(Self {
+            path,
+            mounted: false,
+        })
+    }
+
+    /// Mount a new tmpfs, which may hold at most `size`, at `path` and use it as a scratch
+    /// directory, creating `path` if it does not exist.
+    ///
+    /// This keeps writes to the scratch directory off of the disk.  Mounting requires
+    /// `CAP_SYS_ADMIN`.  The tmpfs is unmounted (and its contents discarded) when this is dropped.
+    pub fn tmpfs(path: impl Into<PathBuf>, size: MemorySize) -> io::Result<Self> {
+        let path = path.into();
+        fs::create_dir_all(&path)?;
+
+        let target = CString::new(path.as_os_str().as_bytes())?;
+        let options = CString::new(format!("size={},mode=0700", size.bytes()))?;
+        // SAFETY: All of the strings are valid and nul-terminated, and any error is reported
+        // through `errno`.
+        let ret = unsafe {
+            libc::mount(
+                c"tmpfs".as_ptr(),
+                target.as_ptr(),
+                c"tmpfs".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                options.as_ptr().cast(),
+            )
+        };
+        if ret != 0 {
+            return Err(read_errno());
+        }
+
+        Ok(Self {
+            path,
+            mounted: true,
+        })
+    }
+
+    /// Get the path of the scratch directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove everything within the scratch directory, leaving the directory itself in place
+    pub fn reset(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            // `file_type` does not follow symlinks, so a link to a directory is only unlinked
+            if entry.file_type()?.is_dir() {
+                fs::remove_dir_all(entry.path())?;
+            } else {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Recursively copy the contents of the scratch directory into `dest`, creating it if it does
+    /// not exist
+    ///
+    /// Symbolic links are copied as links, rather than following them.  Anything which is neither
+    /// a regular file, a directory nor a symbolic link (e.g., a FIFO left behind by a command) is
+    /// skipped.  This fails with [`io::ErrorKind::InvalidInput`] if `dest` is within the scratch
+    /// directory, since the copy would then contain itself.
+    pub fn persist_to(&self, dest: impl AsRef<Path>) -> io::Result<()> {
+        let dest = dest.as_ref();
+        if resolve(dest)?.starts_with(self.path.canonicalize()?) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} is within the scratch directory {}",
+                    dest.display(),
+                    self.path.display()
+                ),
+            ));
+        }
+        copy_dir(&self.path, dest)
+    }
+}
+
+/// Canonicalize `path`, which may not exist yet, by canonicalizing its closest existing ancestor
+fn resolve(path: &Path) -> io::Result<PathBuf> {
+    let path = std::env::current_dir()?.join(path);
+    let mut existing = path.as_path();
+    let mut rest = Vec::new();
+    loop {
+        match existing.canonicalize() {
+            Ok(resolved) => return Ok(rest.into_iter().rev().fold(resolved, |p, c| p.join(c))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let (Some(parent), Some(name)) = (existing.parent(), existing.file_name()) else {
+                    return Err(e);
+                };
+                rest.push(name);
+                existing = parent;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl AsRef<Path> for ScratchDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if !self.mounted {
+            return;
+        }
+
+        if let Ok(target) = CString::new(self.path.as_os_str().as_bytes()) {
+            // SAFETY: `target` is a valid, nul-terminated string.  There is nothing to be done
+            // about an error while dropping.
+            unsafe { libc::umount2(target.as_ptr(), libc::MNT_DETACH) };
+        }
+    }
+}
+
+fn copy_dir(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let to = dest.join(entry.file_name());
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &to)?;
+        } else if file_type.is_symlink() {
+            symlink(fs::read_link(entry.path())?, to)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), to)?;
+        }
+    }
+    Ok(())
+}
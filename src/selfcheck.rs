@@ -0,0 +1,397 @@
+use std::{
+    ffi::{CString, OsStr},
+    fmt, fs, io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt, process::ExitStatusExt},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use landlock::{AccessFs, AccessNet};
+
+use crate::{CommandExt, LimitValue, Phase, SandboxProfile};
+
+/// Exit code of the child when the restriction which is checked was enforced
+const EFFECTIVE: libc::c_int = 10;
+/// Exit code of the child when the restriction which is checked was not enforced
+const NOT_EFFECTIVE: libc::c_int = 11;
+
+/// How long the child burns CPU time waiting to be killed by the hard deadline or the CPU time
+/// limit
+const BURN_TIME: libc::time_t = 5;
+
+/// The result of checking a single restriction, see [`self_check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CheckOutcome {
+    /// The restriction was enforced
+    Effective,
+    /// The restriction is configured, but was not enforced on this host (e.g., the rules are
+    /// [audit only](crate::Rules::audit_only))
+    NotEffective,
+    /// The restriction is configured, but the profile grants everything that could be used to
+    /// check it
+    Unverified,
+    /// The restriction is not configured by the profile
+    Skipped,
+    /// The check could not be run, e.g., because the sandbox could not be applied
+    Failed(String),
+}
+
+impl fmt::Display for CheckOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Effective => f.write_str("effective"),
+            Self::NotEffective => f.write_str("NOT effective"),
+            Self::Unverified => f.write_str("unverified"),
+            Self::Skipped => f.write_str("skipped"),
+            Self::Failed(reason) => write!(f, "failed: {reason}"),
+        }
+    }
+}
+
+/// Which of the restrictions of a [`SandboxProfile`] are actually enforced on this host, see
+/// [`self_check`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SelfCheckReport {
+    /// Whether reading a file which is not granted by the rules is denied
+    pub filesystem: CheckOutcome,
+    /// Whether binding to a TCP port which is not allowed by the rules is denied
+    pub network: CheckOutcome,
+    /// Whether allocating more than [`SandboxProfile::max_memory`] fails
+    pub max_memory: CheckOutcome,
+    /// Whether a command which burns CPU time is killed by [`SandboxProfile::max_cpu_time`]
+    pub max_cpu_time: CheckOutcome,
+    /// Whether a command which burns CPU time is killed by [`SandboxProfile::hard_deadline`]
+    pub hard_deadline: CheckOutcome,
+    /// Whether the command runs in new namespaces, see [`SandboxProfile::private_ipc`] and
+    /// [`SandboxProfile::add_tmpfs`]
+    pub namespaces: CheckOutcome,
+    /// Whether the command runs in a new session, see [`SandboxProfile::detach_controlling_tty`]
+    pub detach_controlling_tty: CheckOutcome,
+}
+
+impl SelfCheckReport {
+    /// Each check along with its name
+    pub fn checks(&self) -> [(&'static str, &CheckOutcome); 7] {
+        [
+            ("filesystem", &self.filesystem),
+            ("network", &self.network),
+            ("max_memory", &self.max_memory),
+            ("max_cpu_time", &self.max_cpu_time),
+            ("hard_deadline", &self.hard_deadline),
+            ("namespaces", &self.namespaces),
+            ("detach_controlling_tty", &self.detach_controlling_tty),
+        ]
+    }
+
+    /// Whether every restriction which is configured by the profile is enforced
+    ///
+    /// A restriction which is configured but could not be verified does not count as enforced.
+    pub fn is_effective(&self) -> bool {
+        self.checks()
+            .iter()
+            .all(|(_, c)| matches!(c, CheckOutcome::Effective | CheckOutcome::Skipped))
+    }
+}
+
+impl fmt::Display for SelfCheckReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, outcome) in self.checks() {
+            writeln!(f, "{name}: {outcome}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that each of the restrictions of `profile` is actually enforced on this host
+///
+/// This is intended as a pre-flight check when a service starts, before it accepts any jobs, to
+/// catch restrictions which silently have no effect (e.g., landlock being disabled in the kernel).
+/// For each restriction which is configured by `profile`, a child is spawned with the profile
+/// applied, which then tries to violate the restriction _before_ executing anything, so no helper
+/// binary needs to be accessible in the sandbox:
+///
+/// - `filesystem`: reads a temporary file, or `/etc/passwd`, if the rules don't grant it.  This is
+///   unverified if the profile changes the root, since these files can't be reached then.
+/// - `network`: binds to a TCP port on the loopback address which the rules don't allow
+/// - `max_memory`: maps twice the memory limit.  This is unverified if the parent can't map that
+///   much either.
+/// - `max_cpu_time`: burns CPU time until it is killed.  The limit of the profile is replaced by
+///   one second, so that this doesn't take as long as the limit.
+/// - `hard_deadline`: burns CPU time until it is killed.  The deadline of the profile is replaced
+///   by a short one, so that this doesn't take as long as the deadline.
+/// - `namespaces`: checks that its IPC and mount namespaces differ from those of the parent
+/// - `detach_controlling_tty`: checks that it is the leader of its session
+///
+/// Rules which are [audit only](crate::Rules::audit_only) are reported as not effective, since
+/// they never deny anything.  Each check takes a few milliseconds, except for the hard deadline,
+/// which takes 100ms, and the CPU time limit, which takes a second.
+pub fn self_check(profile: &SandboxProfile) -> SelfCheckReport {
+    SelfCheckReport {
+        filesystem: check_filesystem(profile),
+        network: check_network(profile),
+        max_memory: check_max_memory(profile),
+        max_cpu_time: check_max_cpu_time(profile),
+        hard_deadline: check_hard_deadline(profile),
+        namespaces: check_namespaces(profile),
+        detach_controlling_tty: check_session(profile),
+    }
+}
+
+fn check_filesystem(profile: &SandboxProfile) -> CheckOutcome {
+    let Some(rules) = profile.rules.as_deref() else {
+        return CheckOutcome::Skipped;
+    };
+    if rules.is_audit_only() {
+        return CheckOutcome::NotEffective;
+    }
+    // The candidates are paths on the host, which can't be reached from within another root
+    if profile.chroot.is_some() {
+        return CheckOutcome::Unverified;
+    }
+
+    let nanos = SystemTime::UNIX_EPOCH
+        .elapsed()
+        .unwrap_or_default()
+        .subsec_nanos();
+    let temp = std::env::temp_dir()
+        .canonicalize()
+        .unwrap_or_else(|_| std::env::temp_dir())
+        .join(format!("leucite-self-check-{}-{nanos}", std::process::id()));
+    let created = fs::write(&temp, "").is_ok();
+
+    let candidates = [temp.clone(), PathBuf::from("/etc/passwd")];
+    let denied = candidates
+        .into_iter()
+        .filter(|path| {
+            !profile
+                .tmpfs
+                .iter()
+                .any(|(mount, _)| path.starts_with(mount))
+        })
+        .find(|path| path.exists() && !rules.missing_access(path, AccessFs::ReadFile).is_empty());
+    let outcome = match denied.map(|p| CString::new(p.as_os_str().as_bytes())) {
+        Some(Ok(path)) => run_check(profile.clone(), &[], move || {
+            // SAFETY: `path` is a valid, nul-terminated string
+            let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+            // A file which can't be found in the sandbox doesn't mean that reading it was denied
+            fd < 0 && crate::prlimit::read_errno().raw_os_error() == Some(libc::EACCES)
+        }),
+        Some(Err(e)) => CheckOutcome::Failed(e.to_string()),
+        None => CheckOutcome::Unverified,
+    };
+
+    if created {
+        let _ = fs::remove_file(&temp);
+    }
+    outcome
+}
+
+fn check_network(profile: &SandboxProfile) -> CheckOutcome {
+    let Some(rules) = profile.rules.as_deref() else {
+        return CheckOutcome::Skipped;
+    };
+    let Some(allowed) = rules.ports(AccessNet::BindTcp).allowed() else {
+        return CheckOutcome::Skipped;
+    };
+    if rules.is_audit_only() {
+        return CheckOutcome::NotEffective;
+    }
+    let Some(port) = (1..=u16::MAX).rev().find(|p| !allowed.contains(p)) else {
+        return CheckOutcome::Skipped;
+    };
+
+    run_check(profile.clone(), &[], move || {
+        // SAFETY: `socket` has no memory safety requirements
+        let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_STREAM | libc::SOCK_CLOEXEC, 0) };
+        if fd < 0 {
+            return false;
+        }
+        let addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: port.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        // SAFETY: `addr` is a valid `sockaddr_in` of the given length
+        let ret = unsafe {
+            libc::bind(
+                fd,
+                (&addr as *const libc::sockaddr_in).cast(),
+                std::mem::size_of_val(&addr) as libc::socklen_t,
+            )
+        };
+        // Another process using the port does not mean that the bind was allowed
+        ret != 0 && crate::prlimit::read_errno().raw_os_error() == Some(libc::EACCES)
+    })
+}
+
+fn check_max_memory(profile: &SandboxProfile) -> CheckOutcome {
+    let Some(LimitValue::Bounded(max_memory)) = profile.limits.max_memory else {
+        return CheckOutcome::Skipped;
+    };
+    // A mapping which fails without the limit as well doesn't say anything about the limit
+    let Some(len) = max_memory
+        .checked_mul(2)
+        .and_then(|len| usize::try_from(len).ok())
+        .filter(|&len| map_anonymous(len).is_ok())
+    else {
+        return CheckOutcome::Unverified;
+    };
+
+    run_check(profile.clone(), &[], move || {
+        map_anonymous(len).is_err_and(|e| e.raw_os_error() == Some(libc::ENOMEM))
+    })
+}
+
+/// Map `len` bytes of anonymous memory without reserving it, and unmap it again if that succeeded
+fn map_anonymous(len: usize) -> io::Result<()> {
+    // SAFETY: A new private mapping does not alias any existing memory
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return Err(crate::prlimit::read_errno());
+    }
+    // SAFETY: The mapping was created above and is not used by anything else
+    unsafe { libc::munmap(ptr, len) };
+    Ok(())
+}
+
+fn check_max_cpu_time(profile: &SandboxProfile) -> CheckOutcome {
+    if !matches!(profile.limits.max_cpu_time, Some(LimitValue::Bounded(_))) {
+        return CheckOutcome::Skipped;
+    }
+    let mut profile = profile.clone().max_cpu_time(1);
+    // Only the CPU time limit may kill the check
+    profile.hard_deadline = None;
+
+    // Exceeding the soft limit sends `SIGXCPU`, and exceeding the hard limit sends `SIGKILL`
+    run_check(profile, &[libc::SIGXCPU, libc::SIGKILL], || {
+        burn(libc::CLOCK_PROCESS_CPUTIME_ID)
+    })
+}
+
+fn check_hard_deadline(profile: &SandboxProfile) -> CheckOutcome {
+    if profile.hard_deadline.is_none() {
+        return CheckOutcome::Skipped;
+    }
+    let profile = profile.clone().hard_deadline(Duration::from_millis(100));
+
+    run_check(profile, &[libc::SIGALRM], || burn(libc::CLOCK_MONOTONIC))
+}
+
+/// Burn CPU time until `BURN_TIME` has elapsed on `clock`, returning `false` if the process was
+/// not killed by then
+fn burn(clock: libc::clockid_t) -> bool {
+    let mut start = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let mut now = start;
+    // SAFETY: `start` and `now` are valid `timespec`s
+    unsafe { libc::clock_gettime(clock, &mut start) };
+    while now.tv_sec - start.tv_sec < BURN_TIME {
+        // SAFETY: `now` is a valid `timespec`
+        unsafe { libc::clock_gettime(clock, &mut now) };
+    }
+    // The command should have been killed by now
+    false
+}
+
+fn check_namespaces(profile: &SandboxProfile) -> CheckOutcome {
+    let mut namespaces = Vec::new();
+    if profile.private_ipc {
+        namespaces.push(c"/proc/self/ns/ipc");
+    }
+    if !profile.tmpfs.is_empty() {
+        namespaces.push(c"/proc/self/ns/mnt");
+    }
+    if namespaces.is_empty() {
+        return CheckOutcome::Skipped;
+    }
+
+    let mut host = Vec::new();
+    for path in namespaces {
+        match fs::metadata(OsStr::from_bytes(path.to_bytes())) {
+            Ok(metadata) => host.push((path, metadata.ino())),
+            Err(e) => return CheckOutcome::Failed(e.to_string()),
+        }
+    }
+
+    // `/proc` may not be visible once the root directory has been changed
+    let mut profile = profile.clone();
+    profile.chroot = None;
+
+    run_check(profile, &[], move || {
+        host.iter().all(|(path, ino)| {
+            // SAFETY: An all-zero `stat` is valid
+            let mut stat = unsafe { std::mem::zeroed::<libc::stat>() };
+            // SAFETY: `path` is a valid, nul-terminated string, and `stat` is a valid `stat`
+            let ret = unsafe { libc::stat(path.as_ptr(), &mut stat) };
+            ret == 0 && stat.st_ino != *ino
+        })
+    })
+}
+
+fn check_session(profile: &SandboxProfile) -> CheckOutcome {
+    if !profile.detach_controlling_tty {
+        return CheckOutcome::Skipped;
+    }
+
+    // SAFETY: `getsid` and `getpid` have no memory safety requirements
+    run_check(profile.clone(), &[], || unsafe {
+        libc::getsid(0) == libc::getpid()
+    })
+}
+
+/// Spawn a child with `profile` applied, which runs `check` once the profile has been applied and
+/// exits without executing anything
+///
+/// `check` returns whether the restriction was enforced.  Being killed by one of `signals` also
+/// counts as being enforced.
+fn run_check(
+    mut profile: SandboxProfile,
+    signals: &[libc::c_int],
+    check: impl Fn() -> bool + Send + Sync + 'static,
+) -> CheckOutcome {
+    let hook = move || {
+        let code = if check() { EFFECTIVE } else { NOT_EFFECTIVE };
+        // SAFETY: Exiting without running any destructors or `atexit` handlers is what the child
+        // would do if `exec` failed
+        unsafe { libc::_exit(code) }
+    };
+    // Run after everything else, so that every step of the profile has been applied
+    profile.hooks.push((Phase::Restrict, Arc::new(hook)));
+
+    // The program is never executed, since the check exits before that
+    let status = Command::new("/bin/true")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .sandbox(&profile)
+        .status();
+
+    match status {
+        Ok(s)
+            if s.code() == Some(EFFECTIVE) || s.signal().is_some_and(|s| signals.contains(&s)) =>
+        {
+            CheckOutcome::Effective
+        }
+        Ok(s) if s.code() == Some(NOT_EFFECTIVE) => CheckOutcome::NotEffective,
+        Ok(s) => CheckOutcome::Failed(format!("the check exited with {s}")),
+        Err(e) => CheckOutcome::Failed(e.to_string()),
+    }
+}
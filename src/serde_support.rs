@@ -0,0 +1,334 @@
+//! `serde` implementations for the types which can't simply derive them
+
+use landlock::{AccessFs, BitFlags};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use std::fmt;
+
+use crate::{json::ACCESS_NAMES, LimitValue, MemorySize, Ports};
+
+/// The ports of [`Rules`](crate::Rules) as they are serialized: `"any"`, `"none"`, or a list of
+/// ports, where an empty list allows no ports
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum PortsRepr {
+    Keyword(PortsKeyword),
+    Only(Vec<u16>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PortsKeyword {
+    Any,
+    None,
+}
+
+impl Serialize for Ports {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Any => PortsRepr::Keyword(PortsKeyword::Any),
+            Self::None => PortsRepr::Keyword(PortsKeyword::None),
+            Self::Only(ports) => PortsRepr::Only(ports.clone()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ports {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match PortsRepr::deserialize(deserializer)? {
+            PortsRepr::Keyword(PortsKeyword::Any) => Self::Any,
+            PortsRepr::Keyword(PortsKeyword::None) => Self::None,
+            PortsRepr::Only(ports) => Self::Only(ports),
+        })
+    }
+}
+
+/// Get the names of the `LANDLOCK_ACCESS_FS_*` rights in `access`
+fn access_names(access: BitFlags<AccessFs>) -> Vec<&'static str> {
+    ACCESS_NAMES
+        .iter()
+        .filter(|(right, _)| access.contains(*right))
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Parse the names of `LANDLOCK_ACCESS_FS_*` rights, see [`access_names`]
+fn parse_access<E: de::Error>(names: &[String]) -> Result<BitFlags<AccessFs>, E> {
+    names.iter().try_fold(BitFlags::empty(), |acc, name| {
+        match ACCESS_NAMES.iter().find(|(_, n)| n == name) {
+            Some((right, _)) => Ok(acc | *right),
+            None => Err(de::Error::custom(format!("unknown access right `{name}`"))),
+        }
+    })
+}
+
+/// Serialize access rights as the names of the `LANDLOCK_ACCESS_FS_*` rights
+pub(crate) fn serialize_access<S: Serializer>(
+    access: &BitFlags<AccessFs>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    access_names(*access).serialize(serializer)
+}
+
+/// Access rights which are serialized as the names of the `LANDLOCK_ACCESS_FS_*` rights, e.g.,
+/// `["read_file", "read_dir"]`, or `null` if they are not overridden
+pub(crate) mod access {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        access: &Option<BitFlags<AccessFs>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        access.map(access_names).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<BitFlags<AccessFs>>, D::Error> {
+        Option::<Vec<String>>::deserialize(deserializer)?
+            .map(|names| parse_access(&names))
+            .transpose()
+    }
+}
+
+/// Paths with their own access rights, which are serialized as a list of objects like
+/// `{"path": "/var/log", "access": ["write_file"]}`
+pub(crate) mod path_access {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct PathAccess<T> {
+        path: PathBuf,
+        access: Vec<T>,
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        paths: &[(PathBuf, BitFlags<AccessFs>)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        paths
+            .iter()
+            .map(|(path, access)| PathAccess {
+                path: path.clone(),
+                access: access_names(*access),
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(PathBuf, BitFlags<AccessFs>)>, D::Error> {
+        Vec::<PathAccess<String>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|p| Ok((p.path, parse_access(&p.access)?)))
+            .collect()
+    }
+}
+
+/// Accepts a number of bytes, or a size like `"512MiB"`, see
+/// [`MemorySize::from_str`](std::str::FromStr)
+struct MemorySizeVisitor;
+
+impl de::Visitor<'_> for MemorySizeVisitor {
+    type Value = MemorySize;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a number of bytes, or a size like \"512MiB\"")
+    }
+
+    fn visit_u64<E: de::Error>(self, bytes: u64) -> Result<Self::Value, E> {
+        Ok(MemorySize::from_bytes(bytes))
+    }
+
+    fn visit_i64<E: de::Error>(self, bytes: i64) -> Result<Self::Value, E> {
+        u64::try_from(bytes)
+            .map(MemorySize::from_bytes)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(bytes), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        s.parse().map_err(E::custom)
+    }
+}
+
+/// A [`MemorySize`] is serialized as its number of bytes, and can be deserialized from either a
+/// number of bytes or a string like `"512MiB"`, see [`MemorySize::from_str`](std::str::FromStr)
+///
+/// Formats which are not self-describing (e.g., `bincode`) only accept a number of bytes.
+impl Serialize for MemorySize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for MemorySize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(MemorySizeVisitor)
+        } else {
+            deserializer.deserialize_u64(MemorySizeVisitor)
+        }
+    }
+}
+
+/// The representation of a [`LimitValue`] in formats which are not self-describing
+#[derive(Serialize, Deserialize)]
+enum LimitValueRepr {
+    Unlimited,
+    Bounded(u64),
+}
+
+/// Accepts a number or `"unlimited"`, and a size like `"512MiB"` if `sizes` is set
+struct LimitValueVisitor {
+    sizes: bool,
+}
+
+impl de::Visitor<'_> for LimitValueVisitor {
+    type Value = LimitValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.sizes {
+            f.write_str("a number of bytes, a size like \"512MiB\", or \"unlimited\"")
+        } else {
+            f.write_str("a number, or \"unlimited\"")
+        }
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Self::Value, E> {
+        Ok(LimitValue::Bounded(value))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Self::Value, E> {
+        u64::try_from(value)
+            .map(LimitValue::Bounded)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(value), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        match s {
+            "unlimited" => Ok(LimitValue::Unlimited),
+            _ if self.sizes => MemorySizeVisitor.visit_str(s).map(Into::into),
+            _ => Err(E::invalid_value(de::Unexpected::Str(s), &self)),
+        }
+    }
+}
+
+impl LimitValue {
+    fn deserialize_with<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        sizes: bool,
+    ) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(LimitValueVisitor { sizes })
+        } else {
+            Ok(match LimitValueRepr::deserialize(deserializer)? {
+                LimitValueRepr::Unlimited => Self::Unlimited,
+                LimitValueRepr::Bounded(value) => Self::Bounded(value),
+            })
+        }
+    }
+}
+
+/// A [`LimitValue`] is serialized as its value, or as `"unlimited"`, and can be deserialized from
+/// either of those
+///
+/// Formats which are not self-describing (e.g., `bincode`) use an enum with the variants of
+/// [`LimitValue`] instead.  A limit in bytes of [`ResourceLimits`](crate::ResourceLimits) also
+/// accepts a size like `"512MiB"`.
+impl Serialize for LimitValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self, serializer.is_human_readable()) {
+            (Self::Unlimited, true) => serializer.serialize_str("unlimited"),
+            (Self::Bounded(value), true) => serializer.serialize_u64(*value),
+            (Self::Unlimited, false) => LimitValueRepr::Unlimited.serialize(serializer),
+            (Self::Bounded(value), false) => LimitValueRepr::Bounded(*value).serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LimitValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::deserialize_with(deserializer, false)
+    }
+}
+
+/// A limit in bytes of [`ResourceLimits`](crate::ResourceLimits), which may also be a size like
+/// `"512MiB"`
+pub(crate) mod byte_limit {
+    use super::*;
+
+    struct ByteLimit(LimitValue);
+
+    impl<'de> Deserialize<'de> for ByteLimit {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            LimitValue::deserialize_with(deserializer, true).map(Self)
+        }
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        limit: &Option<LimitValue>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        limit.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<LimitValue>, D::Error> {
+        Ok(Option::<ByteLimit>::deserialize(deserializer)?.map(|limit| limit.0))
+    }
+}
+
+/// Serialize a [`MemorySize`] as a human-readable string, for use with `#[serde(with = "...")]`
+///
+/// The size is written in the largest binary unit which represents it exactly (e.g., `"512 MiB"`,
+/// or `"1000 B"`), so it is deserialized to the same size.  Deserializing accepts the same forms
+/// as [`MemorySize`] itself.
+///
+/// ```
+/// # use leucite::MemorySize;
+/// #[derive(serde::Serialize, serde::Deserialize)]
+/// struct Limits {
+///     #[serde(with = "leucite::human_memory_size")]
+///     max_memory: MemorySize,
+/// }
+///
+/// let limits = Limits { max_memory: MemorySize::from_mib(512) };
+/// assert_eq!(serde_json::to_string(&limits)?, r#"{"max_memory":"512 MiB"}"#);
+/// # Ok::<_, serde_json::Error>(())
+/// ```
+pub mod human_memory_size {
+    use super::*;
+
+    /// Serialize `size` in the largest binary unit which represents it exactly
+    pub fn serialize<S: Serializer>(size: &MemorySize, serializer: S) -> Result<S::Ok, S::Error> {
+        let bytes = size.bytes();
+        // Each unit is a power of two, given as the number of bits to shift by
+        let unit = [("GiB", 30), ("MiB", 20), ("KiB", 10)]
+            .into_iter()
+            .find(|(_, shift)| bytes != 0 && bytes.trailing_zeros() >= *shift);
+        match unit {
+            Some((name, shift)) => {
+                serializer.collect_str(&format_args!("{} {name}", bytes >> shift))
+            }
+            None => serializer.collect_str(&format_args!("{bytes} B")),
+        }
+    }
+
+    /// Deserialize a size from a number of bytes or a string, like [`MemorySize`]
+    ///
+    /// Formats which are not self-describing (e.g., `bincode`) only accept a string, since that
+    /// is how the size is serialized.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MemorySize, D::Error> {
+        if deserializer.is_human_readable() {
+            MemorySize::deserialize(deserializer)
+        } else {
+            deserializer.deserialize_str(MemorySizeVisitor)
+        }
+    }
+}
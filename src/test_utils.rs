@@ -0,0 +1,92 @@
+//! Helpers to test [`Rules`] against Rust code, rather than against external programs
+//!
+//! ```
+//! # use leucite::{test_utils::run_restricted, Rules};
+//! let rules = Rules::new().add_read_only("/usr");
+//!
+//! assert!(run_restricted(&rules, || std::fs::metadata("/usr/bin").is_ok())?);
+//! assert!(run_restricted(&rules, || std::fs::read("/etc/passwd").is_err())?);
+//! # Ok::<_, std::io::Error>(())
+//! ```
+use std::{
+    io,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{prlimit::read_errno, ErrorPipe, Rules};
+
+/// Exit code of the child when the closure returned `true`
+const TRUE: libc::c_int = 0;
+/// Exit code of the child when the closure returned `false`
+const FALSE: libc::c_int = 1;
+/// Exit code of the child when it could not be restricted
+const RESTRICT_FAILED: libc::c_int = 2;
+/// Exit code of the child when the closure panicked
+const PANICKED: libc::c_int = 3;
+
+/// Fork a child, restrict it using `rules`, and run `f` in it, returning the result of `f`
+///
+/// Landlock can never be undone, so policies can't be tested in the process that runs the tests.
+/// This runs `f` in a forked child instead, so a test can check what a policy allows using
+/// ordinary Rust code (e.g., [`std::fs::read`] or [`std::net::TcpStream::connect`]) without
+/// compiling or installing a helper program.
+///
+/// Fails if the child could not be restricted, in which case the error wraps a
+/// [`ChildError`](crate::ChildError), or if `f` panicked or the child was killed.
+///
+/// The child is forked from a process which is likely to run other threads (e.g., other tests),
+/// of which only the calling thread is copied into the child.  `f` should therefore avoid
+/// anything which might wait on another thread, such as a lock which was held during the fork.
+/// Allocating is fine with the system allocator of glibc and musl, which make sure that it can
+/// be used after forking.
+pub fn run_restricted(rules: &Rules, f: impl FnOnce() -> bool) -> io::Result<bool> {
+    let pipe = ErrorPipe::new()?;
+
+    // SAFETY: The child only restricts itself, runs `f`, and exits without returning
+    match unsafe { libc::fork() } {
+        -1 => Err(read_errno()),
+        0 => {
+            // SAFETY: We are restricting in a new process, which only runs `f` afterwards
+            let code = match unsafe { rules.restrict_self() } {
+                Err(e) => {
+                    pipe.report(rules, &e);
+                    RESTRICT_FAILED
+                }
+                Ok(()) => match panic::catch_unwind(AssertUnwindSafe(f)) {
+                    Ok(true) => TRUE,
+                    Ok(false) => FALSE,
+                    Err(_) => PANICKED,
+                },
+            };
+            // SAFETY: Exiting without running any destructors or `atexit` handlers of the parent
+            unsafe { libc::_exit(code) }
+        }
+        pid => {
+            let mut status = 0;
+            // SAFETY: `pid` is our child, and `status` is a valid pointer
+            while unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+                let e = read_errno();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(e);
+                }
+            }
+
+            if libc::WIFSIGNALED(status) {
+                let signal = libc::WTERMSIG(status);
+                return Err(io::Error::other(format!(
+                    "the child was killed by signal {signal}"
+                )));
+            }
+            match libc::WEXITSTATUS(status) {
+                TRUE => Ok(true),
+                FALSE => Ok(false),
+                RESTRICT_FAILED => Err(match pipe.take(rules) {
+                    Some(e) => io::Error::other(e),
+                    None => io::Error::other("the child could not be restricted"),
+                }),
+                PANICKED => Err(io::Error::other("the closure panicked in the child")),
+                code => Err(io::Error::other(format!("the child exited with {code}"))),
+            }
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Utilities for sandboxed commands which are run using [`tokio`](::tokio)
+//!
+//! [`CommandExt`] is implemented for [`tokio::process::Command`](::tokio::process::Command) as
+//! well as the standard library's `Command`, and is re-exported here so that everything that
+//! tokio users need can be imported from this module.  Its methods which spawn the command return
+//! a [`tokio::process::Child`](::tokio::process::Child).  [`TokioCommandExt`] adds methods which
+//! only exist for tokio, since they also wait for the command.
+//!
+//! ```
+//! # use std::sync::Arc;
+//! use leucite::{tokio::CommandExt, Rules};
+//! use tokio::process::Command;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> std::io::Result<()> {
+//! let rules = Arc::new(Rules::system_readonly());
+//! let status = Command::new("true").spawn_restricted(rules)?.wait().await?;
+//! assert!(status.success());
+//! # Ok(())
+//! # }
+//! ```
+use std::{
+    future::{poll_fn, Future},
+    io,
+    pin::pin,
+    process::ExitStatus,
+    sync::Arc,
+    task::Poll,
+    time::Duration,
+};
+
+use ::tokio::process::{Child, Command};
+
+use crate::{prlimit::read_errno, resident_memory, MemorySize, Rules, WaitOutcome};
+
+pub use crate::CommandExt;
+
+/// Wait for `child` to exit, killing it if `cancel` completes first.
+///
+/// This ties the lifetime of a sandboxed command to structured cancellation, such as a
+/// `CancellationToken::cancelled()` future, or a [`tokio::time::sleep`](::tokio::time::sleep).
+/// See [`TokioCommandExt::spawn_restricted_with_cancel`] to spawn the command and wait for it in
+/// one step.
+///
+/// If the child is the leader of its own process group (i.e., it was spawned with
+/// [`process_group(0)`](::tokio::process::Command::process_group)), the entire group is killed so
+/// that none of its descendants outlive the cancellation.
+///
+/// Note that if the returned future is _dropped_ before completing, the child is not killed.  Use
+/// [`kill_on_drop`](::tokio::process::Command::kill_on_drop) to cover that case as well.
+///
+/// ```no_run
+/// # use leucite::{Rules, CommandExt, WaitOutcome};
+/// # use tokio::process::Command;
+/// # use std::time::Duration;
+/// # async fn f() -> std::io::Result<()> {
+/// let mut child = Command::new("sleep")
+///     .arg("60")
+///     .restrict(Rules::new().add_read_only("/usr").into())
+///     .spawn()?;
+///
+/// let outcome =
+///     leucite::tokio::wait_or_cancel(&mut child, tokio::time::sleep(Duration::from_secs(1)))
+///         .await?;
+/// assert_eq!(outcome, WaitOutcome::Cancelled);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn wait_or_cancel<F>(child: &mut Child, cancel: F) -> io::Result<WaitOutcome>
+where
+    F: Future,
+{
+    if let Some(status) = wait_until(child, cancel).await? {
+        return Ok(WaitOutcome::Exited(status));
+    }
+
+    kill_group(child)?;
+    child.kill().await?;
+    Ok(WaitOutcome::Cancelled)
+}
+
+/// Extension for [`tokio::process::Command`](::tokio::process::Command) to spawn a restricted
+/// command and wait for it
+// Sealed through `CommandExt`, which can only be implemented by this crate
+pub trait TokioCommandExt: CommandExt {
+    /// Spawn this command restricted by `rules` and wait for it to exit, killing it if `cancel`
+    /// completes first
+    ///
+    /// This binds the lifetime of the command to a cancellation future, e.g.,
+    /// `CancellationToken::cancelled()` of the request which the command serves, so that
+    /// cancelling the request never leaks the command.  As with [`wait_or_cancel`], the entire
+    /// process group is killed if the command is the leader of its own group (e.g., it was
+    /// spawned with [`process_group(0)`](::tokio::process::Command::process_group)).
+    ///
+    /// This sets [`kill_on_drop`](::tokio::process::Command::kill_on_drop) on the command, so that
+    /// it is also killed if the returned future is dropped (e.g., when the task which drives it is
+    /// aborted), and this setting remains on the command for any later spawns.
+    ///
+    /// ```no_run
+    /// # use leucite::{tokio::TokioCommandExt, Rules, WaitOutcome};
+    /// # use tokio::process::Command;
+    /// # async fn f(cancelled: impl std::future::Future + Send) -> std::io::Result<()> {
+    /// let outcome = Command::new("sleep")
+    ///     .arg("60")
+    ///     .spawn_restricted_with_cancel(Rules::new().add_read_only("/usr").into(), cancelled)
+    ///     .await?;
+    /// if outcome == WaitOutcome::Cancelled {
+    ///     // the command was killed
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn spawn_restricted_with_cancel<F>(
+        &mut self,
+        rules: Arc<Rules>,
+        cancel: F,
+    ) -> impl Future<Output = io::Result<WaitOutcome>> + Send
+    where
+        F: Future + Send;
+
+    /// Spawn this command restricted by `rules` and wait for it to exit, killing it if it is still
+    /// running after `timeout` of wall-clock time
+    ///
+    /// Unlike a CPU time limit, this also catches commands which sleep or block (e.g., on a pipe).
+    /// The timer runs in the parent, so it can not be disarmed by the command, unlike
+    /// [`CommandExt::hard_deadline`].  As with [`wait_or_cancel`], the entire process group is
+    /// killed if the command is the leader of its own group, and
+    /// [`kill_on_drop`](::tokio::process::Command::kill_on_drop) is set on the command, see
+    /// [`TokioCommandExt::spawn_restricted_with_cancel`].
+    ///
+    /// ```no_run
+    /// # use leucite::{tokio::TokioCommandExt, Rules, WaitOutcome};
+    /// # use tokio::process::Command;
+    /// # use std::time::Duration;
+    /// # async fn f() -> std::io::Result<()> {
+    /// let outcome = Command::new("sleep")
+    ///     .arg("60")
+    ///     .spawn_restricted_with_timeout(
+    ///         Rules::new().add_read_only("/usr").into(),
+    ///         Duration::from_secs(1),
+    ///     )
+    ///     .await?;
+    /// assert_eq!(outcome, WaitOutcome::TimedOut);
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn spawn_restricted_with_timeout(
+        &mut self,
+        rules: Arc<Rules>,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<WaitOutcome>> + Send;
+}
+
+impl TokioCommandExt for Command {
+    fn spawn_restricted_with_cancel<F>(
+        &mut self,
+        rules: Arc<Rules>,
+        cancel: F,
+    ) -> impl Future<Output = io::Result<WaitOutcome>> + Send
+    where
+        F: Future + Send,
+    {
+        let child = self.kill_on_drop(true).spawn_restricted(rules);
+        async move { wait_or_cancel(&mut child?, cancel).await }
+    }
+
+    fn spawn_restricted_with_timeout(
+        &mut self,
+        rules: Arc<Rules>,
+        timeout: Duration,
+    ) -> impl Future<Output = io::Result<WaitOutcome>> + Send {
+        let outcome = self.spawn_restricted_with_cancel(rules, ::tokio::time::sleep(timeout));
+        async move {
+            match outcome.await? {
+                WaitOutcome::Cancelled => Ok(WaitOutcome::TimedOut),
+                outcome => Ok(outcome),
+            }
+        }
+    }
+}
+
+/// Wait for `child` to exit, killing it if its resident memory exceeds `limit`.
+///
+/// This is the async variant of [`wait_with_rss_limit`](crate::wait_with_rss_limit), see its
+/// documentation for more information.
+pub async fn wait_with_rss_limit(
+    child: &mut Child,
+    limit: MemorySize,
+    interval: Duration,
+) -> io::Result<WaitOutcome> {
+    loop {
+        let Some(pid) = child.id() else {
+            // the child has already been reaped
+            return child.wait().await.map(WaitOutcome::Exited);
+        };
+
+        let rss = match resident_memory(pid) {
+            Ok(rss) => rss,
+            // the child exited between the call to `id` and reading the status
+            Err(e) if e.kind() == io::ErrorKind::NotFound => MemorySize::from_bytes(0),
+            Err(e) => return Err(e),
+        };
+
+        if rss > limit {
+            child.kill().await?;
+            return Ok(WaitOutcome::MemoryLimitExceeded(rss));
+        }
+
+        if let Some(status) = wait_until(child, ::tokio::time::sleep(interval)).await? {
+            return Ok(WaitOutcome::Exited(status));
+        }
+    }
+}
+
+/// Wait for `child` to exit, returning `None` if `other` completes first
+async fn wait_until<F>(child: &mut Child, other: F) -> io::Result<Option<ExitStatus>>
+where
+    F: Future,
+{
+    let mut other = pin!(other);
+    let mut wait = pin!(child.wait());
+    poll_fn(|cx| {
+        if let Poll::Ready(status) = wait.as_mut().poll(cx) {
+            return Poll::Ready(status.map(Some));
+        }
+        other.as_mut().poll(cx).map(|_| Ok(None))
+    })
+    .await
+}
+
+/// Kill the process group of `child` if it is the leader of its own group
+fn kill_group(child: &Child) -> io::Result<()> {
+    let Some(pid) = child.id() else {
+        // The child has already been reaped
+        return Ok(());
+    };
+    let pid = pid as libc::pid_t;
+
+    // SAFETY: These functions have no memory safety requirements, and any error is reported
+    // through `errno`.
+    unsafe {
+        if libc::getpgid(pid) == pid && libc::kill(-pid, libc::SIGKILL) != 0 {
+            return Err(read_errno());
+        }
+    }
+    Ok(())
+}
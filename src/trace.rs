@@ -0,0 +1,44 @@
+//! Events which are emitted using `tracing` with the `tracing` feature
+//!
+//! Nothing is emitted from the child, since a subscriber may allocate or take a lock which was
+//! held by another thread of the parent when it forked.  Instead, the rules are described in the
+//! parent when they are added to a command, and the [`RulesetStatus`](landlock::RulesetStatus) is
+//! only known when the child reports it, i.e., with
+//! [`CommandExt::sandbox_audited`](crate::CommandExt::sandbox_audited).
+use landlock::AccessNet;
+
+use crate::{Enforcement, PermissivePolicy, Rules};
+
+/// Describe `rules` when they are added to a command, before forking
+pub(crate) fn restrict(rules: &Rules) {
+    // The number of ports which may be used, which is not recorded if any port may be used
+    let ports = |access| rules.ports(access).listed().map(<[u16]>::len);
+    tracing::debug!(
+        requested_abi = rules.abi() as i32,
+        effective_abi = rules.effective_abi() as i32,
+        paths = rules.path_grants().count(),
+        bind_ports = ports(AccessNet::BindTcp),
+        connect_ports = ports(AccessNet::ConnectTcp),
+        audit_only = rules.is_audit_only(),
+        "restricting command",
+    );
+    // The child skips restricting silently, so this is the only trace of it
+    if matches!(rules.permissive_policy, PermissivePolicy::Skip)
+        && !rules.is_audit_only()
+        && rules.is_fully_permissive()
+    {
+        tracing::warn!("not restricting command, since the rules grant full access");
+    }
+}
+
+/// Report how the rules of the command with the process ID `pid` were enforced
+pub(crate) fn enforced(pid: u32, enforcement: Option<Enforcement>) {
+    match enforcement {
+        Some(Enforcement::FullyEnforced) | None => {
+            tracing::info!(pid, ?enforcement, "sandboxed command")
+        }
+        Some(Enforcement::PartiallyEnforced | Enforcement::NotEnforced) => {
+            tracing::warn!(pid, ?enforcement, "sandboxed command is not fully enforced")
+        }
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    env, fmt, fs,
+    io::{BufRead, BufReader, Read},
+    os::unix::{ffi::OsStrExt, fs::PermissionsExt},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use landlock::{AccessFs, BitFlags, PathFd};
+
+use crate::{elf, Rules};
+
+/// The accesses which a command needs in order to start, but which are not granted by [`Rules`],
+/// see [`Rules::validate_for_command`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MissingAccess {
+    /// The program of the command, resolved against `PATH` if it could be found
+    pub program: PathBuf,
+    /// Each path which is missing some access, with the rights that are missing
+    pub missing: Vec<(PathBuf, BitFlags<AccessFs>)>,
+}
+
+impl fmt::Display for MissingAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} can not start, as the rules do not grant",
+            self.program.display()
+        )?;
+        for (i, (path, access)) in self.missing.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            write!(f, "{sep} {access:?} on {}", path.display())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingAccess {}
+
+impl Rules {
+    /// Check that each of the paths in these rules exists and can be opened, returning the paths
+    /// which can't
+    ///
+    /// When restricting, paths which can't be opened are silently skipped, so a typo such as
+    /// `add_read_only("/tpy")` produces a policy which doesn't grant what was intended.  Call this
+    /// when building the rules to fail fast instead, or use [`Rules::restrict_strict`].
+    ///
+    /// ```
+    /// # use leucite::Rules;
+    /// let rules = Rules::new().add_read_only("/usr").add_read_only("/tpy");
+    /// assert_eq!(rules.validate(), Err(vec!["/tpy".into()]));
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<PathBuf>> {
+        let mut invalid = Vec::new();
+        for (path, _) in self.path_grants() {
+            if PathFd::new(path).is_err() && !invalid.iter().any(|p| p == path) {
+                invalid.push(path.to_path_buf());
+            }
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid)
+        }
+    }
+
+    /// Check that these rules grant the access that `cmd` needs in order to start
+    ///
+    /// This resolves the program of `cmd` against `PATH` (using the `PATH` of `cmd` if it has been
+    /// set, like [`Command::spawn`]), and checks that the rules grant:
+    ///
+    /// - `Execute` on the program, and on the interpreter of a script (from its `#!` line)
+    /// - `Execute` on the dynamic linker of an ELF program
+    /// - `ReadFile` on each shared library which the program depends on, see
+    ///   [`Rules::allow_shared_libs_of`]
+    ///
+    /// Paths are canonicalised before they are compared, since landlock follows symlinks.  This
+    /// catches the most common reason for a command failing with "permission denied" before it
+    /// even runs, but the command may of course still need access to other files once it is
+    /// running.  A program which can't be found is reported as missing `Execute` access.
+    pub fn validate_for_command(&self, cmd: &Command) -> Result<(), MissingAccess> {
+        let grants: Vec<_> = self
+            .path_grants()
+            .map(|(path, access)| (fs::canonicalize(path).unwrap_or(path.into()), access))
+            .collect();
+        let granted = |path: &Path| {
+            (grants.iter())
+                .filter(|(p, _)| path.starts_with(p))
+                .fold(BitFlags::empty(), |acc, (_, access)| acc | *access)
+        };
+
+        let Some(program) = resolve_program(cmd) else {
+            let program = PathBuf::from(cmd.get_program());
+            return Err(MissingAccess {
+                missing: vec![(program.clone(), AccessFs::Execute.into())],
+                program,
+            });
+        };
+
+        let mut missing = Vec::new();
+        for (path, access) in required_access(&program) {
+            let path = fs::canonicalize(&path).unwrap_or(path);
+            let lacking = access & !granted(&path);
+            if !lacking.is_empty() && !missing.iter().any(|(p, _)| *p == path) {
+                missing.push((path, lacking));
+            }
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(MissingAccess { program, missing })
+        }
+    }
+}
+
+/// Find the file which is executed for the program of `cmd`
+fn resolve_program(cmd: &Command) -> Option<PathBuf> {
+    let program = Path::new(cmd.get_program());
+    if program.as_os_str().as_bytes().contains(&b'/') {
+        let program = match cmd.get_current_dir() {
+            Some(dir) => dir.join(program),
+            None => program.to_path_buf(),
+        };
+        return program.is_file().then_some(program);
+    }
+
+    let path = match cmd.get_envs().find(|(k, _)| *k == "PATH") {
+        Some((_, path)) => path?.to_os_string(),
+        None => env::var_os("PATH")?,
+    };
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|p| {
+            p.metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        })
+}
+
+/// The number of interpreters which the kernel follows before failing with `ELOOP`
+const MAX_INTERPRETERS: usize = 4;
+
+/// Get each path which must be accessible to execute `program`, with the access that is needed
+fn required_access(program: &Path) -> Vec<(PathBuf, BitFlags<AccessFs>)> {
+    let mut required = Vec::new();
+    let mut program = program.to_path_buf();
+
+    // A script may name itself, or another script, as its interpreter
+    for _ in 0..=MAX_INTERPRETERS {
+        required.push((program.clone(), AccessFs::Execute.into()));
+        if let Some(interpreter) = shebang(&program) {
+            program = interpreter;
+            continue;
+        }
+
+        if let Ok(libs) = elf::shared_libs_of(&program) {
+            // The dynamic linker is executed by the kernel, while the libraries are opened by it.
+            // If a library can't be found, the command fails no matter what the rules grant.
+            let interpreter = elf::interpreter_of(&program).ok().flatten();
+            required.extend(libs.into_iter().map(|lib| {
+                let access = if Some(&lib) == interpreter.as_ref() {
+                    AccessFs::Execute
+                } else {
+                    AccessFs::ReadFile
+                };
+                (lib, access.into())
+            }));
+        }
+        break;
+    }
+    required
+}
+
+/// Get the interpreter from the `#!` line of a script
+fn shebang(program: &Path) -> Option<PathBuf> {
+    // The kernel only reads the first 256 bytes of the line
+    let mut line = Vec::new();
+    BufReader::new(fs::File::open(program).ok()?.take(256))
+        .read_until(b'\n', &mut line)
+        .ok()?;
+    let line = std::str::from_utf8(line.strip_prefix(b"#!")?).ok()?;
+    line.split_whitespace().next().map(PathBuf::from)
+}
@@ -0,0 +1,92 @@
+use std::{
+    fs, io,
+    process::{Child, ExitStatus},
+    thread,
+    time::Duration,
+};
+
+use crate::MemorySize;
+
+/// The way in which a sandboxed command finished running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WaitOutcome {
+    /// The command exited on its own with the given status
+    Exited(ExitStatus),
+    /// The command was killed because the operation which was driving it was cancelled
+    Cancelled,
+    /// The command was killed because its resident memory exceeded the limit.  Contains the
+    /// resident memory which was observed.
+    MemoryLimitExceeded(MemorySize),
+    /// The command was killed because it was still running when its wall-clock timeout expired
+    TimedOut,
+}
+
+impl WaitOutcome {
+    /// Get the exit status of the command if it exited on its own
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        match self {
+            Self::Exited(status) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// Read the resident memory (`VmRSS`) of the process with the given `pid` from
+/// `/proc/<pid>/status`
+///
+/// Processes which have no address space (e.g., zombies) use no resident memory.
+pub fn resident_memory(pid: u32) -> io::Result<MemorySize> {
+    let status = fs::read_to_string(format!("/proc/{pid}/status"))?;
+    let Some(line) = status.lines().find_map(|l| l.strip_prefix("VmRSS:")) else {
+        return Ok(MemorySize::from_bytes(0));
+    };
+
+    // The kernel reports this value as "<n> kB", where a kB is 1024 bytes
+    let kib = line
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .map_err(io::Error::other)?;
+    Ok(MemorySize::from_kib(kib))
+}
+
+/// Wait for `child` to exit, killing it if its resident memory exceeds `limit`.
+///
+/// The resident memory of the child is polled every `interval` from `/proc/<pid>/status`.  This
+/// catches memory which is mapped using `mmap`, which is not covered by
+/// [`CommandExt::max_memory`](crate::CommandExt::max_memory), and does not require cgroups.
+///
+/// Since the memory is only checked periodically, a child which allocates quickly can overshoot
+/// `limit` between polls.  A shorter `interval` reduces the overshoot at the cost of more work in
+/// the parent.  Only the memory of `child` itself is counted, not that of its descendants.
+///
+/// This blocks the current thread until the child has exited.  For the async variant, see
+/// [`tokio::wait_with_rss_limit`](crate::tokio::wait_with_rss_limit).
+pub fn wait_with_rss_limit(
+    child: &mut Child,
+    limit: MemorySize,
+    interval: Duration,
+) -> io::Result<WaitOutcome> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(WaitOutcome::Exited(status));
+        }
+
+        let rss = match resident_memory(child.id()) {
+            Ok(rss) => rss,
+            // the child exited between the call to `try_wait` and reading the status
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+
+        if rss > limit {
+            child.kill()?;
+            child.wait()?;
+            return Ok(WaitOutcome::MemoryLimitExceeded(rss));
+        }
+
+        thread::sleep(interval);
+    }
+}
@@ -0,0 +1,65 @@
+use std::{
+    process::Command as StdCommand,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use leucite::{
+    audit::{AuditListener, DeniedAccess},
+    CommandExt, Rules,
+};
+use tempdir::TempDir;
+
+#[test]
+#[ignore = "requires landlock ABI 7 or later, and an enabled audit log which the test may read"]
+fn denials_of() -> Result<(), Box<dyn std::error::Error>> {
+    let mut listener = AuditListener::new()?;
+    // SAFETY: This queries the landlock ABI version without creating a ruleset
+    let abi = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<libc::c_void>(),
+            0usize,
+            1u32,
+        )
+    };
+    assert!(abi >= 7, "denials can't be logged with landlock ABI {abi}");
+
+    let tempdir = TempDir::new("leucite")?;
+    let denied = tempdir.path().join("denied");
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_only("/dev")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+        .log_denials();
+
+    let mut child = StdCommand::new("sh")
+        .arg("-c")
+        .arg(format!("echo hi > {}", denied.display()))
+        .restrict(Arc::new(rules))
+        .spawn()?;
+    assert!(!child.wait()?.success());
+
+    // the records are delivered by the kernel asynchronously, so wait until they arrive
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let found = |denials: &[DeniedAccess]| {
+        denials.iter().any(|d| {
+            d.path.as_deref() == Some(tempdir.path())
+                && d.requested_rights.iter().any(|r| r == "fs.make_reg")
+        })
+    };
+    let mut denials = listener.denials_of(child.id())?;
+    while !found(&denials) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+        denials = listener.denials_of(child.id())?;
+    }
+    assert!(found(&denials), "{denials:?}");
+
+    // the records of a command are not mixed up with other commands
+    assert!(listener.denials_of(std::process::id())?.is_empty());
+
+    Ok(())
+}
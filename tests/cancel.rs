@@ -0,0 +1,94 @@
+use std::{process::Stdio, sync::Arc, time::Duration};
+
+use leucite::{tokio::TokioCommandExt, Rules, WaitOutcome};
+use tokio::{io::AsyncBufReadExt, io::BufReader, process::Command as TokioCommand};
+
+#[tokio::test]
+async fn cancel_kills_child() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = TokioCommand::new("sleep").arg("30").spawn()?;
+
+    let outcome =
+        leucite::tokio::wait_or_cancel(&mut child, tokio::time::sleep(Duration::from_millis(100)))
+            .await?;
+
+    assert_eq!(outcome, WaitOutcome::Cancelled);
+    assert!(child.try_wait()?.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_after_exit() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = TokioCommand::new("true").spawn()?;
+
+    let outcome =
+        leucite::tokio::wait_or_cancel(&mut child, tokio::time::sleep(Duration::from_secs(30)))
+            .await?;
+
+    assert!(matches!(outcome, WaitOutcome::Exited(status) if status.success()));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_kills_process_group() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = TokioCommand::new("sh")
+        .arg("-c")
+        .arg("sleep 30 & echo $!; wait")
+        .stdout(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).await?;
+    let grandchild: u32 = line.trim().parse()?;
+
+    let outcome =
+        leucite::tokio::wait_or_cancel(&mut child, tokio::time::sleep(Duration::from_millis(100)))
+            .await?;
+    assert_eq!(outcome, WaitOutcome::Cancelled);
+
+    // give the kernel a moment to deliver the signal to the rest of the group
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let stat = std::fs::read_to_string(format!("/proc/{grandchild}/stat")).unwrap_or_default();
+    // The grandchild is either gone, or a zombie waiting to be reaped
+    assert!(stat.is_empty() || stat.contains(") Z "), "{stat}");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spawn_restricted_with_cancel() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Arc::new(Rules::new().add_read_execute("/"));
+    let (cancel, cancelled) = tokio::sync::oneshot::channel::<()>();
+
+    let mut cmd = TokioCommand::new("sleep");
+    cmd.arg("30");
+    let wait =
+        tokio::spawn(async move { cmd.spawn_restricted_with_cancel(rules, cancelled).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    cancel.send(()).unwrap();
+
+    assert_eq!(wait.await??, WaitOutcome::Cancelled);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spawn_restricted_with_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Arc::new(Rules::new().add_read_execute("/"));
+
+    let outcome = TokioCommand::new("sleep")
+        .arg("30")
+        .spawn_restricted_with_timeout(rules.clone(), Duration::from_millis(100))
+        .await?;
+    assert_eq!(outcome, WaitOutcome::TimedOut);
+
+    let outcome = TokioCommand::new("true")
+        .spawn_restricted_with_timeout(rules, Duration::from_secs(30))
+        .await?;
+    assert!(matches!(outcome, WaitOutcome::Exited(status) if status.success()));
+
+    Ok(())
+}
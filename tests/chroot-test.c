@@ -0,0 +1,23 @@
+#include <stdio.h>
+#include <unistd.h>
+
+// Print the current directory and the contents of `data.txt` within it
+int main(void) {
+    char cwd[256];
+    if (!getcwd(cwd, sizeof(cwd))) {
+        perror("getcwd");
+        return 1;
+    }
+    printf("%s\n", cwd);
+
+    FILE *f = fopen("data.txt", "r");
+    if (!f) {
+        perror("fopen");
+        return 1;
+    }
+    char buf[256];
+    size_t n = fread(buf, 1, sizeof(buf), f);
+    fwrite(buf, 1, n, stdout);
+    fclose(f);
+    return 0;
+}
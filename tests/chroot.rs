@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    process::{Command as StdCommand, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+
+use leucite::{CommandExt, Rules, SandboxProfile};
+use tempdir::TempDir;
+
+#[test]
+fn chroot_with_current_dir_and_restrict() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let root = tempdir.path().join("root");
+    fs::create_dir_all(root.join("work"))?;
+    fs::write(root.join("work/data.txt"), "contents\n")?;
+    fs::write(
+        tempdir.path().join("test.c"),
+        include_str!("./chroot-test.c"),
+    )?;
+
+    // the program is linked statically, so the root does not need any libraries
+    let status = StdCommand::new("gcc")
+        .args(["-static", "-o"])
+        .arg(root.join("test"))
+        .arg(tempdir.path().join("test.c"))
+        .status()?;
+    assert!(status.success());
+
+    // these paths are resolved within the new root, since the root is changed first
+    let rules = Rules::new().add_read_only("/test").add_read_only("/work");
+
+    let res = StdCommand::new("/test")
+        // this is applied before the root is changed, so it refers to the host
+        .current_dir(tempdir.path())
+        .chroot(&root, "/work")
+        .restrict(Arc::new(rules))
+        .stdout(Stdio::piped())
+        .output();
+
+    let out = match res {
+        Ok(out) => out,
+        // changing the root requires privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "/work\ncontents\n");
+
+    Ok(())
+}
+
+#[test]
+fn chroot_to_hides_host() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let root = tempdir.path().join("root");
+    fs::create_dir(&root)?;
+    fs::write(tempdir.path().join("test.c"), include_str!("./stat-test.c"))?;
+
+    let status = StdCommand::new("gcc")
+        .args(["-static", "-o"])
+        .arg(root.join("test"))
+        .arg(tempdir.path().join("test.c"))
+        .status()?;
+    assert!(status.success());
+
+    let res = StdCommand::new("/test")
+        .args(["/test", "test", "/etc/passwd", "../../etc/passwd"])
+        .chroot_to(&root)
+        .restrict(Arc::new(Rules::new().add_read_only("/")))
+        .stdout(Stdio::piped())
+        .output();
+
+    let out = match res {
+        Ok(out) => out,
+        // changing the root requires privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "/test: found\ntest: found\n/etc/passwd: missing\n../../etc/passwd: missing\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn chroot_invalid_cwd() {
+    let tempdir = TempDir::new("leucite").unwrap();
+
+    let res = StdCommand::new("/test")
+        .chroot(tempdir.path(), "/missing")
+        .spawn();
+
+    assert!(res.is_err());
+}
+
+#[test]
+fn chroot_with_max_cpu_bandwidth() {
+    let tempdir = TempDir::new("leucite").unwrap();
+
+    let profile = SandboxProfile::new()
+        .chroot_to(tempdir.path())
+        .max_cpu_bandwidth(Duration::from_millis(50), Duration::from_millis(100));
+    let err = profile
+        .wrap(&mut StdCommand::new("/test"))
+        .spawn()
+        .unwrap_err();
+    // changing the root requires privileges that the test may not have
+    if err.kind() == std::io::ErrorKind::PermissionDenied {
+        return;
+    }
+
+    // the cgroup of the command is still found after the root was changed, so the limit is only
+    // refused because the command shares the cgroup of the test
+    assert!(
+        matches!(err.raw_os_error(), Some(libc::EBUSY | libc::EOPNOTSUPP)),
+        "{err:?}"
+    );
+}
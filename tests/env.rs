@@ -0,0 +1,95 @@
+use std::{path::PathBuf, process::Command as StdCommand};
+
+use leucite::{CommandExt, Rules, RulesFromEnvError};
+
+const VARS: [&str; 6] = [
+    "LEUCITE_READ_ONLY",
+    "LEUCITE_READ_EXECUTE",
+    "LEUCITE_READ_WRITE",
+    "LEUCITE_WRITE_ONLY",
+    "LEUCITE_BIND_PORTS",
+    "LEUCITE_CONNECT_PORTS",
+];
+
+fn set_vars(vars: &[(&str, &str)]) {
+    for var in VARS {
+        std::env::remove_var(var);
+    }
+    for (var, value) in vars {
+        std::env::set_var(var, value);
+    }
+}
+
+// The environment is shared by the whole process, so everything is tested in a single test
+#[test]
+fn from_env() -> Result<(), Box<dyn std::error::Error>> {
+    set_vars(&[]);
+    assert_eq!(Rules::from_env()?, Rules::new());
+
+    set_vars(&[
+        ("LEUCITE_READ_ONLY", "/usr:/etc::"),
+        ("LEUCITE_READ_EXECUTE", "/bin"),
+        ("LEUCITE_READ_WRITE", "/tmp"),
+        ("LEUCITE_WRITE_ONLY", "/var/log"),
+        ("LEUCITE_BIND_PORTS", "none"),
+        ("LEUCITE_CONNECT_PORTS", " 80, 443 "),
+    ]);
+    let rules = Rules::from_env()?;
+    // `none` and no ports at all only differ structurally
+    assert_eq!(
+        rules.clone().normalize(),
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_only("/etc")
+            .add_read_execute("/bin")
+            .add_read_write("/tmp")
+            .add_write_only("/var/log")
+            .add_connect_port(80)
+            .add_connect_port(443)
+            .normalize()
+    );
+    assert_eq!(
+        rules.read_only_paths(),
+        [PathBuf::from("/usr"), "/etc".into()]
+    );
+    assert_eq!(rules.bind_ports(), Some(&[][..]));
+
+    set_vars(&[("LEUCITE_BIND_PORTS", "any")]);
+    assert_eq!(Rules::from_env()?, Rules::new().allow_all_bind());
+
+    set_vars(&[("LEUCITE_CONNECT_PORTS", "80,http")]);
+    assert_eq!(
+        Rules::from_env(),
+        Err(RulesFromEnvError::InvalidPort {
+            var: "LEUCITE_CONNECT_PORTS",
+            value: "http".into()
+        })
+    );
+    set_vars(&[("LEUCITE_BIND_PORTS", "65536")]);
+    assert!(matches!(
+        Rules::from_env(),
+        Err(RulesFromEnvError::InvalidPort { .. })
+    ));
+    set_vars(&[("LEUCITE_BIND_PORTS", " ")]);
+    assert_eq!(
+        Rules::from_env(),
+        Err(RulesFromEnvError::NoPorts {
+            var: "LEUCITE_BIND_PORTS"
+        })
+    );
+
+    set_vars(&[("LEUCITE_READ_EXECUTE", "/usr:/lib:/lib64")]);
+    let status = StdCommand::new("/usr/bin/true")
+        .inherit_rules_from_env()?
+        .status()?;
+    assert!(status.success());
+    let status = StdCommand::new("/usr/bin/cat")
+        .arg("/etc/hostname")
+        .stderr(std::process::Stdio::null())
+        .inherit_rules_from_env()?
+        .status()?;
+    assert!(!status.success());
+
+    set_vars(&[]);
+    Ok(())
+}
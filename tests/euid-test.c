@@ -0,0 +1,8 @@
+#include <stdio.h>
+#include <unistd.h>
+
+// Print the effective user ID, which is changed by executing this program if it is setuid
+int main(void) {
+    printf("%d\n", (int)geteuid());
+    return 0;
+}
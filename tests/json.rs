@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use leucite::{FsType, LimitValue, MemorySize, ResourceLimits, Rules, SandboxProfile};
+use serde_json::{json, Value};
+
+#[test]
+fn empty_profile() -> Result<(), serde_json::Error> {
+    let json: Value = serde_json::from_str(&SandboxProfile::new().to_json())?;
+
+    assert_eq!(
+        json,
+        json!({
+            "schema": "leucite-sandbox-profile",
+            "version": SandboxProfile::JSON_SCHEMA_VERSION,
+            "rules": null,
+            "limits": {
+                "max_memory_bytes": null,
+                "max_memory_hard_bytes": null,
+                "max_address_space_bytes": null,
+                "max_file_size_bytes": null,
+                "max_file_size_hard_bytes": null,
+                "max_threads": null,
+                "max_cpu_time_s": null,
+                "max_core_size_bytes": null,
+                "max_stack_size_bytes": null,
+                "max_open_files": null,
+                "max_processes": null,
+                "max_pending_signals": null,
+                "max_message_queue_bytes": null,
+                "max_locked_memory_bytes": null,
+                "min_nice": null,
+                "hard_deadline_us": null,
+                "max_cpu_bandwidth": null,
+            },
+            "process": {
+                "oom_score_adj": null,
+                "nice": null,
+                "idle_priority": false,
+                "detach_controlling_tty": false,
+                "private_ipc": false,
+                "chroot": null,
+                "tmpfs": [],
+                "uid": null,
+                "gid": null,
+                "supplementary_groups": null,
+            },
+            "environment": { "clear": false, "set": [] },
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn full_profile() -> Result<(), serde_json::Error> {
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_write("/tmp/\"quoted\"\n")
+        .add_connect_port(443)
+        .add_connect_port(80)
+        .deny_filesystem_types(&[FsType::NFS])
+        .scope_signals()
+        .with_abi(landlock::ABI::V4);
+    let profile = SandboxProfile::new()
+        .restrict(rules.into())
+        .resource_limits(
+            ResourceLimits::new()
+                .max_memory(MemorySize::from_mb(100))
+                .max_file_size(LimitValue::Unlimited)
+                .min_nice(0),
+        )
+        .max_threads(8)
+        .max_cpu_time(10)
+        .hard_deadline(Duration::from_millis(1500))
+        .max_cpu_bandwidth(Duration::from_millis(50), Duration::from_millis(100))
+        .oom_score_adj(500)
+        .nice(5)
+        .idle_priority()
+        .private_ipc()
+        .add_tmpfs("/tmp", MemorySize::from_mb(10))
+        .chroot_to("/srv/jail")
+        .set_uid(65534)
+        .set_gid(65534)
+        .supplementary_groups(&[])
+        .env_clear()
+        .env("PATH", "/usr/bin")
+        .env("SECRET", "hunter2");
+
+    let out = profile.to_json();
+    assert!(!out.contains("hunter2"), "{out}");
+    let json: Value = serde_json::from_str(&out)?;
+
+    assert_eq!(
+        json["rules"],
+        json!({
+            "enforced": true,
+            "filesystem": {
+                "grants": [
+                    { "path": "/usr", "access": ["execute", "read_file", "read_dir"] },
+                    {
+                        "path": "/tmp/\"quoted\"\n",
+                        "access": [
+                            "execute", "write_file", "read_file", "read_dir", "remove_dir",
+                            "remove_file", "make_char", "make_dir", "make_reg", "make_sock",
+                            "make_fifo", "make_block", "make_sym", "refer", "truncate",
+                        ],
+                    },
+                ],
+                "denied_filesystems": [0x6969],
+            },
+            "network": { "bind": [], "connect": [80, 443] },
+            "scopes": { "abstract_unix_sockets": false, "signals": true },
+            "abi": 4,
+        })
+    );
+    assert_eq!(json["limits"]["max_memory_bytes"], 100_000_000);
+    assert_eq!(json["limits"]["max_threads"], 8);
+    assert_eq!(json["limits"]["max_cpu_time_s"], 10);
+    assert_eq!(json["limits"]["max_file_size_bytes"], "unlimited");
+    assert_eq!(json["limits"]["min_nice"], 0);
+    assert_eq!(json["limits"]["hard_deadline_us"], 1_500_000);
+    assert_eq!(
+        json["limits"]["max_cpu_bandwidth"],
+        json!({ "quota_us": 50_000, "period_us": 100_000 })
+    );
+    assert_eq!(json["process"]["oom_score_adj"], 500);
+    assert_eq!(json["process"]["nice"], 5);
+    assert_eq!(json["process"]["idle_priority"], true);
+    assert_eq!(json["process"]["private_ipc"], true);
+    assert_eq!(
+        json["process"]["chroot"],
+        json!({ "root": "/srv/jail", "cwd": "/" })
+    );
+    assert_eq!(
+        json["process"]["tmpfs"],
+        json!([{ "path": "/tmp", "size_bytes": 10_000_000 }])
+    );
+    assert_eq!(json["process"]["uid"], 65534);
+    assert_eq!(json["process"]["gid"], 65534);
+    assert_eq!(json["process"]["supplementary_groups"], json!([]));
+    assert_eq!(
+        json["environment"],
+        json!({ "clear": true, "set": ["PATH", "SECRET"] })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn unrestricted_network_and_audit_only() -> Result<(), serde_json::Error> {
+    let profile = SandboxProfile::new().restrict(Rules::new().client_only().audit_only().into());
+    let json: Value = serde_json::from_str(&profile.to_json())?;
+
+    assert_eq!(json["rules"]["enforced"], false);
+    assert_eq!(
+        json["rules"]["scopes"],
+        json!({ "abstract_unix_sockets": false, "signals": false })
+    );
+    assert_eq!(json["rules"]["abi"], Rules::new().abi() as i32);
+    assert_eq!(
+        json["rules"]["network"],
+        json!({ "bind": [], "connect": null })
+    );
+
+    Ok(())
+}
@@ -0,0 +1,688 @@
+use std::{path::Path, process::Command as StdCommand, sync::Arc};
+
+use landlock::{Access, AccessFs};
+use leucite::{ChildError, CommandExt, FsType, PermissivePolicy, RestrictErrorKind, Rules};
+use tempdir::TempDir;
+
+/// Rules which allow running simple commands
+fn base_rules() -> Rules {
+    Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_only("/dev")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+}
+
+/// Run a shell `script` in `dir` using `rules`, returning whether it succeeded
+fn run_sh(dir: &Path, rules: Rules, script: &str) -> std::io::Result<bool> {
+    let status = StdCommand::new("sh")
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir)
+        .env_clear()
+        .restrict(Arc::new(rules))
+        .status()?;
+    Ok(status.success())
+}
+
+#[test]
+fn read_only_with_writable() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::create_dir(tempdir.path().join("out"))?;
+    std::fs::create_dir(tempdir.path().join("src"))?;
+    std::fs::write(tempdir.path().join("src/main.c"), "int main;")?;
+
+    let rules = || base_rules().add_read_only_with_writable(tempdir.path(), &["out".into()]);
+
+    assert!(run_sh(tempdir.path(), rules()?, "cat src/main.c")?);
+    assert!(run_sh(tempdir.path(), rules()?, "echo hi > out/file")?);
+    assert!(!run_sh(tempdir.path(), rules()?, "echo hi > file")?);
+    assert!(!run_sh(tempdir.path(), rules()?, "echo hi > src/main.c")?);
+
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("out/file"))?,
+        "hi\n"
+    );
+    assert!(!tempdir.path().join("file").exists());
+
+    // writable paths which would escape the root are rejected
+    for escaping in ["/etc", "../sibling", "out/../../sibling"] {
+        let err = base_rules()
+            .add_read_only_with_writable(tempdir.path(), &["out".into(), escaping.into()])
+            .unwrap_err();
+        assert_eq!(err.kind(), RestrictErrorKind::NotBeneathRoot, "{escaping}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn spawn_restricted() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let file = tempdir.path().join("file");
+    std::fs::write(&file, "")?;
+
+    let status = StdCommand::new("cat")
+        .arg(&file)
+        .stderr(std::process::Stdio::null())
+        .spawn_restricted(Arc::new(base_rules()))?
+        .wait()?;
+    assert!(!status.success());
+
+    let status = StdCommand::new("cat")
+        .arg(&file)
+        .spawn_restricted_if(None)?
+        .wait()?;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spawn_restricted_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let file = tempdir.path().join("file");
+    std::fs::write(&file, "")?;
+
+    let status = tokio::process::Command::new("cat")
+        .arg(&file)
+        .stderr(std::process::Stdio::null())
+        .spawn_restricted_if(Some(Arc::new(base_rules())))?
+        .wait()
+        .await?;
+    assert!(!status.success());
+
+    Ok(())
+}
+
+#[test]
+fn spawn_restricted_child_error() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let fs_type = FsType::of(tempdir.path())?;
+
+    let rules = Rules::new()
+        .add_read_write(tempdir.path())
+        .deny_filesystem_types(&[fs_type]);
+    let err = StdCommand::new("/usr/bin/true")
+        .spawn_restricted(Arc::new(rules))
+        .unwrap_err();
+    let child = ChildError::of(&err).expect("the child reports its error");
+    assert_eq!(child.kind(), RestrictErrorKind::DeniedFilesystem);
+    assert_eq!(child.path(), Some(tempdir.path()));
+    assert_eq!(child.fs_type(), Some(fs_type));
+    assert_eq!(child.port(), None);
+
+    let rules = Rules::new()
+        .add_read_write("/")
+        .allow_all_network()
+        .on_fully_permissive(PermissivePolicy::Error);
+    let err = StdCommand::new("/usr/bin/true")
+        .spawn_restricted(Arc::new(rules))
+        .unwrap_err();
+    let child = ChildError::of(&err).expect("the child reports its error");
+    assert_eq!(child.kind(), RestrictErrorKind::FullyPermissive);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spawn_restricted_child_error_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let rules = Rules::new()
+        .add_read_write(tempdir.path())
+        .deny_filesystem_types(&[FsType::of(tempdir.path())?]);
+
+    let err = tokio::process::Command::new("/usr/bin/true")
+        .spawn_restricted(Arc::new(rules))
+        .unwrap_err();
+    let child = ChildError::of(&err).expect("the child reports its error");
+    assert_eq!(child.kind(), RestrictErrorKind::DeniedFilesystem);
+    assert_eq!(child.path(), Some(tempdir.path()));
+
+    Ok(())
+}
+
+#[test]
+fn restrict_with_workdir() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let workdir = tempdir.path().join("job");
+    std::fs::create_dir(&workdir)?;
+
+    let status = StdCommand::new("/usr/bin/touch")
+        .arg("output")
+        .restrict_with_workdir(Arc::new(base_rules()), &workdir)
+        .status()?;
+    assert!(status.success());
+    assert!(workdir.join("output").exists());
+
+    // The rest of the tree is still denied
+    let status = StdCommand::new("/usr/bin/touch")
+        .arg("../output")
+        .stderr(std::process::Stdio::null())
+        .restrict_with_workdir(Arc::new(base_rules()), &workdir)
+        .status()?;
+    assert!(!status.success());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn restrict_with_workdir_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+
+    let status = tokio::process::Command::new("/usr/bin/touch")
+        .arg("output")
+        .restrict_with_workdir(Arc::new(base_rules()), tempdir.path())
+        .status()
+        .await?;
+    assert!(status.success());
+    assert!(tempdir.path().join("output").exists());
+
+    Ok(())
+}
+
+#[test]
+fn system_readonly() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let rules = || Rules::system_readonly().add_read_write(tempdir.path().join("out"));
+    std::fs::create_dir(tempdir.path().join("out"))?;
+
+    assert!(run_sh(
+        tempdir.path(),
+        rules(),
+        // `/proc/self` is only that of the command itself, not of the processes that it spawns
+        "cat /etc/passwd > out/file && exec cat /proc/self/status > out/status"
+    )?);
+    assert!(!run_sh(tempdir.path(), rules(), "echo hi > file")?);
+    assert!(!run_sh(tempdir.path(), rules(), "echo hi > /etc/leucite")?);
+
+    Ok(())
+}
+
+#[test]
+fn with_dynamic_linker() -> Result<(), Box<dyn std::error::Error>> {
+    let run = |rules: Rules| {
+        StdCommand::new("/usr/bin/true")
+            .stderr(std::process::Stdio::null())
+            .restrict(Arc::new(rules.add_read_only("/usr/bin/true")))
+            .status()
+    };
+
+    assert!(run(Rules::new().with_dynamic_linker())?.success());
+    // the libraries of the binary can not be loaded without the dynamic linker paths
+    assert!(!matches!(run(Rules::new()), Ok(status) if status.success()));
+
+    Ok(())
+}
+
+#[test]
+fn restrict_all_stacks() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::create_dir(tempdir.path().join("task"))?;
+
+    let run = |script: &str| {
+        let platform = Arc::new(base_rules().add_read_write(tempdir.path()));
+        let task = Arc::new(
+            base_rules()
+                .add_read_only("/proc")
+                .add_read_only(tempdir.path())
+                .add_read_write(tempdir.path().join("task")),
+        );
+        StdCommand::new("sh")
+            .arg("-c")
+            .arg(script)
+            .current_dir(tempdir.path())
+            .stderr(std::process::Stdio::null())
+            .restrict_all([platform, task])
+            .status()
+    };
+
+    // only accesses which are allowed by both rules are allowed
+    assert!(run("echo hi > task/file")?.success());
+    assert!(run("cat task/file")?.success());
+    assert!(!run("echo hi > file")?.success());
+    assert!(!run("cat /proc/self/status")?.success());
+
+    Ok(())
+}
+
+#[test]
+fn add_path_access_no_truncate() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("log"), "first\n")?;
+
+    let rules = || {
+        base_rules().add_path_access(
+            tempdir.path(),
+            AccessFs::from_all(landlock::ABI::V4) & !AccessFs::Truncate,
+        )
+    };
+
+    assert!(run_sh(tempdir.path(), rules(), "echo second >> log")?);
+    assert!(!run_sh(tempdir.path(), rules(), "echo third > log")?);
+    assert!(!run_sh(tempdir.path(), rules(), "truncate -s 0 log")?);
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("log"))?,
+        "first\nsecond\n"
+    );
+
+    // Without the `Truncate` right being handled, it can't be denied
+    let v2 = || rules().with_abi(landlock::ABI::V2);
+    assert!(run_sh(tempdir.path(), v2(), "truncate -s 0 log")?);
+
+    Ok(())
+}
+
+#[test]
+fn add_read_write_file() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("scratch"), "")?;
+    std::fs::write(tempdir.path().join("secret"), "hunter2")?;
+    std::fs::write(tempdir.path().join("input"), "data")?;
+
+    let rules = || {
+        base_rules()
+            .add_read_write_file(tempdir.path().join("scratch"))
+            .add_read_only_file(tempdir.path().join("input"))
+    };
+
+    assert!(run_sh(tempdir.path(), rules(), "echo hi > scratch")?);
+    assert!(run_sh(tempdir.path(), rules(), "cat scratch input")?);
+    assert!(!run_sh(tempdir.path(), rules(), "cat secret")?);
+    assert!(!run_sh(tempdir.path(), rules(), "echo hi > input")?);
+    assert!(!run_sh(tempdir.path(), rules(), "echo hi > new")?);
+    assert!(!run_sh(tempdir.path(), rules(), "ls")?);
+
+    assert_eq!(
+        std::fs::read_to_string(tempdir.path().join("scratch"))?,
+        "hi\n"
+    );
+    assert!(!tempdir.path().join("new").exists());
+
+    Ok(())
+}
+
+#[test]
+fn scope_abstract_unix_sockets() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixListener};
+
+    if !matches!(leucite::landlock_abi(), Some(abi) if abi as i32 >= 6) {
+        eprintln!("skipping: scoping requires landlock ABI 6");
+        return Ok(());
+    }
+
+    let name = format!("leucite-scope-{}", std::process::id());
+    let _listener = UnixListener::bind_addr(&SocketAddr::from_abstract_name(&name)?)?;
+
+    let script = format!("import socket; socket.socket(socket.AF_UNIX).connect('\\0{name}')");
+    let connect = |rules: Rules| {
+        StdCommand::new("python3")
+            .arg("-c")
+            .arg(&script)
+            .stderr(std::process::Stdio::null())
+            .restrict(Arc::new(rules))
+            .status()
+    };
+
+    assert!(connect(base_rules())?.success());
+    assert!(!connect(base_rules().scope_abstract_unix_sockets())?.success());
+
+    Ok(())
+}
+
+#[test]
+fn scope_signals() -> Result<(), Box<dyn std::error::Error>> {
+    if !matches!(leucite::landlock_abi(), Some(abi) if abi as i32 >= 6) {
+        eprintln!("skipping: scoping requires landlock ABI 6");
+        return Ok(());
+    }
+
+    let tempdir = TempDir::new("leucite")?;
+    // Signal 0 only checks whether a signal may be sent
+    let script = format!("kill -0 {}", std::process::id());
+
+    assert!(run_sh(tempdir.path(), base_rules(), &script)?);
+    assert!(!run_sh(
+        tempdir.path(),
+        base_rules().scope_signals(),
+        &script
+    )?);
+    assert!(run_sh(
+        tempdir.path(),
+        base_rules().scope_signals(),
+        "sleep 10 & kill $!"
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn merge_beneath_overlapping() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let dir = tempdir.path();
+    let out = dir.join("out");
+    std::fs::create_dir(&out)?;
+    std::fs::write(dir.join("in.txt"), "in")?;
+    std::fs::write(out.join("out.txt"), "out")?;
+
+    type MakeRules = fn(&Path, &Path) -> Rules;
+    #[rustfmt::skip]
+    let cases: &[(&str, MakeRules, MakeRules, &str, bool)] = &[
+        // broad read-only, nested read/write: the nested rule adds write access
+        ("ro + nested rw", |d, _| Rules::new().add_read_only(d), |_, o| Rules::new().add_read_write(o), "echo x > out/new", true),
+        ("ro + nested rw", |d, _| Rules::new().add_read_only(d), |_, o| Rules::new().add_read_write(o), "echo x > new", false),
+        ("ro + nested rw", |d, _| Rules::new().add_read_only(d), |_, o| Rules::new().add_read_write(o), "cat in.txt", true),
+        // broad read/write, nested read-only: rights can not be removed from the subtree
+        ("rw + nested ro", |d, _| Rules::new().add_read_write(d), |_, o| Rules::new().add_read_only(o), "echo x > out/new", true),
+        ("rw + nested ro", |d, _| Rules::new().add_read_write(d), |_, o| Rules::new().add_read_only(o), "echo x > new", true),
+        // broad read-only, nested write-only: the nested path is both readable and writable
+        ("ro + nested wo", |d, _| Rules::new().add_read_only(d), |_, o| Rules::new().add_write_only(o), "cat out/out.txt && echo x > out/new", true),
+        // only the nested path: its parent is not accessible
+        ("nested ro", |_, _| Rules::new(), |_, o| Rules::new().add_read_only(o), "cat in.txt", false),
+        ("nested ro", |_, _| Rules::new(), |_, o| Rules::new().add_read_only(o), "cat out/out.txt", true),
+        // the same path with different rights: the union of the rights
+        ("same path", |d, _| Rules::new().add_read_only(d), |d, _| Rules::new().add_write_only(d), "cat in.txt && echo x > new", true),
+    ];
+
+    for (name, base, overlay, script, expected) in cases {
+        for swap in [false, true] {
+            let (a, b) = if swap {
+                (overlay, base)
+            } else {
+                (base, overlay)
+            };
+            let rules = base_rules().merge_beneath(a(dir, &out).merge_beneath(b(dir, &out)));
+            assert_eq!(
+                run_sh(dir, rules, script)?,
+                *expected,
+                "{name} (swapped: {swap}): {script}"
+            );
+            let _ = std::fs::remove_file(dir.join("new"));
+            let _ = std::fs::remove_file(out.join("new"));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn merge_beneath_dedup() {
+    let a = Rules::new()
+        .add_read_only("/usr")
+        .add_read_write("/tmp")
+        .add_connect_port(443);
+    let b = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_connect_port(443);
+
+    assert_eq!(
+        a.merge_beneath(b),
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_only("/etc")
+            .add_read_write("/tmp")
+            .add_connect_port(443)
+    );
+}
+
+#[test]
+fn read_access_bits() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = tempdir.path().join("true");
+    std::fs::copy("/bin/true", &exe)?;
+
+    // By default, read access includes `Execute`
+    let status = StdCommand::new(&exe)
+        .restrict(Arc::new(base_rules().add_read_only(tempdir.path())))
+        .status()?;
+    assert!(status.success());
+
+    let rules = base_rules()
+        .add_read_only(tempdir.path())
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir);
+    assert_eq!(rules.read_access(), AccessFs::ReadFile | AccessFs::ReadDir);
+
+    // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+    std::thread::spawn(move || {
+        unsafe { rules.restrict_self() }.unwrap();
+
+        // reading is still allowed...
+        std::fs::read(&exe).unwrap();
+
+        // ...but executing is not
+        let err = StdCommand::new(&exe).status().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+    })
+    .join()
+    .unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn allow_shared_libs_of() -> Result<(), Box<dyn std::error::Error>> {
+    let binary = Path::new("/usr/bin/ls");
+    let rules = Rules::new().allow_shared_libs_of(binary)?;
+
+    let report = rules.exposure_report();
+    assert!(
+        report.readable.iter().any(|p| p.ends_with("libc.so.6")),
+        "{report}"
+    );
+    assert!(!report.readable.iter().any(|p| p.is_dir()), "{report}");
+
+    let tempdir = TempDir::new("leucite")?;
+    let run = |rules: Rules| {
+        StdCommand::new(binary)
+            .arg(tempdir.path())
+            .stdout(std::process::Stdio::null())
+            .restrict(Arc::new(rules))
+            .status()
+    };
+
+    // only the binary and its libraries are needed for it to run
+    let dir = tempdir.path();
+    assert!(run(rules.add_read_only(binary).add_read_only(dir))?.success());
+    // without the dynamic linker, the binary can not be executed at all
+    let res = run(Rules::new().add_read_only(binary).add_read_only(dir));
+    assert!(!res.is_ok_and(|s| s.success()));
+
+    Ok(())
+}
+
+#[test]
+fn allow_shared_libs_of_invalid() {
+    let err = Rules::new()
+        .allow_shared_libs_of("/etc/passwd")
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn allow_shared_libs_of_malformed() -> Result<(), Box<dyn std::error::Error>> {
+    /// A 64-bit little-endian ELF header with a single program header
+    fn elf(phoff: u64, ph_type: u32, p_offset: u64, p_filesz: u64) -> Vec<u8> {
+        let mut data = vec![0; 64 + 56];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[32..40].copy_from_slice(&phoff.to_le_bytes());
+        data[54..56].copy_from_slice(&56u16.to_le_bytes());
+        data[56..58].copy_from_slice(&1u16.to_le_bytes());
+        data[64..68].copy_from_slice(&ph_type.to_le_bytes());
+        data[72..80].copy_from_slice(&p_offset.to_le_bytes());
+        data[96..104].copy_from_slice(&p_filesz.to_le_bytes());
+        data
+    }
+
+    let tempdir = TempDir::new("leucite")?;
+    let binary = tempdir.path().join("binary");
+    let cases = [
+        ("truncated", b"\x7fELF\x02\x01".to_vec()),
+        ("dynamic segment overflows", elf(64, 2, 8, u64::MAX)),
+        ("program headers overflow", elf(u64::MAX - 4, 2, 8, 16)),
+        ("interpreter out of range", elf(64, 3, u64::MAX, 0)),
+    ];
+    for (name, data) in cases {
+        std::fs::write(&binary, data)?;
+        let err = Rules::new().allow_shared_libs_of(&binary).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{name}: {err}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn allow_tmp_execution() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = tempdir.path().join("true");
+    std::fs::copy("/bin/true", &exe)?;
+
+    let rules = || base_rules().add_read_write(tempdir.path());
+    assert!(rules().allows_tmp_execution());
+    let run = |rules: Rules| StdCommand::new(&exe).restrict(Arc::new(rules)).status();
+
+    assert!(run(rules())?.success());
+    assert!(run(rules().allow_tmp_execution(true))?.success());
+
+    let noexec = rules().allow_tmp_execution(false);
+    assert!(!noexec.allows_tmp_execution());
+    assert!(!noexec
+        .exposure_report()
+        .executable
+        .iter()
+        .any(|p| p == tempdir.path()));
+    let err = run(noexec.clone()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    // the files are still writable and readable
+    assert!(run_sh(
+        tempdir.path(),
+        noexec,
+        "echo hi > file && cat file"
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn add_read_execute() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let (bin, data) = (tempdir.path().join("bin"), tempdir.path().join("data"));
+    std::fs::create_dir(&bin)?;
+    std::fs::create_dir(&data)?;
+    std::fs::copy("/bin/true", bin.join("true"))?;
+    std::fs::copy("/bin/true", data.join("true"))?;
+
+    // read access does not include `Execute`, so only the paths added with `add_read_execute`
+    // may be executed
+    let rules = Rules::new()
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+        .add_read_execute("/usr")
+        .add_read_execute("/lib")
+        .add_read_execute("/lib64")
+        .add_read_execute(&bin)
+        .add_read_only(&data);
+    let report = rules.exposure_report();
+    assert!(report.executable.contains(&bin));
+    assert!(!report.executable.contains(&data));
+    let rules = Arc::new(rules);
+
+    let status = StdCommand::new(bin.join("true"))
+        .restrict(Arc::clone(&rules))
+        .status()?;
+    assert!(status.success());
+
+    let err = StdCommand::new(data.join("true"))
+        .restrict(rules)
+        .status()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+    Ok(())
+}
+
+#[test]
+fn merge_and_extend() {
+    let system = Rules::new().add_read_only("/usr").add_read_only("/etc");
+    let task = Rules::new()
+        .add_read_only("/usr")
+        .add_read_write("/tmp/work")
+        .add_connect_port(443);
+    let expected = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_write("/tmp/work")
+        .add_connect_port(443);
+
+    assert_eq!(system.clone().merge(task.clone()), expected);
+
+    let mut rules = system;
+    rules.extend(&task);
+    assert_eq!(rules, expected);
+}
+
+#[test]
+fn merge_audit_only_fragment() -> Result<(), Box<dyn std::error::Error>> {
+    use leucite::test_utils::run_restricted;
+
+    let base = Rules::new().add_read_only("/usr");
+    let fragment = Rules::new().add_read_only("/etc").audit_only();
+
+    // an audit-only fragment does not switch off enforcement of the base
+    for rules in [base.clone().merge(fragment.clone()), {
+        let mut rules = base.clone();
+        rules.extend(&fragment);
+        rules
+    }] {
+        assert!(!rules.is_audit_only());
+        assert!(run_restricted(&rules, || std::fs::read(
+            "/proc/self/status"
+        )
+        .is_err())?);
+    }
+    assert!(fragment.clone().merge(fragment).is_audit_only());
+
+    Ok(())
+}
+
+#[test]
+fn refer() -> Result<(), Box<dyn std::error::Error>> {
+    use leucite::test_utils::run_restricted;
+
+    let tempdir = TempDir::new("leucite")?;
+    let (src, dst) = (tempdir.path().join("src"), tempdir.path().join("dst"));
+    std::fs::create_dir(&src)?;
+    std::fs::create_dir(&dst)?;
+
+    let rename = |rules: Rules| -> std::io::Result<bool> {
+        std::fs::write(src.join("file"), "")?;
+        let renamed = run_restricted(&rules, || {
+            std::fs::rename(src.join("file"), dst.join("file")).is_ok()
+        })?;
+        if renamed {
+            std::fs::rename(dst.join("file"), src.join("file"))?;
+        }
+        Ok(renamed)
+    };
+    // Everything needed to rename, except `Refer`
+    let rules = || {
+        Rules::new()
+            .write_access_bits(AccessFs::WriteFile | AccessFs::MakeReg | AccessFs::RemoveFile)
+            .add_read_write(&src)
+            .add_read_write(&dst)
+    };
+
+    assert!(!rename(rules())?);
+    assert!(!rename(rules().add_refer(&src))?);
+    assert!(!rename(rules().add_refer(&dst))?);
+    assert!(rename(rules().add_refer(&src).add_refer(&dst))?);
+    // `add_read_write` grants `Refer` by default
+    assert!(rename(
+        Rules::new().add_read_write(&src).add_read_write(&dst)
+    )?);
+
+    Ok(())
+}
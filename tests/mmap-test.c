@@ -0,0 +1,17 @@
+#include <stdio.h>
+#include <string.h>
+#include <sys/mman.h>
+
+#define SIZE (256 * 1024 * 1024)
+
+int main(void) {
+    // Shared mappings are not counted by RLIMIT_DATA
+    char *p = mmap(NULL, SIZE, PROT_READ | PROT_WRITE, MAP_SHARED | MAP_ANONYMOUS, -1, 0);
+    if (p == MAP_FAILED) {
+        perror("mmap");
+        return 1;
+    }
+    memset(p, 1, 1024 * 1024);
+    printf("mapped\n");
+    return 0;
+}
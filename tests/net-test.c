@@ -0,0 +1,39 @@
+#include <arpa/inet.h>
+#include <errno.h>
+#include <netinet/in.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/socket.h>
+
+// Usage: ./test <bind|connect> <port>
+// Exits with 0 on success, 2 if the operation was denied, and 1 on any other error
+int main(int argc, char **argv) {
+    if (argc != 3) {
+        return 1;
+    }
+
+    int fd = socket(AF_INET, SOCK_STREAM, 0);
+    if (fd < 0) {
+        return 1;
+    }
+
+    struct sockaddr_in addr;
+    memset(&addr, 0, sizeof(addr));
+    addr.sin_family = AF_INET;
+    addr.sin_port = htons(atoi(argv[2]));
+    addr.sin_addr.s_addr = htonl(INADDR_LOOPBACK);
+
+    int ret;
+    if (strcmp(argv[1], "bind") == 0) {
+        ret = bind(fd, (struct sockaddr *)&addr, sizeof(addr));
+    } else {
+        ret = connect(fd, (struct sockaddr *)&addr, sizeof(addr));
+    }
+
+    if (ret != 0) {
+        fprintf(stderr, "[ERR] %s: %s\n", argv[1], strerror(errno));
+        return errno == EACCES ? 2 : 1;
+    }
+    return 0;
+}
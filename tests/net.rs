@@ -0,0 +1,242 @@
+use std::{
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{Command as StdCommand, Stdio},
+    sync::Arc,
+};
+
+use leucite::{test_utils::run_restricted, CommandExt, Rules};
+use tempdir::TempDir;
+
+/// Compile the network test program into `dir`
+fn compile(dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(dir.join("test.c"), include_str!("./net-test.c"))?;
+    let status = StdCommand::new("gcc")
+        .arg("-o")
+        .arg("test")
+        .arg("test.c")
+        .current_dir(dir)
+        .status()?;
+    assert!(status.success());
+    Ok(())
+}
+
+/// Rules which allow running the test program in `dir`
+fn base_rules(dir: &Path) -> Rules {
+    Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_only("/dev")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+        .add_read_only(dir)
+}
+
+/// Run the test program, returning its exit code (0 on success, 2 if the operation was denied)
+fn run(dir: &Path, rules: Rules, op: &str, port: u16) -> std::io::Result<Option<i32>> {
+    let out = StdCommand::new("./test")
+        .arg(op)
+        .arg(port.to_string())
+        .current_dir(dir)
+        .env_clear()
+        .stderr(Stdio::piped())
+        .restrict(Arc::new(rules))
+        .output()?;
+
+    // capture the stderr so that it is not logged when the test succeeds
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    stderr.lines().for_each(|l| println!("[STDERR] {}", l));
+
+    Ok(out.status.code())
+}
+
+/// Find a port which is not currently in use
+fn free_port() -> std::io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+#[test]
+fn client_only() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    compile(tempdir.path())?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let rules = || base_rules(tempdir.path()).client_only();
+
+    assert_eq!(run(tempdir.path(), rules(), "connect", port)?, Some(0));
+    assert_eq!(run(tempdir.path(), rules(), "bind", free_port()?)?, Some(2));
+    assert_eq!(run(tempdir.path(), rules(), "bind", 0)?, Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn server_only() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    compile(tempdir.path())?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let bind_port = free_port()?;
+    let rules = || base_rules(tempdir.path()).server_only([bind_port]);
+
+    assert_eq!(run(tempdir.path(), rules(), "bind", bind_port)?, Some(0));
+    assert_eq!(run(tempdir.path(), rules(), "bind", free_port()?)?, Some(2));
+    assert_eq!(run(tempdir.path(), rules(), "connect", port)?, Some(2));
+
+    // make sure that the listener is still alive (and the connection was denied by landlock)
+    TcpStream::connect(("127.0.0.1", port))?;
+
+    Ok(())
+}
+
+#[test]
+fn explicit_network_modes() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    compile(tempdir.path())?;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let dir = tempdir.path();
+
+    let deny = || base_rules(dir).add_connect_port(port).deny_all_network();
+    assert_eq!(run(dir, deny(), "connect", port)?, Some(2));
+    assert_eq!(run(dir, deny(), "bind", 0)?, Some(2));
+
+    let bind = || base_rules(dir).deny_all_network().allow_all_bind();
+    assert_eq!(run(dir, bind(), "bind", free_port()?)?, Some(0));
+    assert_eq!(run(dir, bind(), "connect", port)?, Some(2));
+
+    let connect = || base_rules(dir).deny_all_network().allow_all_connect();
+    assert_eq!(run(dir, connect(), "connect", port)?, Some(0));
+    assert_eq!(run(dir, connect(), "bind", free_port()?)?, Some(2));
+
+    Ok(())
+}
+
+#[test]
+fn empty_rules_deny_everything() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::CommandExt as _;
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+
+    // Nothing can be executed with empty rules, so check each access from the child before `exec`
+    let check = move || {
+        let denied = |ret: libc::c_int| {
+            ret < 0 && std::io::Error::last_os_error().raw_os_error() == Some(libc::EACCES)
+        };
+        let addr = |port: u16| libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: port.to_be(),
+            sin_addr: libc::in_addr {
+                s_addr: u32::from(std::net::Ipv4Addr::LOCALHOST).to_be(),
+            },
+            sin_zero: [0; 8],
+        };
+        let len = std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t;
+
+        // SAFETY: The path is nul-terminated, and each address is a valid `sockaddr_in` of `len`
+        let results = unsafe {
+            [
+                libc::open(c"/etc/passwd".as_ptr(), libc::O_RDONLY),
+                libc::bind(
+                    libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0),
+                    (&addr(0) as *const libc::sockaddr_in).cast(),
+                    len,
+                ),
+                libc::connect(
+                    libc::socket(libc::AF_INET, libc::SOCK_STREAM, 0),
+                    (&addr(port) as *const libc::sockaddr_in).cast(),
+                    len,
+                ),
+            ]
+        };
+        let code = results
+            .iter()
+            .enumerate()
+            .fold(0, |code, (i, &ret)| code | (i32::from(!denied(ret)) << i));
+        // SAFETY: Exiting without running any destructors is what the child does if `exec` fails
+        unsafe { libc::_exit(code) }
+    };
+
+    // SAFETY: The check doesn't allocate or de-allocate memory
+    let status = unsafe {
+        StdCommand::new("/bin/true")
+            .restrict(Arc::new(Rules::new()))
+            .pre_exec(check)
+            .status()?
+    };
+    // Each bit of the exit code is an access which was not denied
+    assert_eq!(status.code(), Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn port_ranges() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Rules::new()
+        .add_connect_port(8080)
+        .add_connect_port_range(8079..=8081)
+        .add_connect_port_range(8080..=8082);
+    assert_eq!(rules.connect_ports(), Some(&[8080, 8079, 8081, 8082][..]));
+    let rules = Rules::new().allow_all_bind().add_bind_port_range(1..=100);
+    assert_eq!(rules.bind_ports(), None);
+
+    let tempdir = TempDir::new("leucite")?;
+    compile(tempdir.path())?;
+    let dir = tempdir.path();
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    let range = |start: u16, end: u16| base_rules(dir).add_connect_port_range(start..=end);
+    assert_eq!(
+        run(dir, range(port.saturating_sub(5), port), "connect", port)?,
+        Some(0)
+    );
+    assert_eq!(
+        run(
+            dir,
+            range(port.saturating_add(1), u16::MAX),
+            "connect",
+            port
+        )?,
+        Some(2)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn connect_port_any_host() -> Result<(), Box<dyn std::error::Error>> {
+    let v4 = TcpListener::bind("127.0.0.1:0")?;
+    let port = v4.local_addr()?.port();
+    // the same port on another address, if IPv6 is available
+    let v6 = TcpListener::bind(("::1", port)).ok();
+
+    let rules = Rules::new().add_connect_port_any_host(port);
+    assert_eq!(rules, Rules::new().add_connect_port(port));
+
+    // the port is allowed for every address, not only the one that the rule was meant for
+    assert!(run_restricted(&rules, || TcpStream::connect((
+        "127.0.0.1",
+        port
+    ))
+    .is_ok())?);
+    if v6.is_some() {
+        assert!(run_restricted(&rules, || TcpStream::connect((
+            "::1", port
+        ))
+        .is_ok())?);
+    }
+    // while other ports are still denied
+    let other = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+    assert!(run_restricted(&rules, || {
+        TcpStream::connect(("127.0.0.1", other))
+            .is_err_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+    })?);
+
+    Ok(())
+}
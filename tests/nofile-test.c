@@ -0,0 +1,12 @@
+#include <errno.h>
+#include <fcntl.h>
+#include <stdio.h>
+
+int main(void) {
+    int opened = 0;
+    while (open("/dev/null", O_RDONLY) >= 0) {
+        opened++;
+    }
+    printf("%d\n", opened);
+    return errno == EMFILE ? 0 : 1;
+}
@@ -0,0 +1,76 @@
+use std::{path::Path, process::Command as StdCommand};
+
+use leucite::{CommandExt, Error, PreparedRules, Rules};
+use tempdir::TempDir;
+
+fn prepared() -> Result<PreparedRules, Error> {
+    Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_only("/dev")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+        .add_read_only("/bin/sh")
+        .prepare()
+}
+
+fn run_sh(dir: &Path, rules: PreparedRules, script: &str) -> std::io::Result<bool> {
+    let status = StdCommand::new("sh")
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir)
+        .restrict_prepared(rules)
+        .status()?;
+    Ok(status.success())
+}
+
+#[test]
+fn with_working_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let base = prepared()?;
+    let first = TempDir::new("leucite")?;
+    let second = TempDir::new("leucite")?;
+
+    for _ in 0..2 {
+        assert!(run_sh(
+            first.path(),
+            base.with_working_dir(first.path()),
+            "echo first > out"
+        )?);
+        assert!(run_sh(
+            second.path(),
+            base.with_working_dir(second.path()),
+            "echo second > out"
+        )?);
+    }
+
+    // the working directory of one command is not granted to the other
+    let script = format!("echo other > {}/out", second.path().display());
+    assert!(!run_sh(
+        first.path(),
+        base.with_working_dir(first.path()),
+        &script
+    )?);
+    // the base rules don't grant any working directory at all
+    assert!(!run_sh(first.path(), base.clone(), "echo base > out")?);
+
+    assert_eq!(
+        std::fs::read_to_string(first.path().join("out"))?,
+        "first\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(second.path().join("out"))?,
+        "second\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn prepare_missing_path() {
+    let res = Rules::new()
+        .add_read_only("/this/path/does/not/exist")
+        .prepare();
+
+    assert!(matches!(res, Err(Error::InvalidPath { .. })));
+}
@@ -1,6 +1,8 @@
 use std::{process::Stdio, sync::Arc};
 
-use leucite::{CommandExt, MemorySize, Rules};
+use leucite::{
+    CommandExt, LimitValue, MemorySize, ParseMemorySizeError, Resource, ResourceLimits, Rules,
+};
 use std::process::Command as StdCommand;
 use tempdir::TempDir;
 use tmpdir::TmpDir;
@@ -107,3 +109,593 @@ fn prlimit_std() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn memory_size_delta() {
+    let delta = MemorySize::from_mib(200) - MemorySize::from_mib(100);
+    assert!(!delta.is_negative());
+    assert_eq!(delta.magnitude(), MemorySize::from_mib(100));
+    assert_eq!(delta.to_string(), "+100 MiB");
+
+    let delta = MemorySize::from_mib(50) - MemorySize::from_mib(100);
+    assert!(delta.is_negative());
+    assert_eq!(delta.to_string(), "-50 MiB");
+
+    assert_eq!(
+        (MemorySize::from_mib(1536) - MemorySize::from_bytes(0)).to_string(),
+        "+1.5 GiB"
+    );
+    assert_eq!(
+        (MemorySize::from_kb(1) - MemorySize::from_kb(1)).to_string(),
+        "+0 B"
+    );
+    assert_eq!(
+        (MemorySize::from_bytes(0) - MemorySize::from_bytes(1000)).to_string(),
+        "-1000 B"
+    );
+}
+
+#[test]
+fn memory_size_display() {
+    assert_eq!(MemorySize::from_bytes(0).to_string(), "0 B");
+    assert_eq!(MemorySize::from_bytes(1023).to_string(), "1023 B");
+    assert_eq!(MemorySize::from_kib(1).to_string(), "1 KiB");
+    assert_eq!(MemorySize::from_mib(100).to_string(), "100 MiB");
+    assert_eq!(MemorySize::from_mib(1536).to_string(), "1.5 GiB");
+    assert_eq!(MemorySize::from_bytes(u64::MAX).to_string(), "16 EiB");
+
+    assert_eq!(MemorySize::from_mib(100).to_human_binary(), "100 MiB");
+    assert_eq!(MemorySize::from_mb(100).to_human_decimal(), "100 MB");
+    assert_eq!(MemorySize::from_mib(100).to_human_decimal(), "104.9 MB");
+    assert_eq!(MemorySize::from_bytes(999).to_human_decimal(), "999 B");
+}
+
+#[test]
+fn memory_size_consts() -> Result<(), Box<dyn std::error::Error>> {
+    assert!(MemorySize::ZERO.is_zero());
+    assert!(MemorySize::from_bytes(0).is_zero());
+    assert!(!MemorySize::from_bytes(1).is_zero());
+    assert!(!MemorySize::MAX.is_zero());
+    assert_eq!(MemorySize::MAX.bytes(), u64::MAX);
+
+    // `MAX` is the same as `RLIM_INFINITY`
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .max_file_size(MemorySize::MAX)
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = limits
+        .lines()
+        .find_map(|l| l.strip_prefix("Max file size"))
+        .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>());
+    assert_eq!(limit, Some(vec!["unlimited", "unlimited"]));
+
+    Ok(())
+}
+
+#[test]
+fn memory_size_fraction_of_total() -> Result<(), Box<dyn std::error::Error>> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo")?;
+    let total_kib: u64 = meminfo
+        .lines()
+        .find_map(|l| l.strip_prefix("MemTotal:"))
+        .and_then(|l| l.trim().strip_suffix("kB"))
+        .ok_or("no MemTotal in /proc/meminfo")?
+        .trim()
+        .parse()?;
+
+    let total = MemorySize::fraction_of_total(1.0)?;
+    assert_eq!(total.kibibytes(), total_kib);
+    let quarter = MemorySize::fraction_of_total(0.25)?;
+    assert!(quarter.bytes().abs_diff(total.bytes() / 4) <= 1);
+
+    assert_eq!(MemorySize::fraction_of_total(2.0)?, total);
+    assert_eq!(MemorySize::fraction_of_total(-1.0)?.bytes(), 0);
+    assert!(MemorySize::fraction_of_total(f64::NAN).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn memory_size_serde() -> Result<(), serde_json::Error> {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Limits {
+        max_memory: MemorySize,
+        #[serde(with = "leucite::human_memory_size")]
+        max_file_size: MemorySize,
+    }
+
+    let limits: Limits =
+        serde_json::from_str(r#"{ "max_memory": "512MiB", "max_file_size": 1000 }"#)?;
+    assert_eq!(
+        limits,
+        Limits {
+            max_memory: MemorySize::from_mib(512),
+            max_file_size: MemorySize::from_bytes(1000),
+        }
+    );
+
+    let json = serde_json::to_value(&limits)?;
+    assert_eq!(json["max_memory"], 512 * 1024 * 1024);
+    assert_eq!(json["max_file_size"], "1000 B");
+    assert_eq!(serde_json::from_value::<Limits>(json)?, limits);
+
+    let human = |size| {
+        let limits = Limits {
+            max_memory: size,
+            max_file_size: size,
+        };
+        serde_json::to_value(limits).map(|json| json["max_file_size"].clone())
+    };
+    assert_eq!(human(MemorySize::from_gib(2))?, "2 GiB");
+    assert_eq!(human(MemorySize::from_kib(1536))?, "1536 KiB");
+
+    assert!(serde_json::from_str::<MemorySize>("-1").is_err());
+    assert!(serde_json::from_str::<MemorySize>(r#""12 parsecs""#).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn memory_size_from_str() {
+    assert_eq!("100MB".parse(), Ok(MemorySize::from_mb(100)));
+    assert_eq!("100 MiB".parse(), Ok(MemorySize::from_mib(100)));
+    assert_eq!(" 2gib ".parse(), Ok(MemorySize::from_gib(2)));
+    assert_eq!("1.5GiB".parse(), Ok(MemorySize::from_mib(1536)));
+    assert_eq!("4096".parse(), Ok(MemorySize::from_bytes(4096)));
+    assert_eq!("512 b".parse(), Ok(MemorySize::from_bytes(512)));
+    assert_eq!("1KB".parse(), Ok(MemorySize::from_kb(1)));
+
+    assert!(matches!(
+        "100 MBs".parse::<MemorySize>(),
+        Err(ParseMemorySizeError::UnknownUnit(_))
+    ));
+    assert!(matches!(
+        "MB".parse::<MemorySize>(),
+        Err(ParseMemorySizeError::InvalidNumber(_))
+    ));
+    assert!(matches!(
+        "1.2.3 MB".parse::<MemorySize>(),
+        Err(ParseMemorySizeError::InvalidNumber(_))
+    ));
+    assert!(matches!(
+        "99999999999 GiB".parse::<MemorySize>(),
+        Err(ParseMemorySizeError::TooLarge(_))
+    ));
+    // the fraction overflows the sum of the whole and fractional bytes
+    assert!(matches!(
+        "340282366920938463463374607431.999999999999 GB".parse::<MemorySize>(),
+        Err(ParseMemorySizeError::TooLarge(_))
+    ));
+}
+
+#[test]
+fn memory_size_round_trip() {
+    // each size is a whole number of the unit that it is formatted in
+    for size in [
+        MemorySize::from_bytes(512),
+        MemorySize::from_kib(3),
+        MemorySize::from_mib(1536),
+        MemorySize::from_gib(2),
+        MemorySize::from_gib(5 << 10),
+        MemorySize::from_gib(3 << 20),
+        MemorySize::from_gib(7 << 30),
+    ] {
+        assert_eq!(size.to_string().parse(), Ok(size), "{size}");
+    }
+    for size in [
+        MemorySize::from_bytes(512),
+        MemorySize::from_kb(3),
+        MemorySize::from_mb(100),
+        MemorySize::from_gb(2),
+        MemorySize::from_gb(2_500),
+        MemorySize::from_gb(4_000_000),
+        MemorySize::from_gb(12_000_000_000),
+    ] {
+        let decimal = size.to_human_decimal();
+        assert_eq!(decimal.parse(), Ok(size), "{decimal}");
+    }
+
+    assert_eq!("1TB".parse(), Ok(MemorySize::from_gb(1000)));
+    assert_eq!("1 PB".parse(), Ok(MemorySize::from_gb(1_000_000)));
+    assert_eq!("1 eib".parse(), Ok(MemorySize::from_gib(1 << 30)));
+}
+
+/// Compile the C program `source` into `dir`, returning the path of the executable
+fn compile(
+    dir: &std::path::Path,
+    source: &str,
+) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    std::fs::write(dir.join("test.c"), source)?;
+    let status = StdCommand::new("gcc")
+        .arg("-o")
+        .arg("test")
+        .arg("test.c")
+        .current_dir(dir)
+        .status()?;
+    assert!(status.success());
+    Ok(dir.join("test"))
+}
+
+#[test]
+fn max_cpu_time_std() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./cpu-test.c"))?;
+
+    let start = std::time::Instant::now();
+    let status = StdCommand::new(exe).max_cpu_time(1).status()?;
+
+    assert_eq!(status.signal(), Some(libc::SIGXCPU));
+    assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn max_cpu_time_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./cpu-test.c"))?;
+
+    let status = TokioCommand::new(exe)
+        .max_cpu_time_if(Some(1))
+        .status()
+        .await?;
+
+    assert_eq!(status.signal(), Some(libc::SIGXCPU));
+
+    Ok(())
+}
+
+#[test]
+fn max_address_space() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./mmap-test.c"))?;
+
+    // A shared mapping escapes `max_memory`...
+    let out = StdCommand::new(&exe)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .max_memory(MemorySize::from_mib(64))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "mapped");
+
+    // ...but not `max_address_space`
+    let out = StdCommand::new(&exe)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .max_address_space_if(Some(MemorySize::from_mib(64)))
+        .output()?;
+    assert!(!out.status.success(), "{out:?}");
+    assert!(out.stdout.is_empty());
+
+    Ok(())
+}
+
+/// Run a shell script which starts 10 background processes with at most `max_processes`
+fn fork_bomb(max_processes: Option<u64>) -> std::io::Result<std::process::Output> {
+    use std::os::unix::process::CommandExt as _;
+
+    let mut cmd = StdCommand::new("sh");
+    cmd.arg("-c")
+        .arg("for i in 1 2 3 4 5 6 7 8 9 10; do sleep 0.2 & done; wait")
+        .stderr(Stdio::piped())
+        .max_processes_if(max_processes);
+    // the limit is not enforced for root, so run the command as `nobody`
+    // SAFETY: `geteuid` has no memory safety requirements
+    if unsafe { libc::geteuid() } == 0 {
+        cmd.uid(65534).gid(65534);
+    }
+    cmd.output()
+}
+
+#[test]
+fn max_processes() -> Result<(), Box<dyn std::error::Error>> {
+    let out = fork_bomb(Some(3))?;
+    assert!(!out.status.success());
+    assert!(
+        String::from_utf8_lossy(&out.stderr).contains("fork"),
+        "{out:?}"
+    );
+
+    assert!(fork_bomb(None)?.status.success());
+
+    Ok(())
+}
+
+#[test]
+fn max_open_files() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./nofile-test.c"))?;
+
+    let out = StdCommand::new(exe)
+        .stdout(Stdio::piped())
+        .max_open_files(16)
+        .output()?;
+
+    assert!(out.status.success(), "{out:?}");
+    // the standard streams count towards the limit
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "13");
+
+    Ok(())
+}
+
+#[test]
+fn restrict_and_limit() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./nofile-test.c"))?;
+
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+        .add_read_only("/dev")
+        .add_read_only(&exe);
+    let limits = ResourceLimits::new()
+        .max_open_files(16)
+        .max_memory(MemorySize::from_mb(50));
+
+    let out = StdCommand::new(exe)
+        .stdout(Stdio::piped())
+        .restrict_and_limit(Arc::new(rules), &limits)
+        .output()?;
+
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "13");
+
+    Ok(())
+}
+
+#[test]
+fn max_file_size() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let tempdir = TempDir::new("leucite")?;
+    let write = |limit: u64, script: &str| {
+        StdCommand::new("sh")
+            .arg("-c")
+            .arg(script)
+            .current_dir(tempdir.path())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .max_file_size(MemorySize::from_bytes(limit))
+            .status()
+    };
+    let contents = || std::fs::read(tempdir.path().join("file"));
+
+    // creating an empty file is allowed, but writing a single byte is not
+    let status = write(0, "printf a > file")?;
+    assert_eq!(status.signal(), Some(libc::SIGXFSZ));
+    assert_eq!(contents()?, b"");
+
+    assert!(write(3, "printf abc > file")?.success());
+    assert_eq!(contents()?, b"abc");
+
+    let status = write(3, "printf abcd > file")?;
+    assert_eq!(status.signal(), Some(libc::SIGXFSZ));
+    assert_eq!(contents()?, b"abc");
+
+    // the write fails instead if the signal is ignored
+    let status = write(3, "trap '' XFSZ; printf abcd > file")?;
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(contents()?, b"abc");
+
+    // pipes are not affected
+    assert!(write(0, "printf abc | cat")?.success());
+
+    Ok(())
+}
+
+#[test]
+fn max_rt_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .max_rt_cpu_time(std::time::Duration::from_millis(1500))
+        // raising the hard limit above the current one requires `CAP_SYS_RESOURCE`
+        .max_rt_priority(0)
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = |name: &str| {
+        limits
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>())
+    };
+    assert_eq!(limit("Max realtime priority"), Some(vec!["0", "0"]));
+    assert_eq!(
+        limit("Max realtime timeout"),
+        Some(vec!["1500000", "2500000"])
+    );
+
+    Ok(())
+}
+
+#[test]
+fn max_queue_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .max_pending_signals(16)
+        .max_message_queue_bytes(MemorySize::from_kib(64))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = |name: &str| {
+        limits
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>())
+    };
+    assert_eq!(limit("Max pending signals"), Some(vec!["16", "16"]));
+    assert_eq!(limit("Max msgqueue size"), Some(vec!["65536", "65536"]));
+
+    Ok(())
+}
+
+#[test]
+fn max_locked_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .max_locked_memory(MemorySize::from_kib(128))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = limits
+        .lines()
+        .find_map(|l| l.strip_prefix("Max locked memory"))
+        .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>());
+    assert_eq!(limit, Some(vec!["131072", "131072"]));
+
+    Ok(())
+}
+
+#[test]
+fn soft_hard_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .max_memory_soft_hard(MemorySize::from_mib(256), MemorySize::from_mib(512))
+        .max_file_size_soft_hard(MemorySize::from_kib(1), MemorySize::from_kib(4))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = |name: &str| {
+        limits
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>())
+    };
+    assert_eq!(limit("Max data size"), Some(vec!["268435456", "536870912"]));
+    assert_eq!(limit("Max file size"), Some(vec!["1024", "4096"]));
+
+    // The command may raise its soft limit up to the hard limit
+    let out = StdCommand::new("sh")
+        .arg("-c")
+        // `ulimit -f` counts blocks of 1024 bytes
+        .arg("ulimit -S -f 4 && ulimit -S -f")
+        .max_file_size_soft_hard(MemorySize::from_kib(1), MemorySize::from_kib(4))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "4");
+
+    let err = StdCommand::new("/bin/true")
+        .max_memory_soft_hard(MemorySize::from_mib(2), MemorySize::from_mib(1))
+        .status()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EINVAL));
+
+    Ok(())
+}
+
+#[test]
+fn max_stack_size() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::process::ExitStatusExt;
+
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./stack-test.c"))?;
+
+    let status = StdCommand::new(exe)
+        .max_stack_size(MemorySize::from_mib(1))
+        .status()?;
+
+    assert_eq!(status.signal(), Some(libc::SIGSEGV));
+
+    Ok(())
+}
+
+#[test]
+fn no_core_dumps() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let exe = compile(tempdir.path(), include_str!("./abort-test.c"))?;
+    let cores = || -> std::io::Result<usize> {
+        let mut n = 0;
+        for entry in std::fs::read_dir(tempdir.path())? {
+            if entry?.file_name().to_string_lossy().starts_with("core") {
+                n += 1;
+            }
+        }
+        Ok(n)
+    };
+    let run = |no_core_dumps: bool| {
+        let mut cmd = StdCommand::new("sh");
+        // raise the soft limit as far as the hard limit allows, since it is often 0 by default
+        cmd.arg("-c")
+            .arg("ulimit -c unlimited 2>/dev/null; exec \"$0\"")
+            .arg(&exe)
+            .current_dir(tempdir.path())
+            .stderr(Stdio::null());
+        if no_core_dumps {
+            cmd.no_core_dumps();
+        }
+        cmd.status()
+    };
+
+    assert!(!run(true)?.success());
+    assert_eq!(cores()?, 0);
+
+    // make sure that a core dump would have been written to the working directory otherwise
+    let core_pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern")?;
+    if core_pattern.starts_with("core") {
+        assert!(!run(false)?.success());
+        assert_eq!(cores()?, 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn unlimited() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .resource_limit(Resource::Memory, LimitValue::Unlimited)
+        .resource_limit(Resource::CpuTime, LimitValue::Unlimited)
+        .resource_limit_soft_hard(
+            Resource::FileSize,
+            MemorySize::from_kib(1).into(),
+            LimitValue::Unlimited,
+        )
+        .resource_limit(Resource::RtCpuTime, LimitValue::Bounded(5000))
+        .resource_limit_if(Resource::StackSize, Some(LimitValue::Unlimited))
+        .resource_limit_if(Resource::AddressSpace, None)
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+
+    let limits = String::from_utf8(out.stdout)?;
+    let limit = |name: &str| {
+        limits
+            .lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|l| l.split_whitespace().take(2).collect::<Vec<_>>())
+    };
+    assert_eq!(limit("Max data size"), Some(vec!["unlimited", "unlimited"]));
+    assert_eq!(limit("Max cpu time"), Some(vec!["unlimited", "unlimited"]));
+    assert_eq!(limit("Max file size"), Some(vec!["1024", "unlimited"]));
+    // like the CPU time, the real-time CPU time leaves a second before `SIGKILL`
+    assert_eq!(limit("Max realtime timeout"), Some(vec!["5000", "1005000"]));
+    assert_eq!(
+        limit("Max stack size"),
+        Some(vec!["unlimited", "unlimited"])
+    );
+    assert_eq!(
+        limit("Max address space"),
+        Some(vec!["unlimited", "unlimited"])
+    );
+
+    assert!(LimitValue::Unlimited.is_unlimited());
+    assert_eq!(
+        LimitValue::from(MemorySize::from_kib(1)),
+        LimitValue::Bounded(1024)
+    );
+    assert_eq!(LimitValue::Unlimited.to_string(), "unlimited");
+    assert_eq!(LimitValue::from(64).to_string(), "64");
+
+    Ok(())
+}
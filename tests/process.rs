@@ -0,0 +1,502 @@
+use std::{
+    os::unix::process::ExitStatusExt,
+    process::Stdio,
+    time::{Duration, Instant},
+};
+
+use leucite::{CommandExt, MemorySize, Rules};
+use std::{process::Command as StdCommand, sync::Arc};
+use tokio::process::Command as TokioCommand;
+
+#[tokio::test]
+async fn oom_score_adj_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let out = TokioCommand::new("cat")
+        .arg("/proc/self/oom_score_adj")
+        .stdout(Stdio::piped())
+        .oom_score_adj(500)
+        .spawn()?
+        .wait_with_output()
+        .await?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "500");
+
+    Ok(())
+}
+
+#[test]
+fn oom_score_adj_std() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/oom_score_adj")
+        .stdout(Stdio::piped())
+        .oom_score_adj(500)
+        .spawn()?
+        .wait_with_output()?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "500");
+
+    Ok(())
+}
+
+#[test]
+fn oom_score_adj_out_of_range() {
+    let res = StdCommand::new("true").oom_score_adj(1001).spawn();
+
+    assert!(res.is_err());
+}
+
+/// Get the `n`th (1-indexed) field from the output of `/proc/<pid>/stat`
+fn stat_field(stat: &str, n: usize) -> Option<i64> {
+    if n == 1 {
+        return stat.split_whitespace().next()?.parse().ok();
+    }
+    // The fields after the command name start at the 3rd
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(n - 3)?.parse().ok()
+}
+
+/// Get the scheduling policy from the output of `/proc/<pid>/stat`
+fn sched_policy(stat: &str) -> Option<u32> {
+    stat_field(stat, 41).map(|p| p as u32)
+}
+
+#[tokio::test]
+async fn idle_priority_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let out = TokioCommand::new("cat")
+        .arg("/proc/self/stat")
+        .stdout(Stdio::piped())
+        .idle_priority()
+        .spawn()?
+        .wait_with_output()
+        .await?;
+
+    assert!(out.status.success());
+    assert_eq!(
+        sched_policy(&String::from_utf8_lossy(&out.stdout)),
+        Some(libc::SCHED_IDLE as u32)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn idle_priority_std() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/stat")
+        .stdout(Stdio::piped())
+        .idle_priority()
+        .spawn()?
+        .wait_with_output()?;
+
+    assert!(out.status.success());
+    assert_eq!(
+        sched_policy(&String::from_utf8_lossy(&out.stdout)),
+        Some(libc::SCHED_IDLE as u32)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn nice() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/stat")
+        .nice(5)
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        stat_field(&String::from_utf8_lossy(&out.stdout), 19),
+        Some(5)
+    );
+
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/stat")
+        .nice(100)
+        .output()?;
+    assert_eq!(
+        stat_field(&String::from_utf8_lossy(&out.stdout), 19),
+        Some(19)
+    );
+
+    // A lower hard limit (e.g., the default of 0) is kept rather than raised
+    let nice_limit = |limits: &str| {
+        limits
+            .lines()
+            .find_map(|l| l.strip_prefix("Max nice priority"))
+            .and_then(|l| l.split_whitespace().nth(1)?.parse::<u64>().ok())
+    };
+    let hard = nice_limit(&std::fs::read_to_string("/proc/self/limits")?);
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/limits")
+        .min_nice(19)
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        nice_limit(&String::from_utf8(out.stdout)?),
+        hard.map(|hard| hard.min(1))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn detach_controlling_tty_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let out = TokioCommand::new("cat")
+        .arg("/proc/self/stat")
+        .stdout(Stdio::piped())
+        .detach_controlling_tty()
+        .spawn()?
+        .wait_with_output()
+        .await?;
+
+    assert!(out.status.success());
+    let stat = String::from_utf8_lossy(&out.stdout);
+    // the process is the leader of its session (field 6), and has no controlling terminal (field 7)
+    assert_eq!(stat_field(&stat, 6), stat_field(&stat, 1));
+    assert_eq!(stat_field(&stat, 7), Some(0));
+
+    Ok(())
+}
+
+#[test]
+fn detach_controlling_tty_std() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("cat")
+        .arg("/proc/self/stat")
+        .stdout(Stdio::piped())
+        .detach_controlling_tty()
+        .spawn()?
+        .wait_with_output()?;
+
+    assert!(out.status.success());
+    let stat = String::from_utf8_lossy(&out.stdout);
+    // the process is the leader of its session (field 6), and has no controlling terminal (field 7)
+    assert_eq!(stat_field(&stat, 6), stat_field(&stat, 1));
+    assert_eq!(stat_field(&stat, 7), Some(0));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hard_deadline_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let status = TokioCommand::new("sleep")
+        .arg("10")
+        .hard_deadline(Duration::from_millis(200))
+        .spawn()?
+        .wait()
+        .await?;
+
+    assert_eq!(status.signal(), Some(libc::SIGALRM));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
+
+#[test]
+fn hard_deadline_std() -> Result<(), Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let status = StdCommand::new("sleep")
+        .arg("10")
+        .hard_deadline(Duration::from_millis(200))
+        .spawn()?
+        .wait()?;
+
+    assert_eq!(status.signal(), Some(libc::SIGALRM));
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    Ok(())
+}
+
+#[test]
+fn hard_deadline_not_reached() -> Result<(), Box<dyn std::error::Error>> {
+    let status = StdCommand::new("true")
+        .hard_deadline(Duration::from_secs(10))
+        .spawn()?
+        .wait()?;
+
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn private_ipc() -> Result<(), Box<dyn std::error::Error>> {
+    let res = StdCommand::new("readlink")
+        .arg("/proc/self/ns/ipc")
+        .stdout(Stdio::piped())
+        .private_ipc()
+        .output();
+
+    let out = match res {
+        Ok(out) => out,
+        // creating a namespace requires privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    assert!(out.status.success());
+    let host = std::fs::read_link("/proc/self/ns/ipc")?;
+    assert_ne!(
+        String::from_utf8_lossy(&out.stdout).trim(),
+        host.to_string_lossy()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn add_tmpfs() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = tempdir::TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("host"), "")?;
+
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+        .add_read_write(tempdir.path());
+    let res = StdCommand::new("sh")
+        .arg("-c")
+        // `current_dir` would refer to the directory on the host, beneath the tmpfs
+        .arg(r#"cd "$0" && ls && echo hi > scratch && cat scratch"#)
+        .arg(tempdir.path())
+        .add_tmpfs(tempdir.path(), MemorySize::from_mib(1))
+        .restrict(Arc::new(rules))
+        .stdout(Stdio::piped())
+        .output();
+
+    let out = match res {
+        Ok(out) => out,
+        // mounting requires privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    assert!(out.status.success(), "{out:?}");
+    // the host's files are hidden, and the scratch file never reaches the host
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "hi\n");
+    assert!(!tempdir.path().join("scratch").exists());
+    assert!(tempdir.path().join("host").exists());
+
+    Ok(())
+}
+
+#[test]
+fn env_exact_std() -> Result<(), Box<dyn std::error::Error>> {
+    let out = StdCommand::new("/usr/bin/env")
+        .env("BEFORE", "1")
+        .env_exact([("LANG", "C"), ("PATH", "/usr/bin")])
+        .stdout(Stdio::piped())
+        .output()?;
+
+    assert!(out.status.success());
+    let mut vars: Vec<_> = String::from_utf8(out.stdout)?
+        .lines()
+        .map(String::from)
+        .collect();
+    vars.sort();
+    assert_eq!(vars, ["LANG=C", "PATH=/usr/bin"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn env_exact_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let out = TokioCommand::new("/usr/bin/env")
+        .env_exact([("LEUCITE", "exact")])
+        .stdout(Stdio::piped())
+        .output()
+        .await?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?, "LEUCITE=exact\n");
+
+    Ok(())
+}
+
+#[test]
+fn clear_env_except_std() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::var("PATH")?;
+    let out = StdCommand::new("/usr/bin/env")
+        .env("BEFORE", "1")
+        .clear_env_except(&["PATH", "LEUCITE_NOT_SET"])
+        .env("AFTER", "1")
+        .stdout(Stdio::piped())
+        .output()?;
+
+    assert!(out.status.success());
+    let mut vars: Vec<_> = String::from_utf8(out.stdout)?
+        .lines()
+        .map(String::from)
+        .collect();
+    vars.sort();
+    assert_eq!(vars, ["AFTER=1".to_string(), format!("PATH={path}")]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn clear_env_except_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::var("PATH")?;
+    let out = TokioCommand::new("/usr/bin/env")
+        .clear_env_except(&["PATH"])
+        .stdout(Stdio::piped())
+        .output()
+        .await?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?, format!("PATH={path}\n"));
+
+    Ok(())
+}
+
+#[test]
+fn max_cpu_bandwidth_shared_cgroup() {
+    // the command is still in the cgroup of the test, which must not be limited
+    let err = StdCommand::new("true")
+        .max_cpu_bandwidth(Duration::from_millis(50), Duration::from_millis(100))
+        .status()
+        .unwrap_err();
+
+    assert!(
+        matches!(err.raw_os_error(), Some(libc::EBUSY | libc::EOPNOTSUPP)),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn max_cpu_bandwidth() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::{fd::AsRawFd, unix::process::CommandExt as _};
+
+    let Some(mount) = std::fs::read_to_string("/proc/self/mountinfo")?
+        .lines()
+        .find(|l| l.contains(" - cgroup2 "))
+        .and_then(|l| l.split(' ').nth(4).map(String::from))
+    else {
+        // cgroup v2 is not mounted
+        return Ok(());
+    };
+    let own = std::fs::read_to_string("/proc/self/cgroup")?;
+    let own = own.lines().find_map(|l| l.strip_prefix("0::")).unwrap();
+    let cgroup = std::path::Path::new(&mount)
+        .join(own.trim_start_matches('/'))
+        .join(format!("leucite-test-{}", std::process::id()));
+
+    // the command must be moved into its own cgroup before the limit is set, since the limit
+    // always fails with `EBUSY` in the cgroup of the test
+    match std::fs::create_dir(&cgroup) {
+        Ok(()) => {}
+        // creating a cgroup requires a delegated hierarchy that the test may not have
+        Err(e)
+            if matches!(
+                e.raw_os_error(),
+                Some(libc::EACCES | libc::EPERM | libc::EROFS)
+            ) =>
+        {
+            return Ok(())
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    let procs = std::fs::OpenOptions::new()
+        .write(true)
+        .open(cgroup.join("cgroup.procs"))?;
+    let fd = procs.as_raw_fd();
+    let join = move || {
+        // SAFETY: `fd` is kept open by `procs` until the command has been spawned
+        match unsafe { libc::write(fd, b"0".as_ptr().cast(), 1) } {
+            1 => Ok(()),
+            _ => Err(std::io::Error::last_os_error()),
+        }
+    };
+
+    let status = unsafe { StdCommand::new("true").pre_exec(join) }
+        .max_cpu_bandwidth(Duration::from_millis(50), Duration::from_millis(100))
+        .status();
+    let cpu_max = std::fs::read_to_string(cgroup.join("cpu.max"));
+    std::fs::remove_dir(&cgroup)?;
+
+    match cpu_max {
+        Ok(cpu_max) => {
+            assert!(status?.success());
+            assert_eq!(cpu_max, "50000 100000\n");
+        }
+        // without the `cpu` controller, the command is moved but there is no limit to set
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            assert_eq!(status.unwrap_err().kind(), std::io::ErrorKind::NotFound);
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn drop_privileges() -> Result<(), Box<dyn std::error::Error>> {
+    let id = |args: &[&str], cmd: &mut StdCommand| -> std::io::Result<String> {
+        let out = cmd
+            .args(args)
+            .stdout(Stdio::piped())
+            .restrict(Arc::new(Rules::new().add_read_execute("/")))
+            .output()?;
+        assert!(out.status.success(), "{out:?}");
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+    let dropped = || {
+        let mut cmd = StdCommand::new("id");
+        cmd.set_gid(65534)
+            .supplementary_groups(&[1234])
+            .set_uid(65534);
+        cmd
+    };
+
+    assert_eq!(id(&["-u"], &mut dropped())?, "65534");
+    assert_eq!(id(&["-g"], &mut dropped())?, "65534");
+    assert_eq!(id(&["-G"], &mut dropped())?, "65534 1234");
+
+    // the group can not be changed once the user has been dropped
+    let err = StdCommand::new("true")
+        .set_uid(65534)
+        .set_gid(65534)
+        .status()
+        .unwrap_err();
+    assert_eq!(err.raw_os_error(), Some(libc::EPERM));
+
+    Ok(())
+}
+
+#[test]
+fn no_new_privs() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tempdir = tempdir::TempDir::new("leucite")?;
+    std::fs::set_permissions(tempdir.path(), std::fs::Permissions::from_mode(0o755))?;
+    std::fs::write(tempdir.path().join("test.c"), include_str!("./euid-test.c"))?;
+    let status = StdCommand::new("gcc")
+        .arg("-o")
+        .arg("test")
+        .arg("test.c")
+        .current_dir(tempdir.path())
+        .status()?;
+    assert!(status.success());
+    // the test runs as root, so the helper is owned by root
+    let helper = tempdir.path().join("test");
+    std::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o4755))?;
+
+    let euid = |no_new_privs: bool| -> std::io::Result<String> {
+        let mut cmd = StdCommand::new(&helper);
+        cmd.stdout(Stdio::piped()).set_gid(65534).set_uid(65534);
+        if no_new_privs {
+            cmd.no_new_privs();
+        }
+        let out = cmd.output()?;
+        assert!(out.status.success(), "{out:?}");
+        Ok(String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+
+    assert_eq!(euid(false)?, "0");
+    assert_eq!(euid(true)?, "65534");
+
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use std::process::Stdio;
+
+use leucite::{CheckOutcome, CommandExt, Enforcement, MemorySize, Rules, SandboxProfile};
+use std::process::Command as StdCommand;
+use tokio::process::Command as TokioCommand;
+
+fn profile() -> SandboxProfile {
+    SandboxProfile::new()
+        .restrict(
+            Rules::new()
+                .add_read_only("/usr")
+                .add_read_only("/etc")
+                .add_read_only("/dev")
+                .add_read_only("/bin")
+                .add_read_only("/lib")
+                .add_read_only("/lib64")
+                .add_read_only("/proc")
+                .into(),
+        )
+        .max_memory(MemorySize::from_mb(100))
+        .oom_score_adj(300)
+        .env_clear()
+        .env("LEUCITE", "sandboxed")
+}
+
+const SCRIPT: &str = r#"
+    echo "$LEUCITE"
+    cat /proc/self/oom_score_adj
+    cat /root/.bashrc /home/*/.bashrc 2>/dev/null && echo "read outside of sandbox"
+    true
+"#;
+
+#[tokio::test]
+async fn profile_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = TokioCommand::new("sh");
+    cmd.arg("-c").arg(SCRIPT).stdout(Stdio::piped());
+
+    let out = profile().wrap(&mut cmd).spawn()?.wait_with_output().await?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "sandboxed\n300\n");
+
+    Ok(())
+}
+
+#[test]
+fn profile_std() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = StdCommand::new("sh");
+    cmd.arg("-c").arg(SCRIPT).stdout(Stdio::piped());
+
+    let out = profile().wrap(&mut cmd).spawn()?.wait_with_output()?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout), "sandboxed\n300\n");
+
+    Ok(())
+}
+
+#[test]
+fn profile_phases() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = tempdir::TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("host"), "")?;
+
+    // The tmpfs is mounted before the rules are applied, so the rules can grant access to it
+    let profile = SandboxProfile::new()
+        .restrict(
+            Rules::new()
+                .add_read_only("/usr")
+                .add_read_only("/bin")
+                .add_read_only("/lib")
+                .add_read_only("/lib64")
+                .add_read_only("/proc")
+                .add_read_write(tempdir.path())
+                .into(),
+        )
+        .add_tmpfs(tempdir.path(), MemorySize::from_mib(1))
+        .private_ipc()
+        .max_cpu_time(30)
+        .nice(5);
+
+    let mut cmd = StdCommand::new("sh");
+    cmd.arg("-c")
+        .arg(r#"nice; ulimit -t; readlink /proc/self/ns/ipc; cd "$0" && touch scratch && ls"#)
+        .arg(tempdir.path())
+        .stdout(Stdio::piped());
+    let out = match profile.wrap(&mut cmd).output() {
+        Ok(out) => out,
+        // mounting and creating a namespace require privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    assert!(out.status.success(), "{out:?}");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let lines: Vec<_> = stdout.lines().collect();
+    let host = std::fs::read_link("/proc/self/ns/ipc")?;
+    assert_eq!(lines[..2], ["5", "30"]);
+    assert_ne!(lines[2], host.to_string_lossy());
+    assert_eq!(lines[3..], ["scratch"]);
+    assert!(!tempdir.path().join("scratch").exists());
+
+    Ok(())
+}
+
+#[test]
+fn profile_drop_privileges() -> Result<(), Box<dyn std::error::Error>> {
+    // The user is set first, but the groups are still changed before it
+    let profile = SandboxProfile::new()
+        .restrict(Rules::new().add_read_execute("/").into())
+        .set_uid(65534)
+        .supplementary_groups(&[1234])
+        .set_gid(65534);
+
+    let mut cmd = StdCommand::new("sh");
+    cmd.arg("-c")
+        .arg("id -u; id -g; id -G")
+        .stdout(Stdio::piped());
+    let out = profile.wrap(&mut cmd).output()?;
+
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "65534\n65534\n65534 1234\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn hook_phases() -> Result<(), Box<dyn std::error::Error>> {
+    use leucite::Phase;
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    // The hooks run in the child, so they can only report their order through the exit status.
+    // Each hook checks that the hooks before it have run, in the order of their phases.
+    static STEP: AtomicU8 = AtomicU8::new(0);
+    let step = |expected: u8| {
+        move || {
+            if STEP.fetch_add(1, Ordering::SeqCst) == expected {
+                Ok(())
+            } else {
+                Err(std::io::ErrorKind::Other.into())
+            }
+        }
+    };
+
+    // SAFETY: The hooks don't allocate or de-allocate memory
+    let profile = unsafe {
+        SandboxProfile::new()
+            .hook(Phase::Restrict, step(3))
+            .hook(Phase::DropPrivileges, step(2))
+            .hook(Phase::Privileged, step(0))
+            .hook(Phase::Limits, step(1))
+    };
+
+    let status = profile.wrap(&mut StdCommand::new("true")).status()?;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn hook_error() {
+    // SAFETY: The hook doesn't allocate or de-allocate memory
+    let profile = unsafe {
+        SandboxProfile::new().hook(leucite::Phase::Privileged, || {
+            Err(std::io::Error::from_raw_os_error(libc::EPERM))
+        })
+    };
+
+    let res = profile.wrap(&mut StdCommand::new("true")).spawn();
+    assert_eq!(
+        res.unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+}
+
+#[test]
+fn sandbox_audited() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = StdCommand::new("true");
+    let pending = cmd.sandbox_audited(&profile())?;
+    let mut child = cmd.spawn()?;
+    let audit = pending.finish(child.id())?;
+    assert!(child.wait()?.success());
+
+    // the expectations depend on the landlock ABI of the running kernel
+    let requested = Rules::new().abi() as i32;
+    let (enforcement, effective) = match leucite::landlock_abi() {
+        Some(abi) if abi as i32 >= requested => (Enforcement::FullyEnforced, requested),
+        Some(abi) => (Enforcement::PartiallyEnforced, abi as i32),
+        None => (Enforcement::NotEnforced, landlock::ABI::Unsupported as i32),
+    };
+    assert_eq!(audit.pid, child.id());
+    assert_eq!(audit.enforcement, Some(enforcement));
+    assert_eq!(audit.requested_abi, requested);
+    assert_eq!(audit.effective_abi, Some(effective));
+    assert_eq!(audit.oom_score_adj, Some(300));
+    assert_eq!(audit.exposure, profile().exposure_report());
+
+    let json = serde_json::to_value(&audit)?;
+    assert_eq!(json["enforcement"], "FullyEnforced");
+
+    // the enforcement status is only available once the command has been spawned
+    let err = StdCommand::new("true")
+        .sandbox_audited(&profile())?
+        .finish(0)
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sandbox_audited_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let profile = SandboxProfile::new().restrict(Rules::new().audit_only().into());
+
+    let mut cmd = TokioCommand::new("true");
+    let pending = cmd.sandbox_audited(&profile)?;
+    let mut child = cmd.spawn()?;
+    let audit = pending.finish(child.id().unwrap())?;
+    assert!(child.wait().await?.success());
+
+    assert_eq!(audit.enforcement, Some(Enforcement::NotEnforced));
+
+    let mut cmd = TokioCommand::new("true");
+    let pending = cmd.sandbox_audited(&SandboxProfile::new())?;
+    let mut child = cmd.spawn()?;
+    let audit = pending.finish(child.id().unwrap())?;
+    assert!(child.wait().await?.success());
+
+    assert_eq!(audit.enforcement, None);
+    assert_eq!(audit.effective_abi, None);
+
+    Ok(())
+}
+
+#[test]
+fn self_check() {
+    let profile = profile()
+        .hard_deadline(std::time::Duration::from_secs(3600))
+        .max_cpu_time(3600)
+        .private_ipc()
+        .detach_controlling_tty();
+
+    let report = leucite::self_check(&profile);
+    assert!(report.is_effective(), "{report}");
+    for (name, outcome) in report.checks() {
+        assert_eq!(*outcome, CheckOutcome::Effective, "{name}");
+    }
+
+    let report = leucite::self_check(&SandboxProfile::new());
+    for (name, outcome) in report.checks() {
+        assert_eq!(*outcome, CheckOutcome::Skipped, "{name}");
+    }
+
+    // rules which are not enforced are reported as such
+    let profile = SandboxProfile::new().restrict(Rules::new().audit_only().into());
+    let report = leucite::self_check(&profile);
+    assert_eq!(report.filesystem, CheckOutcome::NotEffective);
+    assert_eq!(report.network, CheckOutcome::NotEffective);
+    assert!(!report.is_effective());
+
+    // rules which can not be checked are not reported as effective
+    let profile = SandboxProfile::new().restrict(Rules::new().add_read_only("/").into());
+    let report = leucite::self_check(&profile);
+    assert_eq!(report.filesystem, CheckOutcome::Unverified);
+    assert_eq!(report.network, CheckOutcome::Effective);
+    assert!(!report.is_effective());
+
+    // probes which would fail regardless of the restriction are not reported as effective
+    let profile = SandboxProfile::new()
+        .restrict(Rules::new().into())
+        .chroot_to("/nonexistent")
+        .max_memory(u64::MAX / 2);
+    let report = leucite::self_check(&profile);
+    assert_eq!(report.filesystem, CheckOutcome::Unverified);
+    assert_eq!(report.max_memory, CheckOutcome::Unverified);
+}
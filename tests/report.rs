@@ -0,0 +1,150 @@
+use std::{path::PathBuf, time::Duration};
+
+use landlock::{AccessFs, ABI};
+use leucite::{FsType, MemorySize, Rules, SandboxProfile};
+
+#[test]
+fn rules_exposure() {
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_write("/tmp/work")
+        .add_write_only("/tmp/out")
+        .add_connect_port(443)
+        .add_connect_port(80)
+        .deny_filesystem_types(&[FsType::NFS]);
+
+    let report = rules.exposure_report();
+
+    assert_eq!(report.readable, ["/usr", "/tmp/work"].map(PathBuf::from));
+    assert_eq!(
+        report.writable,
+        ["/tmp/out", "/tmp/work"].map(PathBuf::from)
+    );
+    assert_eq!(report.executable, ["/usr", "/tmp/work"].map(PathBuf::from));
+    assert_eq!(report.connect_ports, Some(vec![80, 443]));
+    assert_eq!(report.bind_ports, Some(vec![]));
+    assert_eq!(report.denied_filesystems, [FsType::NFS]);
+    assert!(report.limits.is_empty());
+}
+
+#[test]
+fn rules_exposure_lower_abi() {
+    let rules = Rules::new()
+        .add_read_write("/tmp/work")
+        .add_connect_port(443)
+        .with_abi(ABI::V3);
+
+    // TCP ports and rights of later ABIs are not restricted, whatever the rules add
+    let report = rules.exposure_report();
+    assert_eq!(report.connect_ports, None);
+    assert_eq!(report.bind_ports, None);
+    assert!(report.unrestricted_access.contains(AccessFs::IoctlDev));
+    assert!(!report.unrestricted_access.contains(AccessFs::WriteFile));
+
+    let report = rules.with_abi(ABI::V1).exposure_report();
+    assert!(report
+        .unrestricted_access
+        .contains(AccessFs::Truncate | AccessFs::Refer));
+
+    let out = report.to_string();
+    assert!(out.contains("unrestricted rights: "), "{out}");
+    assert!(out.contains("truncate"), "{out}");
+    assert!(out.contains("connect ports: any\n"), "{out}");
+}
+
+#[test]
+fn rules_describe() {
+    let rules = Rules::new()
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+        .add_read_only("/usr")
+        .add_read_write("/tmp/work")
+        .add_path_access("/var/log", AccessFs::WriteFile)
+        .allow_tmp_execution(false)
+        .add_connect_port(443)
+        .with_abi(ABI::V3);
+
+    let description = rules.describe();
+
+    assert!(description.enforced);
+    assert_eq!(description.read_only, [PathBuf::from("/usr")]);
+    assert_eq!(description.read_write, [PathBuf::from("/tmp/work")]);
+    assert!(description.write_only.is_empty());
+    assert_eq!(
+        description.path_access,
+        [(PathBuf::from("/var/log"), AccessFs::WriteFile.into())]
+    );
+    assert_eq!(
+        description.read_access,
+        AccessFs::ReadFile | AccessFs::ReadDir
+    );
+    assert_eq!(description.write_access, AccessFs::from_write(ABI::V3));
+    // Ports can't be restricted with ABI 3
+    assert_eq!(description.connect_ports, None);
+    assert_eq!(description.requested_abi, 3);
+    assert!(description.effective_abi <= 3);
+    assert!(description.kernel_abi.unwrap_or(0) >= description.effective_abi);
+
+    let text = description.to_string();
+    assert!(text.contains("read only:\n  /usr\n"), "{text}");
+    assert!(
+        text.contains("custom access:\n  /var/log: write_file\n"),
+        "{text}"
+    );
+    assert!(text.contains("connect ports: any\n"), "{text}");
+
+    assert!(!Rules::new().audit_only().describe().enforced);
+}
+
+#[test]
+fn unrestricted_ports() {
+    let report = Rules::new().client_only().exposure_report();
+
+    assert_eq!(report.connect_ports, None);
+    assert_eq!(report.bind_ports, Some(vec![]));
+}
+
+#[test]
+fn profile_exposure() {
+    let profile = SandboxProfile::new()
+        .max_memory(MemorySize::from_mb(100))
+        .max_threads(4)
+        .max_cpu_time(10)
+        .hard_deadline(Duration::from_secs(2));
+
+    let report = profile.exposure_report();
+
+    assert_eq!(report.readable, ["/"].map(PathBuf::from));
+    assert_eq!(report.connect_ports, None);
+    assert_eq!(report.limits["max_memory"], 100_000_000);
+    assert_eq!(report.limits["max_threads"], 4);
+    assert_eq!(report.limits["max_cpu_time"], 10);
+    assert_eq!(report.limits["hard_deadline"], 2_000_000);
+    assert!(!report.limits.contains_key("max_file_size"));
+}
+
+#[test]
+fn display() {
+    let report = Rules::new()
+        .add_read_only("/usr")
+        .add_connect_port(443)
+        .exposure_report();
+
+    let out = report.to_string();
+    assert!(out.contains("readable:\n  /usr\n"), "{out}");
+    assert!(out.contains("connect ports: 443\n"), "{out}");
+}
+
+#[test]
+fn serialize() -> Result<(), serde_json::Error> {
+    let report = Rules::new()
+        .add_read_only("/usr")
+        .deny_filesystem_types(&[FsType::FUSE])
+        .exposure_report();
+
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(json["readable"], serde_json::json!(["/usr"]));
+    assert_eq!(json["denied_filesystems"], serde_json::json!([0x65735546]));
+    assert_eq!(json["connect_ports"], serde_json::json!([]));
+
+    Ok(())
+}
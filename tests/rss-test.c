@@ -0,0 +1,16 @@
+#include <stdlib.h>
+#include <string.h>
+#include <unistd.h>
+
+int main(void) {
+    // Slowly allocate (and touch) up to 500MB of memory
+    for (int i = 0; i < 500; ++i) {
+        char *data = malloc(1000 * 1000);
+        if (data == NULL) {
+            return 1;
+        }
+        memset(data, 0x45, 1000 * 1000);
+        usleep(5 * 1000);
+    }
+    return 0;
+}
@@ -0,0 +1,82 @@
+use std::{process::Command as StdCommand, time::Duration};
+
+use leucite::{MemorySize, WaitOutcome};
+use tempdir::TempDir;
+use tmpdir::TmpDir;
+use tokio::process::Command as TokioCommand;
+
+#[tokio::test]
+async fn rss_limit_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TmpDir::new("leucite").await?;
+
+    let mut soln = tempdir.to_path_buf();
+    soln.push("test.c");
+    tokio::fs::write(soln, include_str!("./rss-test.c")).await?;
+
+    let status = TokioCommand::new("gcc")
+        .arg("-o")
+        .arg("test")
+        .arg("test.c")
+        .current_dir(&tempdir)
+        .spawn()?
+        .wait()
+        .await?;
+    assert!(status.success());
+
+    let mut child = TokioCommand::new("./test").current_dir(&tempdir).spawn()?;
+    let limit = MemorySize::from_mb(20);
+    let outcome =
+        leucite::tokio::wait_with_rss_limit(&mut child, limit, Duration::from_millis(10)).await?;
+
+    assert!(
+        matches!(outcome, WaitOutcome::MemoryLimitExceeded(rss) if rss > limit),
+        "{outcome:?}"
+    );
+
+    tempdir.close().await?;
+
+    Ok(())
+}
+
+#[test]
+fn rss_limit_std() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+
+    let mut soln = tempdir.path().to_path_buf();
+    soln.push("test.c");
+    std::fs::write(soln, include_str!("./rss-test.c"))?;
+
+    let status = StdCommand::new("gcc")
+        .arg("-o")
+        .arg("test")
+        .arg("test.c")
+        .current_dir(&tempdir)
+        .spawn()?
+        .wait()?;
+    assert!(status.success());
+
+    let mut child = StdCommand::new("./test").current_dir(&tempdir).spawn()?;
+    let limit = MemorySize::from_mb(20);
+    let outcome = leucite::wait_with_rss_limit(&mut child, limit, Duration::from_millis(10))?;
+
+    assert!(
+        matches!(outcome, WaitOutcome::MemoryLimitExceeded(rss) if rss > limit),
+        "{outcome:?}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rss_limit_not_exceeded() -> Result<(), Box<dyn std::error::Error>> {
+    let mut child = StdCommand::new("true").spawn()?;
+    let outcome = leucite::wait_with_rss_limit(
+        &mut child,
+        MemorySize::from_mb(20),
+        Duration::from_millis(10),
+    )?;
+
+    assert!(matches!(outcome, WaitOutcome::Exited(status) if status.success()));
+
+    Ok(())
+}
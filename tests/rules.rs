@@ -0,0 +1,764 @@
+use std::{path::Path, process::Command as StdCommand, sync::Arc};
+
+use landlock::AccessFs;
+use leucite::{AccessMode, CommandExt, Error, FsType, PermissivePolicy, Restricted, Rules};
+use tempdir::TempDir;
+
+#[test]
+fn try_add_existing() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+
+    let rules = Rules::new()
+        .try_add_read_only("/usr")?
+        .try_add_read_write(tempdir.path())?
+        .try_add_write_only(tempdir.path())?;
+
+    assert_eq!(
+        rules,
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_write(tempdir.path())
+            .add_write_only(tempdir.path())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn try_add_missing() {
+    let err = Rules::new()
+        .try_add_read_only("/usr")
+        .and_then(|r| r.try_add_read_only("/this/path/does/not/exist"))
+        .unwrap_err();
+
+    assert!(
+        matches!(&err, Error::InvalidPath { path, .. } if path.as_os_str() == "/this/path/does/not/exist"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn try_add_shallow() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let file = tempdir.path().join("file");
+    std::fs::write(&file, "")?;
+
+    let rules = Rules::new().try_add_read_only_shallow(&file)?;
+    assert_eq!(rules, Rules::new().add_read_only(&file));
+
+    let err = Rules::new()
+        .try_add_read_only_shallow(tempdir.path())
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::RecursiveDirectory { path } if path == tempdir.path()),
+        "{err:?}"
+    );
+    let err = Rules::new()
+        .try_add_read_only_shallow(tempdir.path().join("missing"))
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidPath { .. }), "{err:?}");
+
+    Ok(())
+}
+
+#[test]
+fn builder() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let target = tempdir.path().canonicalize()?.join("target");
+    std::fs::create_dir(&target)?;
+    let link = tempdir.path().join("link");
+    std::os::unix::fs::symlink(&target, &link)?;
+
+    let rules = Rules::builder()
+        .add_read_only("/usr/../usr")
+        .add_read_write(&link)
+        .add_connect_port(443)
+        .build()?;
+    assert_eq!(
+        rules,
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_write(&target)
+            .add_connect_port(443)
+    );
+
+    let missing = tempdir.path().join("missing");
+    let err = Rules::builder()
+        .add_read_only("/usr")
+        .add_read_only(&missing)
+        .build()
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::Canonicalize { path, .. } if *path == missing),
+        "{err:?}"
+    );
+
+    let err = Rules::builder()
+        .add_read_only(&target)
+        .deny_filesystem_types(&[FsType::of(&target)?])
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::DeniedFilesystem { .. }), "{err:?}");
+
+    let err = Rules::builder()
+        .add_read_write("/")
+        .allow_all_network()
+        .on_fully_permissive(PermissivePolicy::Error)
+        .build()
+        .unwrap_err();
+    assert!(matches!(err, Error::FullyPermissive), "{err:?}");
+
+    Ok(())
+}
+
+#[test]
+fn validate() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let missing = tempdir.path().join("missing");
+
+    assert_eq!(
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_write(tempdir.path())
+            .validate(),
+        Ok(())
+    );
+    assert_eq!(
+        Rules::new()
+            .add_read_only("/usr")
+            .add_read_only("/tpy")
+            .add_read_write(&missing)
+            .add_write_only(&missing)
+            .validate(),
+        Err(vec!["/tpy".into(), missing.clone()])
+    );
+
+    let rules = Rules::new().add_read_only("/usr").add_read_only(&missing);
+    let res = std::thread::spawn(move || unsafe { rules.restrict_strict() })
+        .join()
+        .unwrap();
+    assert!(
+        matches!(&res, Err(Error::InvalidPath { path, .. }) if *path == missing),
+        "{res:?}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn effective_abi() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Rules::new().add_read_only("/");
+    let expected = rules.effective_abi() as i32;
+    assert!((1..=4).contains(&expected));
+
+    // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+    let status = std::thread::spawn(move || unsafe { rules.restrict_self_with_status() })
+        .join()
+        .unwrap()?;
+
+    assert_eq!(status.effective_abi as i32, expected);
+
+    Ok(())
+}
+
+#[test]
+fn landlock_abi() {
+    let abi = leucite::landlock_abi().expect("the tests require landlock");
+    assert!(abi as i32 >= 1);
+    assert_eq!(
+        Rules::new().with_abi(abi).effective_abi() as i32,
+        abi as i32
+    );
+}
+
+#[test]
+fn deny_filesystem_types() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let fs_type = FsType::of(tempdir.path())?;
+
+    let rules = Rules::new()
+        .add_read_write(tempdir.path())
+        .deny_filesystem_types(&[fs_type]);
+
+    let err = rules.check_filesystem_types().unwrap_err();
+    assert!(
+        matches!(&err, Error::DeniedFilesystem { path, fs_type: t } if path == tempdir.path() && *t == fs_type),
+        "{err:?}"
+    );
+
+    let res = StdCommand::new("true").restrict(Arc::new(rules)).status();
+    assert!(res.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn deny_network_filesystems() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_write(tempdir.path())
+        .deny_filesystem_types(FsType::NETWORK);
+
+    rules.check_filesystem_types()?;
+    let status = StdCommand::new("true").restrict(Arc::new(rules)).status()?;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn from_open_files_of() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let path = tempdir.path().join("open.txt");
+    let _file = std::fs::File::create(&path)?;
+
+    let rules = Rules::from_open_files_of(std::process::id())?;
+
+    let debug = format!("{rules:?}");
+    assert!(debug.contains(path.to_str().unwrap()), "{debug}");
+    assert!(!debug.contains("socket:"), "{debug}");
+
+    Ok(())
+}
+
+#[test]
+fn from_command_args() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("a.txt"), "")?;
+    std::fs::write(tempdir.path().join("b.txt"), "")?;
+
+    let mut cmd = StdCommand::new("gzip");
+    cmd.current_dir(tempdir.path())
+        .args(["-k", "a.txt", "b.txt", "missing.txt", "a.txt"]);
+
+    let rules = Rules::from_command_args(&cmd, AccessMode::ReadWrite, |p| p != Path::new("b.txt"));
+
+    let report = rules.exposure_report();
+    let expected = [tempdir.path().canonicalize()?.join("a.txt")];
+    assert_eq!(report.readable, expected);
+    assert_eq!(report.writable, expected);
+
+    Ok(())
+}
+
+#[test]
+fn audit_only() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Rules::new().add_read_only("/usr").audit_only();
+    assert!(rules.is_audit_only());
+
+    // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+    let (status, read) = std::thread::spawn(move || {
+        let status = unsafe { rules.restrict_self_with_status() };
+        (status, std::fs::read("/etc/hostname").map(|_| ()))
+    })
+    .join()
+    .unwrap();
+
+    assert_eq!(status?.ruleset, landlock::RulesetStatus::NotEnforced);
+    // nothing was enforced, so paths outside of the rules are still accessible
+    read?;
+
+    Ok(())
+}
+
+#[test]
+fn missing_access() {
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_write("/tmp/work")
+        .add_write_only("/tmp/work/out");
+
+    assert!(rules
+        .missing_access("/usr/lib/libc.so", AccessFs::ReadFile)
+        .is_empty());
+    assert!(rules
+        .missing_access("/usr", AccessFs::from_read(landlock::ABI::V4))
+        .is_empty());
+    assert_eq!(
+        rules.missing_access("/usr/lib", AccessFs::ReadFile | AccessFs::WriteFile),
+        AccessFs::WriteFile
+    );
+    assert_eq!(
+        rules.missing_access("/etc/passwd", AccessFs::ReadFile),
+        AccessFs::ReadFile
+    );
+    assert!(rules
+        .missing_access("/tmp/work/out/x", AccessFs::ReadFile)
+        .is_empty());
+    assert_eq!(
+        rules.missing_access("/usr/../etc", AccessFs::ReadDir),
+        AccessFs::ReadDir
+    );
+}
+
+#[test]
+fn remove_entries() {
+    let base = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/srv/shared")
+        .add_read_write("/tmp/work")
+        .add_write_only("/tmp/out")
+        .add_bind_port(8080)
+        .add_bind_port(8443)
+        .add_connect_port(443);
+
+    let narrowed = base
+        .clone()
+        .remove_read_only("/srv/shared")
+        .remove_read_write("/tmp/work")
+        .remove_write_only("/tmp/out")
+        .remove_bind_port(8080)
+        .remove_connect_port(443);
+    assert_eq!(
+        narrowed,
+        Rules::new().add_read_only("/usr").add_bind_port(8443)
+    );
+    assert_eq!(
+        narrowed.missing_access("/srv/shared/file", AccessFs::ReadFile),
+        AccessFs::ReadFile
+    );
+
+    // Only exact matches are removed
+    assert_eq!(base.clone().remove_read_only("/usr/lib"), base);
+    assert_eq!(base.clone().remove_read_write("/usr"), base);
+    assert_eq!(base.clone().remove_bind_port(443), base);
+
+    let client = Rules::new().client_only();
+    assert_eq!(client.clone().remove_connect_port(443), client);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Rules::new().to_string(), "connect:[] bind:[]");
+
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_execute("/bin")
+        .add_read_write("/tmp/foo")
+        .add_write_only("/var/log")
+        .add_path_access("/srv", AccessFs::ReadFile | AccessFs::ReadDir)
+        .add_connect_port(80)
+        .add_connect_port(443)
+        .allow_all_bind()
+        .audit_only();
+    assert_eq!(
+        rules.to_string(),
+        "ro:/usr ro:/etc rx:/bin rw:/tmp/foo wo:/var/log read_file,read_dir:/srv \
+         connect:[80,443] bind:[*] audit_only"
+    );
+}
+
+#[test]
+fn accessors() {
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_execute("/opt/bin")
+        .add_read_write("/tmp/work")
+        .add_write_only("/tmp/out")
+        .add_path_access("/dev/null", AccessFs::WriteFile)
+        .add_bind_port(8080)
+        .add_connect_port(443);
+
+    assert_eq!(
+        rules.read_only_paths(),
+        [Path::new("/usr"), Path::new("/etc")]
+    );
+    assert_eq!(rules.read_execute_paths(), [Path::new("/opt/bin")]);
+    assert_eq!(rules.read_write_paths(), [Path::new("/tmp/work")]);
+    assert_eq!(rules.write_only_paths(), [Path::new("/tmp/out")]);
+    assert_eq!(
+        rules.path_access(),
+        [("/dev/null".into(), AccessFs::WriteFile.into())]
+    );
+    assert_eq!(rules.bind_ports(), Some(&[8080][..]));
+    assert_eq!(rules.connect_ports(), Some(&[443][..]));
+
+    let client = rules.client_only();
+    assert_eq!(client.bind_ports(), Some(&[][..]));
+    assert_eq!(client.connect_ports(), None);
+}
+
+#[test]
+fn normalize() {
+    let a = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_path_access("/dev/null", AccessFs::ReadFile)
+        .add_path_access("/dev/null", AccessFs::WriteFile)
+        .add_bind_port(8443)
+        .add_bind_port(8080)
+        .add_bind_port(8443)
+        .deny_filesystem_types(FsType::NETWORK);
+    let b = Rules::new()
+        .add_read_only("/etc")
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_path_access("/dev/null", AccessFs::ReadFile | AccessFs::WriteFile)
+        .add_bind_port(8080)
+        .add_bind_port(8443)
+        .deny_filesystem_types(&FsType::NETWORK.iter().rev().copied().collect::<Vec<_>>());
+
+    assert_ne!(a, b);
+    assert_eq!(a.clone().normalize(), b.clone().normalize());
+
+    let mut cache = std::collections::HashMap::new();
+    cache.insert(a.normalize(), "compiled");
+    assert_eq!(cache.get(&b.normalize()), Some(&"compiled"));
+
+    // Different grants still differ
+    assert_ne!(
+        Rules::new().add_read_only("/usr").normalize(),
+        Rules::new().add_read_write("/usr").normalize()
+    );
+}
+
+#[test]
+fn intersect() {
+    // read/write intersected with read-only is read-only, and a nested path keeps the rights that
+    // it inherits from the other rules
+    let baseline = Rules::new()
+        .add_read_write("/srv")
+        .add_read_write("/var/log")
+        .add_read_only("/usr")
+        .add_connect_port(443)
+        .add_connect_port(80)
+        .allow_all_bind();
+    let task = Rules::new()
+        .add_read_only("/srv")
+        .add_read_only("/usr/lib")
+        .add_write_only("/var/log/task")
+        .add_read_write("/tmp")
+        .add_connect_port(443)
+        .add_bind_port(8080);
+
+    let rules = baseline.clone().intersect(task.clone());
+    let expected = Rules::new()
+        .add_read_only("/srv")
+        .add_read_only("/usr/lib")
+        .add_write_only("/var/log/task")
+        .add_connect_port(443)
+        .add_bind_port(8080)
+        .normalize();
+    assert_eq!(rules, expected);
+    assert_eq!(task.clone().intersect(baseline.clone()), expected);
+    assert!(rules.is_subset_of(&baseline));
+    assert!(rules.is_subset_of(&task));
+
+    // read-only intersected with write-only grants nothing
+    let rules = Rules::new()
+        .add_read_only("/data")
+        .intersect(Rules::new().add_write_only("/data"));
+    assert_eq!(rules, Rules::new());
+
+    // rights which are not among the named modes are kept as path access
+    let rules = Rules::new()
+        .add_read_execute("/opt")
+        .intersect(Rules::new().add_path_access("/opt", AccessFs::ReadFile | AccessFs::WriteFile));
+    assert_eq!(
+        rules.path_access(),
+        [("/opt".into(), AccessFs::ReadFile.into())]
+    );
+    assert!(rules.read_execute_paths().is_empty());
+
+    // the stricter of the other settings is kept
+    let rules = Rules::new()
+        .audit_only()
+        .deny_filesystem_types(&[FsType::NFS])
+        .intersect(Rules::new().allow_tmp_execution(false).scope_signals());
+    assert!(!rules.is_audit_only());
+    assert!(!rules.allows_tmp_execution());
+    assert!(rules.is_subset_of(&Rules::new().deny_filesystem_types(&[FsType::NFS])));
+    assert!(rules.is_subset_of(&Rules::new().scope_signals()));
+}
+
+#[test]
+fn canonicalize() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let target = tempdir.path().canonicalize()?.join("target");
+    std::fs::create_dir(&target)?;
+    let link = tempdir.path().join("link");
+    std::os::unix::fs::symlink(&target, &link)?;
+
+    let rules = Rules::new()
+        .add_read_only("/usr/../usr")
+        .add_read_write(&link)
+        .add_path_access(link.join("."), AccessFs::ReadDir)
+        .canonicalize()?;
+    assert_eq!(rules.read_only_paths(), [Path::new("/usr")]);
+    assert_eq!(rules.read_write_paths(), [target.as_path()]);
+    assert_eq!(rules.path_access()[0].0, target);
+
+    let missing = tempdir.path().join("missing");
+    let err = Rules::new()
+        .add_read_only(&missing)
+        .canonicalize()
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert!(
+        err.to_string().contains(&*missing.to_string_lossy()),
+        "{err}"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn fully_permissive() -> Result<(), Box<dyn std::error::Error>> {
+    let permissive = || Rules::new().add_read_write("/").allow_all_network();
+    assert!(permissive().is_fully_permissive());
+    // the rights may be combined from several grants
+    assert!(Rules::new()
+        .add_read_only("/")
+        .add_write_only("/")
+        .allow_all_network()
+        .is_fully_permissive());
+
+    assert!(!Rules::new().add_read_write("/").is_fully_permissive());
+    assert!(!Rules::new()
+        .add_read_write("/")
+        .client_only()
+        .is_fully_permissive());
+    assert!(!Rules::new()
+        .add_read_only("/")
+        .allow_all_network()
+        .is_fully_permissive());
+    assert!(!Rules::new()
+        .add_read_write("/usr")
+        .allow_all_network()
+        .is_fully_permissive());
+    assert!(!permissive()
+        .deny_filesystem_types(&[FsType::NFS])
+        .is_fully_permissive());
+    // rights which are handled, but excluded from the grants by an override, are not granted
+    assert!(!permissive()
+        .read_access_bits(AccessFs::ReadFile)
+        .is_fully_permissive());
+
+    let restrict = |rules: Rules| {
+        // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+        std::thread::spawn(move || unsafe { rules.restrict_self_with_status() })
+            .join()
+            .unwrap()
+    };
+
+    let status = restrict(permissive().on_fully_permissive(PermissivePolicy::Skip))?;
+    assert_eq!(status.ruleset, landlock::RulesetStatus::NotEnforced);
+    let res = restrict(permissive().on_fully_permissive(PermissivePolicy::Error));
+    assert!(matches!(res, Err(Error::FullyPermissive)));
+    let status = restrict(permissive())?;
+    assert_ne!(status.ruleset, landlock::RulesetStatus::NotEnforced);
+
+    // rules which are not fully permissive are enforced
+    let rules = Rules::new()
+        .add_read_only("/")
+        .on_fully_permissive(PermissivePolicy::Error);
+    assert_ne!(
+        restrict(rules)?.ruleset,
+        landlock::RulesetStatus::NotEnforced
+    );
+
+    Ok(())
+}
+
+#[test]
+fn with_abi() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    std::fs::write(tempdir.path().join("file"), "")?;
+
+    // find a port which is free, and which the rules don't allow binding to
+    let port = std::net::TcpListener::bind("127.0.0.1:0")?
+        .local_addr()?
+        .port();
+
+    let restrict = |rules: Rules| {
+        let file = tempdir.path().join("file");
+        // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+        std::thread::spawn(move || {
+            let status = unsafe { rules.restrict_self_with_status() }.unwrap();
+            let read = std::fs::read(file).map(|_| ());
+            let bind = std::net::TcpListener::bind(("127.0.0.1", port)).map(|_| ());
+            (status, read, bind)
+        })
+        .join()
+        .unwrap()
+    };
+
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .with_abi(landlock::ABI::V1);
+    assert_eq!(rules.abi() as i32, 1);
+    assert_eq!(rules.effective_abi() as i32, 1);
+    assert_eq!(rules.exposure_report().bind_ports, None);
+    let (status, read, bind) = restrict(rules);
+    assert_eq!(status.effective_abi as i32, 1);
+    // the filesystem rules are still enforced, but network access is not restricted
+    assert_eq!(
+        read.unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+    bind?;
+
+    // by default, binding is denied
+    let (_, _, bind) = restrict(Rules::new().add_read_only("/usr"));
+    assert_eq!(
+        bind.unwrap_err().kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    // a lower ABI restricts less
+    let v1 = Rules::new().with_abi(landlock::ABI::V1);
+    assert!(!v1.clone().is_subset_of(&Rules::new()));
+    assert!(Rules::new().is_subset_of(&v1));
+    // but merging never lowers the ABI of either of the rules
+    assert_eq!(Rules::new().merge_beneath(v1.clone()).abi() as i32, 4);
+    assert_eq!(
+        v1.clone()
+            .merge(Rules::new().with_abi(landlock::ABI::V2))
+            .abi() as i32,
+        2
+    );
+    assert_eq!(v1.clone().merge(v1).abi() as i32, 1);
+
+    Ok(())
+}
+
+#[test]
+fn merge_keeps_access_of_each_rules() {
+    // the base does not allow executing anything it grants read access to
+    let base = Rules::new()
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+        .add_read_only("/srv");
+    let task = Rules::new().add_read_only("/usr");
+
+    let rules = base.clone().merge_beneath(task.clone());
+    assert_eq!(
+        rules.missing_access("/srv/a.out", AccessFs::Execute),
+        AccessFs::Execute
+    );
+    assert!(rules
+        .missing_access("/usr/bin/ls", AccessFs::Execute)
+        .is_empty());
+
+    let rules = task.merge_beneath(base);
+    assert_eq!(
+        rules.missing_access("/srv/a.out", AccessFs::Execute),
+        AccessFs::Execute
+    );
+    assert!(rules
+        .missing_access("/usr/bin/ls", AccessFs::Execute)
+        .is_empty());
+
+    // rights which are not handled by the lower ABI are only granted where they are added
+    let v1 = Rules::new()
+        .with_abi(landlock::ABI::V1)
+        .add_read_write("/tmp/v1");
+    let rules = Rules::new().add_read_write("/tmp/v4").merge_beneath(v1);
+    assert_eq!(
+        rules.missing_access("/tmp/v1/file", AccessFs::Truncate),
+        AccessFs::Truncate
+    );
+    assert!(rules
+        .missing_access("/tmp/v4/file", AccessFs::Truncate)
+        .is_empty());
+}
+
+#[test]
+fn restrict_self_guarded() -> Result<(), Box<dyn std::error::Error>> {
+    fn read_after_restricting(_proof: &Restricted, path: &Path) -> bool {
+        std::fs::read(path).is_ok()
+    }
+
+    let dir = TempDir::new("leucite")?;
+    let file = dir.path().join("file");
+    std::fs::write(&file, "")?;
+
+    let rules = Rules::new().add_read_only("/usr");
+    // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+    let read = std::thread::spawn(move || {
+        let proof = unsafe { rules.restrict_self_guarded() }?;
+        Ok::<_, Error>(read_after_restricting(&proof, &file))
+    })
+    .join()
+    .unwrap()?;
+    assert!(!read);
+
+    Ok(())
+}
+
+#[test]
+fn restrict_best_effort() -> Result<(), Box<dyn std::error::Error>> {
+    let rules = Rules::new().add_read_only("/usr").add_read_only("/lib");
+
+    // Landlock only restricts the calling thread, so restrict a new thread rather than the test
+    let status = {
+        let rules = rules.clone();
+        std::thread::spawn(move || unsafe { rules.restrict_best_effort() })
+            .join()
+            .unwrap()?
+    };
+    assert_ne!(status.ruleset, landlock::RulesetStatus::NotEnforced);
+
+    let res = StdCommand::new("/usr/bin/cat")
+        .arg("/etc/hostname")
+        .stderr(std::process::Stdio::null())
+        .restrict_best_effort(Arc::new(rules.add_read_only("/lib64")))
+        .status();
+    assert!(!res.is_ok_and(|s| s.success()));
+
+    Ok(())
+}
+
+#[test]
+fn serde() -> Result<(), Box<dyn std::error::Error>> {
+    let rules: Rules = serde_json::from_str(
+        r#"{
+            "read_only": ["/usr", "/etc"],
+            "read_write": ["/tmp/work"],
+            "bind_ports": "none",
+            "connect_ports": [443],
+            "read_access": ["read_file", "read_dir"],
+            "permissive_policy": "error"
+        }"#,
+    )?;
+    let expected = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/etc")
+        .add_read_write("/tmp/work")
+        .server_only([])
+        .add_connect_port(443)
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir)
+        .on_fully_permissive(PermissivePolicy::Error);
+    assert_eq!(rules, expected);
+
+    let json = serde_json::to_value(&rules)?;
+    assert_eq!(json["read_only"], serde_json::json!(["/usr", "/etc"]));
+    assert_eq!(json["bind_ports"], "none");
+    assert_eq!(json["write_access"], serde_json::Value::Null);
+    assert_eq!(serde_json::from_value::<Rules>(json)?, rules);
+
+    let appendable =
+        Rules::new().add_path_access("/var/log", AccessFs::WriteFile | AccessFs::MakeReg);
+    let json = serde_json::to_value(&appendable)?;
+    assert_eq!(
+        json["path_access"],
+        serde_json::json!([{ "path": "/var/log", "access": ["write_file", "make_reg"] }])
+    );
+    assert_eq!(serde_json::from_value::<Rules>(json)?, appendable);
+
+    let any = Rules::new().allow_all_network().with_abi(landlock::ABI::V2);
+    assert_eq!(
+        serde_json::from_str::<Rules>(&serde_json::to_string(&any)?)?,
+        any
+    );
+
+    assert_eq!(serde_json::from_str::<Rules>("{}")?, Rules::new());
+    assert!(serde_json::from_str::<Rules>(r#"{ "read_onyl": ["/"] }"#).is_err());
+    assert!(serde_json::from_str::<Rules>(r#"{ "read_access": ["read_everything"] }"#).is_err());
+
+    Ok(())
+}
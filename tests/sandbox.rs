@@ -0,0 +1,180 @@
+use std::{process::Command as StdCommand, process::Stdio};
+
+use leucite::{LimitValue, MemorySize, ResourceLimits, Rules, Sandbox};
+use tokio::process::Command as TokioCommand;
+
+fn sandbox() -> Sandbox {
+    Sandbox::new(
+        Rules::system_readonly().into(),
+        ResourceLimits::new().max_open_files(16),
+    )
+}
+
+#[test]
+fn sandbox_std() -> Result<(), Box<dyn std::error::Error>> {
+    let out = sandbox()
+        .spawn(
+            StdCommand::new("sh")
+                .arg("-c")
+                .arg("ulimit -n; cat /etc/hostname > /dev/null")
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null()),
+        )?
+        .wait_with_output()?;
+
+    // writing to `/dev/null` is denied by the rules
+    assert!(!out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "16");
+
+    let status = sandbox()
+        .apply(&mut StdCommand::new("cat"))
+        .arg("/etc/hostname")
+        .stdout(Stdio::null())
+        .status()?;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn sandbox_apply_and_spawn() -> Result<(), Box<dyn std::error::Error>> {
+    // Both ways of applying the sandbox apply every limit, including those which are not set by a
+    // single `CommandExt` method
+    let sandbox = Sandbox::new(
+        Rules::new().add_read_execute("/").into(),
+        ResourceLimits::new()
+            .max_file_size_soft_hard(MemorySize::from_kib(1), MemorySize::from_kib(2))
+            .max_pending_signals(16)
+            .max_locked_memory(MemorySize::from_kib(64)),
+    );
+    // the soft and hard limits, in the order of `/proc/self/limits`
+    let script = r#"awk '/^Max (file size|locked memory|pending signals)/ { print $(NF-2), $(NF-1) }' /proc/self/limits"#;
+    let expected = "1024 2048\n65536 65536\n16 16\n";
+
+    let out = sandbox
+        .spawn(
+            StdCommand::new("sh")
+                .arg("-c")
+                .arg(script)
+                .stdout(Stdio::piped()),
+        )?
+        .wait_with_output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8(out.stdout)?, expected);
+
+    let out = sandbox
+        .apply(StdCommand::new("sh").arg("-c").arg(script))
+        .output()?;
+    assert!(out.status.success(), "{out:?}");
+    assert_eq!(String::from_utf8(out.stdout)?, expected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sandbox_tokio() -> Result<(), Box<dyn std::error::Error>> {
+    let out = sandbox()
+        .spawn(
+            TokioCommand::new("sh")
+                .arg("-c")
+                .arg("ulimit -n")
+                .stdout(Stdio::piped()),
+        )?
+        .wait_with_output()
+        .await?;
+
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?.trim(), "16");
+
+    Ok(())
+}
+
+#[test]
+fn sandbox_serde() -> Result<(), serde_json::Error> {
+    let sandbox: Sandbox = serde_json::from_str(
+        r#"{
+            "rules": { "read_only": ["/usr"] },
+            "limits": { "max_memory": "512MiB", "max_threads": 8 }
+        }"#,
+    )?;
+    assert_eq!(
+        sandbox,
+        Sandbox::new(
+            Rules::new().add_read_only("/usr").into(),
+            ResourceLimits::new()
+                .max_memory(MemorySize::from_mib(512))
+                .max_threads(8),
+        )
+    );
+
+    let json = serde_json::to_value(&sandbox)?;
+    assert_eq!(json["limits"]["max_memory"], 512 * 1024 * 1024);
+    assert_eq!(serde_json::from_value::<Sandbox>(json)?, sandbox);
+
+    let sandbox: Sandbox = serde_json::from_str(
+        r#"{
+            "limits": {
+                "max_memory": 1000,
+                "max_memory_hard": "1 KiB",
+                "max_file_size": "unlimited",
+                "max_pending_signals": 16,
+                "max_message_queue_bytes": "8KiB",
+                "max_locked_memory": 0,
+                "min_nice": 19
+            }
+        }"#,
+    )?;
+    assert_eq!(
+        *sandbox.limits(),
+        ResourceLimits::new()
+            .max_memory_soft_hard(1000, MemorySize::from_kib(1))
+            .max_file_size(LimitValue::Unlimited)
+            .max_pending_signals(16)
+            .max_message_queue_bytes(MemorySize::from_kib(8))
+            .max_locked_memory(0)
+            .min_nice(19)
+    );
+    let json = serde_json::to_value(&sandbox)?;
+    assert_eq!(json["limits"]["max_file_size"], "unlimited");
+    assert_eq!(serde_json::from_value::<Sandbox>(json)?, sandbox);
+
+    assert_eq!(serde_json::from_str::<Sandbox>("{}")?, Sandbox::default());
+    assert!(serde_json::from_str::<Sandbox>(r#"{ "limits": { "max_mem": 1 } }"#).is_err());
+    for limits in [
+        r#"{ "limits": { "max_threads": "512MiB" } }"#,
+        r#"{ "limits": { "max_cpu_time": "1GiB" } }"#,
+    ] {
+        assert!(serde_json::from_str::<Sandbox>(limits).is_err());
+    }
+    let sandbox: Sandbox = serde_json::from_str(r#"{ "limits": { "max_threads": "unlimited" } }"#)?;
+    assert_eq!(
+        *sandbox.limits(),
+        ResourceLimits::new().max_threads(LimitValue::Unlimited)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sandbox_serde_binary() -> Result<(), Box<dyn std::error::Error>> {
+    let sandbox = Sandbox::new(
+        Rules::new().add_read_only("/usr").into(),
+        ResourceLimits::new()
+            .max_memory(MemorySize::from_mib(512))
+            .max_file_size(LimitValue::Unlimited)
+            .max_threads(8)
+            .max_cpu_time(LimitValue::Unlimited),
+    );
+
+    let mut cbor = Vec::new();
+    ciborium::ser::into_writer(&sandbox, &mut cbor)?;
+    assert_eq!(ciborium::de::from_reader::<Sandbox, _>(&cbor[..])?, sandbox);
+
+    for size in [MemorySize::from_bytes(0), MemorySize::from_gib(3)] {
+        let mut cbor = Vec::new();
+        ciborium::ser::into_writer(&size, &mut cbor)?;
+        assert_eq!(ciborium::de::from_reader::<MemorySize, _>(&cbor[..])?, size);
+    }
+
+    Ok(())
+}
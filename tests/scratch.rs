@@ -0,0 +1,86 @@
+use std::{fs, os::unix::ffi::OsStringExt};
+
+use leucite::{MemorySize, ScratchDir};
+use tempdir::TempDir;
+
+#[test]
+fn scratch_reset_and_persist() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let scratch = ScratchDir::new(tempdir.path().join("scratch"))?;
+
+    fs::create_dir_all(scratch.path().join("target/debug"))?;
+    fs::write(scratch.path().join("target/debug/out"), "output")?;
+    fs::write(scratch.path().join("main.c"), "int main;")?;
+    std::os::unix::fs::symlink("main.c", scratch.path().join("link.c"))?;
+
+    let persisted = tempdir.path().join("persisted");
+    scratch.persist_to(&persisted)?;
+
+    scratch.reset()?;
+    assert!(scratch.path().is_dir());
+    assert_eq!(fs::read_dir(scratch.path())?.count(), 0);
+
+    assert_eq!(
+        fs::read_to_string(persisted.join("target/debug/out"))?,
+        "output"
+    );
+    assert_eq!(fs::read_to_string(persisted.join("main.c"))?, "int main;");
+    assert_eq!(
+        fs::read_link(persisted.join("link.c"))?.as_os_str(),
+        "main.c"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn scratch_persist_skips_special_files() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let scratch = ScratchDir::new(tempdir.path().join("scratch"))?;
+
+    fs::write(scratch.path().join("out"), "output")?;
+    let fifo = std::ffi::CString::new(scratch.path().join("fifo").into_os_string().into_vec())?;
+    // SAFETY: `fifo` is a valid, nul-terminated string
+    assert_eq!(unsafe { libc::mkfifo(fifo.as_ptr(), 0o644) }, 0);
+
+    // copying the FIFO would block until something writes to it
+    let persisted = tempdir.path().join("persisted");
+    scratch.persist_to(&persisted)?;
+    assert_eq!(fs::read_to_string(persisted.join("out"))?, "output");
+    assert!(!persisted.join("fifo").exists());
+
+    // the copy would contain itself
+    for dest in ["copy", "nested/copy", "."] {
+        let err = scratch.persist_to(scratch.path().join(dest)).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{dest}");
+    }
+    assert!(!scratch.path().join("nested").exists());
+
+    Ok(())
+}
+
+#[test]
+fn scratch_tmpfs() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let path = tempdir.path().join("scratch");
+
+    let scratch = match ScratchDir::tmpfs(&path, MemorySize::from_mib(1)) {
+        Ok(scratch) => scratch,
+        // mounting requires privileges that the test may not have
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    fs::write(scratch.path().join("file"), "data")?;
+    // the tmpfs is limited to 1MiB
+    assert!(fs::write(scratch.path().join("big"), vec![0; 2 * 1024 * 1024]).is_err());
+
+    scratch.reset()?;
+    assert_eq!(fs::read_dir(scratch.path())?.count(), 0);
+
+    drop(scratch);
+    // the tmpfs has been unmounted, so the directory is once again on the original filesystem
+    assert!(path.is_dir());
+
+    Ok(())
+}
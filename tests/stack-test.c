@@ -0,0 +1,12 @@
+#include <stdio.h>
+
+unsigned long recurse(unsigned long depth) {
+    volatile char frame[1024];
+    frame[0] = (char)depth;
+    return recurse(depth + 1) + frame[0];
+}
+
+int main(void) {
+    printf("%lu\n", recurse(0));
+    return 0;
+}
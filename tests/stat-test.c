@@ -0,0 +1,11 @@
+#include <stdio.h>
+#include <sys/stat.h>
+
+// Print whether each of the arguments exists
+int main(int argc, char **argv) {
+    struct stat st;
+    for (int i = 1; i < argc; i++) {
+        printf("%s: %s\n", argv[i], stat(argv[i], &st) == 0 ? "found" : "missing");
+    }
+    return 0;
+}
@@ -0,0 +1,139 @@
+use landlock::AccessFs;
+use leucite::{FsType, Rules};
+
+type Case = (fn(Rules) -> Rules, bool);
+
+/// A ceiling which allows reading the system, and writing to `/srv/tenant`
+fn ceiling() -> Rules {
+    Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/srv")
+        .add_read_write("/srv/tenant")
+        .add_write_only("/var/log/tenant")
+        .add_connect_port(443)
+        .add_connect_port(80)
+}
+
+#[test]
+fn path_matrix() {
+    let ceiling = ceiling();
+
+    #[rustfmt::skip]
+    let cases: &[Case] = &[
+        // equal grants
+        (|r| r.add_read_only("/usr"), true),
+        (|r| r.add_read_write("/srv/tenant"), true),
+        (|r| r.add_write_only("/var/log/tenant"), true),
+        // grants beneath an ancestor
+        (|r| r.add_read_only("/usr/lib"), true),
+        (|r| r.add_read_write("/srv/tenant/data"), true),
+        (|r| r.add_read_only("/srv/tenant/data"), true),
+        (|r| r.add_write_only("/srv/tenant/out"), true),
+        // rights are combined from several ancestors
+        (|r| r.add_read_write("/srv/tenant/a/b"), true),
+        // greater rights than the ceiling
+        (|r| r.add_read_write("/usr"), false),
+        (|r| r.add_read_write("/usr/lib"), false),
+        (|r| r.add_write_only("/usr/lib"), false),
+        (|r| r.add_read_only("/var/log/tenant"), false),
+        (|r| r.add_read_write("/var/log/tenant/x"), false),
+        // ancestors of a grant, or unrelated paths
+        (|r| r.add_read_only("/"), false),
+        (|r| r.add_read_write("/srv"), false),
+        (|r| r.add_read_only("/etc"), false),
+        // prefixes which are not components
+        (|r| r.add_read_only("/usrlocal"), false),
+        (|r| r.add_read_write("/srv/tenant2"), false),
+        // paths which are the same after normalising components
+        (|r| r.add_read_only("/usr/"), true),
+        (|r| r.add_read_write("/srv/./tenant//data"), true),
+        // parent components may escape the ancestor
+        (|r| r.add_read_only("/usr/../etc"), false),
+        (|r| r.add_read_write("/srv/tenant/../other"), false),
+        (|r| r.add_read_only("/usr/lib/.."), false),
+        // relative paths are never beneath absolute paths
+        (|r| r.add_read_only("usr"), false),
+        // every grant must be allowed
+        (|r| r.add_read_only("/usr").add_read_only("/etc"), false),
+        // no grants
+        (|r| r, true),
+    ];
+
+    for (i, (rules, expected)) in cases.iter().enumerate() {
+        let rules = rules(Rules::new());
+        assert_eq!(
+            rules.is_subset_of(&ceiling),
+            *expected,
+            "case {i}: {rules:?}"
+        );
+    }
+}
+
+#[test]
+fn access_overrides() {
+    let ceiling = Rules::new()
+        .add_read_only("/usr")
+        .read_access_bits(AccessFs::ReadFile | AccessFs::ReadDir);
+
+    // the default read access includes executing files, which the ceiling does not allow
+    assert!(!Rules::new().add_read_only("/usr").is_subset_of(&ceiling));
+    assert!(Rules::new()
+        .add_read_only("/usr")
+        .read_access_bits(AccessFs::ReadFile)
+        .is_subset_of(&ceiling));
+}
+
+#[test]
+fn port_matrix() {
+    let ceiling = ceiling();
+
+    assert!(Rules::new().add_connect_port(443).is_subset_of(&ceiling));
+    assert!(Rules::new()
+        .add_connect_port(80)
+        .add_connect_port(443)
+        .is_subset_of(&ceiling));
+    assert!(!Rules::new().add_connect_port(22).is_subset_of(&ceiling));
+    assert!(!Rules::new().client_only().is_subset_of(&ceiling));
+    assert!(!Rules::new().add_bind_port(8080).is_subset_of(&ceiling));
+    assert!(!Rules::new().server_only([8080]).is_subset_of(&ceiling));
+
+    let open = Rules::new().client_only();
+    assert!(Rules::new().client_only().is_subset_of(&open));
+    assert!(Rules::new().add_connect_port(22).is_subset_of(&open));
+    assert!(Rules::new().is_subset_of(&open));
+    // the ceiling does not allow binding to any port, not even the ephemeral port 0
+    assert!(!Rules::new().add_bind_port(0).is_subset_of(&open));
+
+    let server = Rules::new().server_only([8080, 8081]);
+    assert!(Rules::new().server_only([8080]).is_subset_of(&server));
+    assert!(!Rules::new().server_only([8082]).is_subset_of(&server));
+}
+
+#[test]
+fn denied_filesystems() {
+    let ceiling = Rules::new()
+        .add_read_only("/usr")
+        .deny_filesystem_types(FsType::NETWORK);
+
+    let rules = Rules::new().add_read_only("/usr");
+    assert!(!rules.is_subset_of(&ceiling));
+    assert!(rules
+        .clone()
+        .deny_filesystem_types(FsType::NETWORK)
+        .is_subset_of(&ceiling));
+    assert!(rules
+        .deny_filesystem_types(FsType::NETWORK)
+        .deny_filesystem_types(&[FsType::FUSE])
+        .is_subset_of(&ceiling));
+}
+
+#[test]
+fn audit_only() {
+    let rules = Rules::new().add_read_only("/usr");
+
+    assert!(!rules.clone().audit_only().is_subset_of(&ceiling()));
+    assert!(rules.is_subset_of(&ceiling().audit_only()));
+    assert!(Rules::new()
+        .add_read_only("/")
+        .is_subset_of(&Rules::new().audit_only()));
+}
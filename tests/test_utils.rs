@@ -0,0 +1,33 @@
+use std::{fs, io::ErrorKind, net::TcpStream};
+
+use leucite::{test_utils::run_restricted, ChildError, Rules};
+use tempdir::TempDir;
+
+#[test]
+fn run_restricted_closure() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let file = tempdir.path().join("file");
+    fs::write(&file, "hello")?;
+
+    let rules = Rules::new().add_read_only(tempdir.path());
+    assert!(run_restricted(&rules, || fs::read(&file).is_ok())?);
+    assert!(run_restricted(&rules, || fs::write(&file, "").is_err())?);
+    assert!(run_restricted(&rules, || fs::read("/etc/passwd").is_err())?);
+    assert!(!run_restricted(&rules, || fs::read("/etc/passwd").is_ok())?);
+    assert!(run_restricted(&rules, || TcpStream::connect(
+        "127.0.0.1:1"
+    )
+    .is_err_and(|e| e.kind() == ErrorKind::PermissionDenied))?);
+    // The parent is not restricted
+    assert_eq!(fs::read_to_string(&file)?, "hello");
+
+    let err = run_restricted(&rules, || panic!("in the child")).unwrap_err();
+    assert!(err.to_string().contains("panicked"), "{err}");
+
+    let rules = Rules::new().deny_filesystem_types(&[leucite::FsType::of(tempdir.path())?]);
+    let err = run_restricted(&rules.add_read_only(tempdir.path()), || true).unwrap_err();
+    let child = ChildError::of(&err).expect("the child reports its error");
+    assert_eq!(child.path(), Some(tempdir.path()));
+
+    Ok(())
+}
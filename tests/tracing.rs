@@ -0,0 +1,126 @@
+use std::{
+    fmt,
+    process::Command,
+    sync::{Arc, Mutex},
+};
+
+use leucite::{CommandExt, PermissivePolicy, Rules, SandboxProfile};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata, Subscriber,
+};
+
+/// A subscriber which records each event as its level and fields
+#[derive(Default, Clone)]
+struct Recorder(Arc<Mutex<Vec<(Level, String)>>>);
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0 += &format!("{}={value:?} ", field.name());
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+    fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+        let level = *event.metadata().level();
+        self.0.lock().unwrap().push((level, fields.0));
+    }
+    fn enter(&self, _: &span::Id) {}
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn restrict_events() -> Result<(), Box<dyn std::error::Error>> {
+    let recorder = Recorder::default();
+    let rules = Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/lib")
+        .add_bind_port(8080)
+        .allow_all_connect();
+
+    let status = tracing::subscriber::with_default(recorder.clone(), || {
+        Command::new("/usr/bin/true")
+            .restrict(Arc::new(rules))
+            .status()
+    })?;
+    assert!(status.success());
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(events.len(), 1, "{events:?}");
+    let (level, fields) = &events[0];
+    assert_eq!(*level, Level::DEBUG);
+    assert!(fields.contains("paths=2 "), "{fields}");
+    assert!(fields.contains("bind_ports=1 "), "{fields}");
+    // Any port may be used, so nothing is recorded
+    assert!(!fields.contains("connect_ports"), "{fields}");
+    assert!(fields.contains("message=restricting command"), "{fields}");
+
+    Ok(())
+}
+
+#[test]
+fn skipped_events() -> Result<(), Box<dyn std::error::Error>> {
+    let recorder = Recorder::default();
+    let rules = Rules::new()
+        .add_read_write("/")
+        .allow_all_network()
+        .on_fully_permissive(PermissivePolicy::Skip);
+
+    let status = tracing::subscriber::with_default(recorder.clone(), || {
+        Command::new("/usr/bin/true")
+            .restrict(Arc::new(rules))
+            .status()
+    })?;
+    assert!(status.success());
+
+    let events = recorder.0.lock().unwrap();
+    assert_eq!(events.len(), 2, "{events:?}");
+    let (level, fields) = &events[1];
+    assert_eq!(*level, Level::WARN);
+    assert!(fields.contains("grant full access"), "{fields}");
+
+    Ok(())
+}
+
+#[test]
+fn audited_events() -> Result<(), Box<dyn std::error::Error>> {
+    let recorder = Recorder::default();
+    let profile = SandboxProfile::new().restrict(Arc::new(Rules::new().add_read_only("/usr")));
+
+    tracing::subscriber::with_default(recorder.clone(), || {
+        let mut cmd = Command::new("/usr/bin/true");
+        let pending = cmd.sandbox_audited(&profile)?;
+        let mut child = cmd.spawn()?;
+        let audit = pending.finish(child.id())?;
+        child.wait()?;
+        Ok::<_, std::io::Error>(audit)
+    })?;
+
+    // the rules are only fully enforced if the kernel supports the requested ABI
+    let expected = match leucite::landlock_abi() {
+        Some(abi) if abi as i32 >= Rules::new().abi() as i32 => (Level::INFO, "FullyEnforced"),
+        Some(_) => (Level::WARN, "PartiallyEnforced"),
+        None => (Level::WARN, "NotEnforced"),
+    };
+    let events = recorder.0.lock().unwrap();
+    let (level, fields) = events.last().unwrap();
+    assert_eq!(*level, expected.0);
+    assert!(
+        fields.contains(&format!("enforcement=Some({})", expected.1)),
+        "{fields}"
+    );
+
+    Ok(())
+}
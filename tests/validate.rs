@@ -0,0 +1,108 @@
+use std::{os::unix::fs::PermissionsExt, process::Command as StdCommand};
+
+use landlock::AccessFs;
+use leucite::Rules;
+use tempdir::TempDir;
+
+fn system_rules() -> Rules {
+    Rules::new()
+        .add_read_only("/usr")
+        .add_read_only("/bin")
+        .add_read_only("/lib")
+        .add_read_only("/lib64")
+}
+
+#[test]
+fn system_paths() {
+    assert_eq!(
+        system_rules().validate_for_command(&StdCommand::new("sh")),
+        Ok(())
+    );
+    assert_eq!(
+        system_rules().validate_for_command(&StdCommand::new("/usr/bin/ls")),
+        Ok(())
+    );
+}
+
+#[test]
+fn missing_libraries() -> Result<(), Box<dyn std::error::Error>> {
+    let ls = std::fs::canonicalize("/usr/bin/ls")?;
+    let rules = Rules::new().add_read_only(&ls);
+
+    let err = rules
+        .validate_for_command(&StdCommand::new("ls"))
+        .unwrap_err();
+    assert_eq!(err.program.file_name(), ls.file_name());
+    assert!(!err.missing.iter().any(|(p, _)| *p == ls), "{err}");
+    assert!(
+        err.missing
+            .iter()
+            .any(|(p, a)| p.to_string_lossy().contains("libc.so") && *a == AccessFs::ReadFile),
+        "{err}"
+    );
+    assert!(
+        err.missing
+            .iter()
+            .any(|(p, a)| p.to_string_lossy().contains("ld-linux") && *a == AccessFs::Execute),
+        "{err}"
+    );
+
+    // granting the library closure is enough
+    let rules = rules.allow_shared_libs_of(&ls)?;
+    assert_eq!(rules.validate_for_command(&StdCommand::new(&ls)), Ok(()));
+
+    Ok(())
+}
+
+#[test]
+fn script_interpreter() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let script = tempdir.path().join("script");
+    std::fs::write(&script, "#!/bin/sh -e\necho hi\n")?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    let mut cmd = StdCommand::new("./script");
+    cmd.current_dir(tempdir.path());
+
+    let err = Rules::new()
+        .add_read_only(tempdir.path())
+        .validate_for_command(&cmd)
+        .unwrap_err();
+    let sh = std::fs::canonicalize("/bin/sh")?;
+    assert!(
+        err.missing.contains(&(sh, AccessFs::Execute.into())),
+        "{err}"
+    );
+
+    let rules = system_rules().add_read_only(tempdir.path());
+    assert_eq!(rules.validate_for_command(&cmd), Ok(()));
+
+    Ok(())
+}
+
+#[test]
+fn script_interpreter_loop() -> Result<(), Box<dyn std::error::Error>> {
+    let tempdir = TempDir::new("leucite")?;
+    let script = tempdir.path().canonicalize()?.join("script");
+    std::fs::write(&script, format!("#!{}\n", script.display()))?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+
+    let cmd = StdCommand::new(&script);
+    let err = Rules::new().validate_for_command(&cmd).unwrap_err();
+    assert_eq!(err.missing, [(script, AccessFs::Execute.into())]);
+
+    let rules = Rules::new().add_read_only(tempdir.path());
+    assert_eq!(rules.validate_for_command(&cmd), Ok(()));
+
+    Ok(())
+}
+
+#[test]
+fn program_not_found() {
+    let mut cmd = StdCommand::new("ls");
+    cmd.env("PATH", "/does/not/exist");
+
+    let err = system_rules().validate_for_command(&cmd).unwrap_err();
+    assert_eq!(err.program.as_os_str(), "ls");
+    assert_eq!(err.missing, [("ls".into(), AccessFs::Execute.into())]);
+}